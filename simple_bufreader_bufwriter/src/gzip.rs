@@ -0,0 +1,83 @@
+/*
+    透明解压gzip数据的读取适配器，需要开启gzip feature
+    gzip头部、DEFLATE数据以及尾部的CRC32/长度校验都交给flate2处理
+*/
+
+use std::io;
+use std::io::Read;
+
+use flate2::read::GzDecoder;
+
+use crate::BufReader;
+
+pub struct GzipReader<R: Read> {
+    decoder: GzDecoder<BufReader<R>>,
+}
+
+impl<R: Read> BufReader<R> {
+    /// 把BufReader包装为解压读取器，读出的是解压后的原始字节
+    pub fn gzip(self) -> GzipReader<R> {
+        GzipReader {
+            decoder: GzDecoder::new(self),
+        }
+    }
+}
+
+impl<R: Read> GzipReader<R> {
+    pub fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.decoder.read(buf)
+    }
+}
+
+impl<R: Read> Read for GzipReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BufReader;
+    use simple_file::temp::TempFile;
+    use simple_file::{File, OpenMode};
+    use std::io::{self, Read};
+
+    // gzip.compress(b"Hello, gzip!\nHello, gzip!\n", mtime=0)
+    const GZIP_PAYLOAD: [u8; 36] = [
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xf3, 0x48, 0xcd, 0xc9, 0xc9,
+        0xd7, 0x51, 0x48, 0xaf, 0xca, 0x2c, 0x50, 0xe4, 0xf2, 0x40, 0xe6, 0x00, 0x00, 0x04, 0x36,
+        0x24, 0x03, 0x1a, 0x00, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn test_gzip_decompress() -> io::Result<()> {
+        let mut temp_file = TempFile::new()?;
+        temp_file.file_mut().write_all(&GZIP_PAYLOAD)?;
+
+        let file = File::open(temp_file.path(), OpenMode::Read)?;
+        let mut reader = BufReader::new(file).gzip();
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content)?;
+        assert_eq!(content, b"Hello, gzip!\nHello, gzip!\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_gzip_corrupted_trailer() -> io::Result<()> {
+        let mut payload = GZIP_PAYLOAD;
+        payload[30] ^= 0xff; // 破坏尾部的CRC32
+        let mut temp_file = TempFile::new()?;
+        temp_file.file_mut().write_all(&payload)?;
+
+        let file = File::open(temp_file.path(), OpenMode::Read)?;
+        let mut reader = BufReader::new(file).gzip();
+        let mut content = Vec::new();
+        assert!(
+            reader.read_to_end(&mut content).is_err(),
+            "Corrupted trailer should fail"
+        );
+
+        Ok(())
+    }
+}
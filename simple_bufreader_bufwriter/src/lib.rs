@@ -2,29 +2,66 @@
     实现一个简单的BufReader and BufWriter
 */
 
+use std::fmt;
 use std::io;
-use std::usize;
+use std::io::{Read, Seek, SeekFrom, Write};
 
 use simple_file::File;
 
-#[allow(dead_code)]
-pub struct BufReader {
-    file: File,
+#[cfg(feature = "gzip")]
+mod gzip;
+
+#[cfg(feature = "gzip")]
+pub use gzip::GzipReader;
+
+/*
+    BufReader可以包装任何实现了Read的类型，例如本crate的File、管道、socket或std中的类型
+    只有File才有的能力(例如at_eof)放在单独的impl BufReader<File>中
+*/
+pub struct BufReader<R: Read> {
+    inner: R,
     buffer: Vec<u8>,
     pos: usize,
     capacity: usize,
+    buffer_size: usize, // 创建时指定的缓冲区大小，unget可能临时把缓冲区扩大到超过它
 }
 
-impl BufReader {
-    pub fn new(file: File) -> BufReader {
-        const BUFFER_SIZE: usize = 4096; // 4KB 缓冲区
+const DEFAULT_BUFFER_SIZE: usize = 4096; // 4KB 缓冲区
+const MAX_VARINT_LEN: usize = 10; // u64的LEB128编码最多10个字节
+
+impl<R: Read> BufReader<R> {
+    pub fn new(inner: R) -> BufReader<R> {
+        BufReader::with_capacity(DEFAULT_BUFFER_SIZE, inner)
+    }
 
+    /// 指定缓冲区大小，至少为1个字节，否则无法与EOF区分
+    pub fn with_capacity(capacity: usize, inner: R) -> BufReader<R> {
+        let buffer_size = capacity.max(1);
         BufReader {
-            file,
-            buffer: vec![0; BUFFER_SIZE],
+            inner,
+            buffer: vec![0; buffer_size],
             pos: 0,
             capacity: 0,
+            buffer_size,
+        }
+    }
+
+    /*
+        缓冲区已消费完时从底层reader重新填充，返回缓冲区中可用的字节数，0表示EOF
+        unget扩大过的缓冲区此时已经没有数据，缩回创建时的大小并释放多余的内存
+    */
+    fn fill_buf(&mut self) -> io::Result<usize> {
+        if self.pos >= self.capacity {
+            self.pos = 0;
+            self.capacity = 0; // 读取失败时不能留下旧数据
+            if self.buffer.len() > self.buffer_size {
+                self.buffer.truncate(self.buffer_size);
+                self.buffer.shrink_to_fit();
+            }
+            self.capacity = self.inner.read(&mut self.buffer)?;
         }
+
+        Ok(self.capacity - self.pos)
     }
 
     pub fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
@@ -34,12 +71,8 @@ impl BufReader {
 
         let mut total_read = 0;
         while total_read < buf.len() {
-            if self.pos >= self.capacity {
-                self.pos = 0;
-                self.capacity = self.file.read(&mut self.buffer)?;
-                if self.capacity == 0 {
-                    return Ok(total_read);
-                }
+            if self.fill_buf()? == 0 {
+                return Ok(total_read);
             }
 
             let to_copy = std::cmp::min(self.capacity - self.pos, buf.len() - total_read);
@@ -58,28 +91,22 @@ impl BufReader {
         let mut total_read = 0;
 
         loop {
-            if self.pos >= self.capacity {
-                self.pos = 0;
-                self.capacity = self.file.read(&mut self.buffer)?;
-                if self.capacity == 0 {
-                    return Ok(total_read);
-                }
+            if self.fill_buf()? == 0 {
+                return Ok(total_read);
             }
 
             // 查找换行符
-            let start = self.pos;
             let end = self.buffer[self.pos..self.capacity]
                 .iter()
                 .position(|&b| b == b'\n')
                 .map(|i| self.pos + i + 1)
                 .unwrap_or(self.capacity);
             let slice = &self.buffer[self.pos..end];
-            buf.push_str(std::str::from_utf8(slice).map_err(|_| {
-                Err(io::Error::new(
-                    io::ErrorKind::InvalidData,
-                    "Invalid UTF-8 data",
-                ));
-            })?);
+            buf.push_str(
+                std::str::from_utf8(slice).map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidData, "Invalid UTF-8 data")
+                })?,
+            );
             total_read += end - self.pos;
             self.pos = end;
 
@@ -88,12 +115,912 @@ impl BufReader {
             }
         }
     }
+
+    /*
+        与read_line相同，但不校验UTF-8，非法的字节序列替换为U+FFFD，语义同String::from_utf8_lossy
+        整行读完后再统一转换，跨缓冲区边界的多字节字符不会被误判为非法
+        返回值为读取的原始字节数
+    */
+    pub fn read_line_lossy(&mut self, buf: &mut String) -> io::Result<usize> {
+        buf.clear();
+
+        let mut line = Vec::new();
+        loop {
+            if self.fill_buf()? == 0 {
+                break;
+            }
+
+            let available = &self.buffer[self.pos..self.capacity];
+            let (to_copy, found) = match available.iter().position(|&b| b == b'\n') {
+                Some(i) => (i + 1, true),
+                None => (available.len(), false),
+            };
+            line.extend_from_slice(&available[..to_copy]);
+            self.pos += to_copy;
+
+            if found {
+                break;
+            }
+        }
+
+        buf.push_str(&String::from_utf8_lossy(&line));
+        Ok(line.len())
+    }
+
+    /*
+        与read_line相同，但一行最多读取max个字节(包括换行符)
+        超过max个字节仍没有遇到换行符时停止读取并返回InvalidData，buf保持为空，
+        此时恰好消费了max个字节，防止一个没有换行符的超大文件把内存耗尽
+        最后一行没有换行符、恰好max个字节并且之后就是EOF时不算超出，正常返回
+    */
+    pub fn read_line_limited(&mut self, buf: &mut String, max: usize) -> io::Result<usize> {
+        buf.clear();
+
+        let mut line = Vec::new();
+        loop {
+            if self.fill_buf()? == 0 {
+                break;
+            }
+
+            let available = &self.buffer[self.pos..self.capacity];
+            let remaining = max - line.len();
+            let (to_copy, found) = match available.iter().position(|&b| b == b'\n') {
+                Some(i) if i < remaining => (i + 1, true),
+                _ => (std::cmp::min(available.len(), remaining), false),
+            };
+            line.extend_from_slice(&available[..to_copy]);
+            self.pos += to_copy;
+
+            if found {
+                break;
+            }
+            // 只有后面确实还有数据时才算超出
+            if line.len() >= max && self.fill_buf()? > 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Line exceeds the length limit",
+                ));
+            }
+        }
+
+        buf.push_str(
+            std::str::from_utf8(&line)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Invalid UTF-8 data"))?,
+        );
+        Ok(line.len())
+    }
+
+    /// 取回内部的reader，缓冲区中尚未消费的数据被丢弃
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// 查看下一个字节但不消费，EOF时返回None
+    pub fn peek(&mut self) -> io::Result<Option<u8>> {
+        if self.fill_buf()? == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(self.buffer[self.pos]))
+    }
+
+    /*
+        把已读出的字节退回，之后的read/peek等会先按顺序返回这些字节，再继续返回缓冲区和文件中的数据
+        退回的字节直接放在缓冲区未消费部分的前面：
+        1. pos前面的空间足够时原地拷贝
+        2. 否则重新组织缓冲区，缓冲区可以超过初始大小，因此退回的字节数没有上限，这些字节被读完后缓冲区缩回初始大小
+    */
+    pub fn unget(&mut self, bytes: &[u8]) {
+        if bytes.len() <= self.pos {
+            self.pos -= bytes.len();
+            self.buffer[self.pos..self.pos + bytes.len()].copy_from_slice(bytes);
+            return;
+        }
+
+        let buffer_size = self.buffer.len();
+        let mut buffer = Vec::with_capacity(bytes.len() + self.capacity - self.pos);
+        buffer.extend_from_slice(bytes);
+        buffer.extend_from_slice(&self.buffer[self.pos..self.capacity]);
+
+        self.pos = 0;
+        self.capacity = buffer.len();
+        if buffer.len() < buffer_size {
+            buffer.resize(buffer_size, 0);
+        }
+        self.buffer = buffer;
+    }
+
+    /*
+        恰好读取N个字节，用于读取二进制格式中定长的头部
+        let header: [u8; 16] = reader.read_array()?;
+        不足N个字节就到达EOF时返回UnexpectedEof
+    */
+    pub fn read_array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let mut array = [0u8; N];
+
+        let mut filled = 0;
+        while filled < N {
+            let available = self.fill_buf()?;
+            if available == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Not enough bytes for array",
+                ));
+            }
+
+            let to_copy = std::cmp::min(available, N - filled);
+            array[filled..filled + to_copy]
+                .copy_from_slice(&self.buffer[self.pos..self.pos + to_copy]);
+            self.pos += to_copy;
+            filled += to_copy;
+        }
+
+        Ok(array)
+    }
+
+    /*
+        读取一个LEB128编码的无符号varint (protobuf格式)
+        每个字节低7位为数据，最高位为1表示后面还有字节，低位组在前
+        u64最多需要10个字节，第10个字节只能提供最高的1位
+    */
+    pub fn read_varint_u64(&mut self) -> io::Result<u64> {
+        let mut value = 0u64;
+
+        for i in 0..MAX_VARINT_LEN {
+            if self.fill_buf()? == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Truncated varint",
+                ));
+            }
+
+            let byte = self.buffer[self.pos];
+            self.pos += 1;
+
+            if i == MAX_VARINT_LEN - 1 && byte > 1 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Varint overflows u64",
+                ));
+            }
+
+            value |= ((byte & 0x7f) as u64) << (7 * i);
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Varint is too long",
+        ))
+    }
 }
 
-#[allow(dead_code)]
-pub struct BufWriter {
-    file: File,
-    buffer: Vec<u8>,
-    pos: usize,
+impl<R: Read + Seek> BufReader<R> {
+    /*
+        取回内部的reader，并把它的读写位置回退缓冲区中尚未消费的字节数，
+        使它的读写位置与调用方实际消费到的位置一致，之后直接读取不会跳过数据
+        注意通过unget退回的字节也按已缓冲的数据计算
+        seek失败时(例如管道)BufReader连同缓冲区中的数据一起放在错误中返回，不会丢失
+    */
+    pub fn into_inner_rewound(mut self) -> Result<R, IntoInnerError<BufReader<R>>> {
+        let unconsumed = (self.capacity - self.pos) as i64;
+        if unconsumed > 0
+            && let Err(e) = self.inner.seek(SeekFrom::Current(-unconsumed))
+        {
+            return Err(IntoInnerError(self, e));
+        }
+
+        Ok(self.inner)
+    }
+}
+
+/// into_inner_rewound失败时返回的错误，与std::io::IntoInnerError相同，带着原来的对象和错误
+pub struct IntoInnerError<T>(T, io::Error);
+
+impl<T> IntoInnerError<T> {
+    pub fn error(&self) -> &io::Error {
+        &self.1
+    }
+
+    /// 取回原来的对象，可以继续使用或改用into_inner
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+
+    pub fn into_parts(self) -> (io::Error, T) {
+        (self.1, self.0)
+    }
+}
+
+impl<T> From<IntoInnerError<T>> for io::Error {
+    fn from(err: IntoInnerError<T>) -> io::Error {
+        err.1
+    }
+}
+
+impl<T> fmt::Debug for IntoInnerError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.1.fmt(f)
+    }
+}
+
+impl<T> fmt::Display for IntoInnerError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.1.fmt(f)
+    }
+}
+
+impl<T> std::error::Error for IntoInnerError<T> {}
+
+impl BufReader<File> {
+    /// 是否已到达文件末尾，缓冲区中还有未消费的字节时不算EOF
+    pub fn at_eof(&mut self) -> io::Result<bool> {
+        if self.pos < self.capacity {
+            return Ok(false);
+        }
+
+        Ok(self.inner.at_eof()?)
+    }
+}
+
+/*
+    对整个流的总读取量设置上限，用于解析不可信的输入
+    与take不同，超出上限时不是静默地返回EOF，而是返回QuotaExceeded错误，
+    恰好读到上限且数据也恰好结束时仍然正常返回EOF
+*/
+pub struct LimitedReader<R: Read> {
+    inner: BufReader<R>,
+    remaining: u64,
+}
+
+impl<R: Read> BufReader<R> {
+    pub fn limited(self, max_total: u64) -> LimitedReader<R> {
+        LimitedReader {
+            inner: self,
+            remaining: max_total,
+        }
+    }
+}
+
+impl<R: Read> LimitedReader<R> {
+    pub fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        // 额度已用完，只有确实还有数据时才算超额
+        if self.remaining == 0 {
+            if self.inner.peek()?.is_some() {
+                return Err(io::Error::new(
+                    io::ErrorKind::QuotaExceeded,
+                    "Read limit exceeded",
+                ));
+            }
+            return Ok(0);
+        }
+
+        let max = std::cmp::min(buf.len() as u64, self.remaining) as usize;
+        let n = self.inner.read(&mut buf[..max])?;
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+
+    /// 剩余可读取的字节数
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    pub fn into_inner(self) -> BufReader<R> {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read(buf)
+    }
+}
+
+impl<R: Read> Read for BufReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read(buf)
+    }
+}
+
+/*
+    与BufReader对称，BufWriter可以包装任何实现了Write的类型，例如File、管道、socket或内存中的Vec<u8>
+*/
+pub struct BufWriter<W: Write> {
+    inner: W,
+    buffer: Vec<u8>, // 尚未写入底层writer的数据
     capacity: usize,
+    retry_interrupted: bool, // flush时遇到EINTR是否自动重试
+}
+
+impl<W: Write> BufWriter<W> {
+    pub fn new(inner: W) -> BufWriter<W> {
+        BufWriter::with_capacity(DEFAULT_BUFFER_SIZE, inner)
+    }
+
+    pub fn with_capacity(capacity: usize, inner: W) -> BufWriter<W> {
+        BufWriter {
+            inner,
+            buffer: Vec::with_capacity(capacity),
+            capacity,
+            retry_interrupted: true,
+        }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// 直接向底层writer写入会绕过缓冲区，写入顺序可能与预期不符，需要先flush
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// flush时写入被信号打断(EINTR)默认自动重试，设置为false后把Interrupted错误原样返回给调用方
+    pub fn set_retry_interrupted(&mut self, retry: bool) {
+        self.retry_interrupted = retry;
+    }
+
+    /*
+        缓冲区放不下时先flush，数据比整个缓冲区还大时直接写入底层writer，不再经过缓冲区
+    */
+    pub fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.buffer.len() + buf.len() > self.capacity {
+            self.flush_buf()?;
+        }
+        if buf.len() >= self.capacity {
+            return write_retrying(&mut self.inner, buf, self.retry_interrupted);
+        }
+
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    /// 把缓冲区中的数据全部写入底层writer
+    ///
+    /// 写入中途出错时，已写入的部分会从缓冲区移除，缓冲区中恰好剩下尚未写入的字节，
+    /// 再次调用flush只会重试这部分，不会重复写入，也不会丢失数据
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.flush_buf()?;
+        self.inner.flush()
+    }
+
+    fn flush_buf(&mut self) -> io::Result<()> {
+        drain_buffer(&mut self.inner, &mut self.buffer, self.retry_interrupted)
+    }
+}
+
+impl BufWriter<File> {
+    /*
+        flush后再fsync，保证数据落盘，替代常见的 flush -> sync_all 两步写法
+        flush失败时不再fsync，直接返回flush的错误
+    */
+    pub fn commit(&mut self) -> io::Result<()> {
+        self.flush()?;
+        Ok(self.inner.sync_all()?)
+    }
+}
+
+// 把buffer全部写入writer，无论成功与否，已写入的前缀都会从buffer中移除
+// retry_interrupted为true时EINTR只是重试，不算错误
+fn drain_buffer<W: Write>(
+    writer: &mut W,
+    buffer: &mut Vec<u8>,
+    retry_interrupted: bool,
+) -> io::Result<()> {
+    let mut written = 0;
+    let result = loop {
+        if written >= buffer.len() {
+            break Ok(());
+        }
+
+        match write_retrying(writer, &buffer[written..], retry_interrupted) {
+            Ok(0) => {
+                break Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "Failed to write the buffered data",
+                ));
+            }
+            Ok(n) => written += n,
+            Err(e) => break Err(e),
+        }
+    };
+
+    buffer.drain(..written);
+    result
+}
+
+// 调用一次write，retry_interrupted为true时EINTR只是重试
+fn write_retrying<W: Write>(
+    writer: &mut W,
+    buf: &[u8],
+    retry_interrupted: bool,
+) -> io::Result<usize> {
+    loop {
+        match writer.write(buf) {
+            Err(e) if retry_interrupted && e.kind() == io::ErrorKind::Interrupted => {}
+            result => return result,
+        }
+    }
+}
+
+impl<W: Write> Write for BufWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush()
+    }
+}
+
+/*
+    Drop时把剩余数据写入底层writer，Drop无法返回错误，需要感知写入错误时应当先手动flush
+*/
+impl<W: Write> Drop for BufWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.flush_buf();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BufReader, BufWriter, drain_buffer};
+    use simple_file::temp::TempFile;
+    use simple_file::{File, OpenMode};
+    use std::io::{self, Cursor, Read, Write};
+
+    #[test]
+    fn test_at_eof() -> io::Result<()> {
+        let mut temp_file = TempFile::new()?;
+        temp_file.file_mut().write_all(b"Hello, world!")?;
+
+        let file = File::open(temp_file.path(), OpenMode::Read)?;
+        let mut reader = BufReader::new(file);
+        assert!(
+            !reader.at_eof()?,
+            "Freshly opened reader should not be at EOF"
+        );
+
+        // 第一次读取会把整个文件读入缓冲区，底层文件已到末尾，但缓冲区里还有剩余
+        let mut buf = [0u8; 5];
+        reader.read(&mut buf)?;
+        assert!(
+            !reader.at_eof()?,
+            "Reader with buffered bytes should not be at EOF"
+        );
+
+        let mut rest = [0u8; 128];
+        let n = reader.read(&mut rest)?;
+        assert_eq!(&rest[..n], b", world!");
+        assert!(
+            reader.at_eof()?,
+            "Reader should be at EOF after reading all"
+        );
+
+        Ok(())
+    }
+
+    // 把bytes写入临时文件并返回对应的BufReader，临时文件需要在测试期间保持存活
+    fn reader_over(bytes: &[u8]) -> io::Result<(TempFile, BufReader<File>)> {
+        let mut temp_file = TempFile::new()?;
+        temp_file.file_mut().write_all(bytes)?;
+        let file = File::open(temp_file.path(), OpenMode::Read)?;
+
+        Ok((temp_file, BufReader::new(file)))
+    }
+
+    #[test]
+    fn test_read_varint() -> io::Result<()> {
+        let mut bytes = vec![0x05, 0xac, 0x02];
+        bytes.extend_from_slice(&[0xff; 9]);
+        bytes.push(0x01);
+        let (_temp_file, mut reader) = reader_over(&bytes)?;
+
+        assert_eq!(reader.read_varint_u64()?, 5, "1-byte varint");
+        assert_eq!(reader.read_varint_u64()?, 300, "2-byte varint");
+        assert_eq!(reader.read_varint_u64()?, u64::MAX, "10-byte varint");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_varint_truncated() -> io::Result<()> {
+        let (_temp_file, mut reader) = reader_over(&[0x80, 0x80])?;
+        let err = reader.read_varint_u64().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_varint_overlong() -> io::Result<()> {
+        let (_temp_file, mut reader) = reader_over(&[0x80; 11])?;
+        let err = reader.read_varint_u64().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unget() -> io::Result<()> {
+        let (_temp_file, mut reader) = reader_over(b"abcdef")?;
+
+        let mut buf = [0u8; 3];
+        reader.read(&mut buf)?;
+        assert_eq!(&buf, b"abc");
+
+        // 退回已读的字节，下一次读取先返回它们，再继续读文件
+        reader.unget(b"bc");
+        assert_eq!(reader.peek()?, Some(b'b'));
+        let mut rest = [0u8; 16];
+        let n = reader.read(&mut rest)?;
+        assert_eq!(&rest[..n], b"bcdef");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unget_beyond_buffer() -> io::Result<()> {
+        let (_temp_file, mut reader) = reader_over(b"xyz")?;
+
+        // 退回的字节比已消费的多，也比缓冲区大
+        let pushed = vec![b'-'; 10_000];
+        reader.unget(&pushed);
+        reader.unget(b"<<");
+
+        let mut rest = Vec::new();
+        let mut buf = [0u8; 512];
+        loop {
+            let n = reader.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            rest.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(&rest[..2], b"<<");
+        assert_eq!(&rest[2..10_002], &pushed[..]);
+        assert_eq!(&rest[10_002..], b"xyz");
+
+        // 退回的字节读完后不再占用扩大的缓冲区
+        assert_eq!(reader.buffer.len(), super::DEFAULT_BUFFER_SIZE);
+        assert!(
+            reader.buffer.capacity() < 10_000,
+            "Grown buffer should be freed"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_line_limited() -> io::Result<()> {
+        let (_temp_file, mut reader) = reader_over(b"first line\nsecond")?;
+
+        let mut line = String::new();
+        assert_eq!(reader.read_line_limited(&mut line, 1024)?, 11);
+        assert_eq!(line, "first line\n");
+        assert_eq!(reader.read_line_limited(&mut line, 1024)?, 6);
+        assert_eq!(line, "second");
+        assert_eq!(reader.read_line_limited(&mut line, 1024)?, 0);
+
+        // 最后一行恰好max个字节、没有换行符
+        let (_temp_file, mut reader) = reader_over(b"12345\nabcde")?;
+        assert_eq!(reader.read_line_limited(&mut line, 6)?, 6);
+        assert_eq!(reader.read_line_limited(&mut line, 5)?, 5);
+        assert_eq!(line, "abcde");
+        assert_eq!(reader.read_line_limited(&mut line, 5)?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_line_limited_without_newline() -> io::Result<()> {
+        let (_temp_file, mut reader) = reader_over(&vec![b'a'; 1024 * 1024])?;
+
+        let mut line = String::new();
+        let err = reader.read_line_limited(&mut line, 1024).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(line.is_empty(), "Line should not be filled on error");
+
+        // 恰好消费了max个字节，剩余数据还能继续读
+        let mut rest = Vec::new();
+        let mut buf = [0u8; 8192];
+        loop {
+            let n = reader.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            rest.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(rest.len(), 1024 * 1024 - 1024);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_line_lossy() -> io::Result<()> {
+        let (_temp_file, mut reader) = reader_over(b"bad \xff byte\n\xe4\xb8\x96\xe7\x95\x8c\n")?;
+
+        let mut line = String::new();
+        assert_eq!(reader.read_line_lossy(&mut line)?, 11);
+        assert_eq!(
+            line, "bad \u{fffd} byte\n",
+            "Invalid byte should be replaced"
+        );
+        assert_eq!(reader.read_line_lossy(&mut line)?, 7);
+        assert_eq!(line, "世界\n", "Valid UTF-8 should be kept");
+        assert_eq!(reader.read_line_lossy(&mut line)?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_array() -> io::Result<()> {
+        let (_temp_file, mut reader) = reader_over(b"\x7fELFrest")?;
+
+        let magic: [u8; 4] = reader.read_array()?;
+        assert_eq!(&magic, b"\x7fELF");
+        let empty: [u8; 0] = reader.read_array()?;
+        assert_eq!(empty, [0u8; 0]);
+
+        let result = reader.read_array::<8>();
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_buf_writer() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let file = File::open(temp_file.path(), OpenMode::Write)?;
+        let mut writer = BufWriter::new(file);
+
+        // 多次小写入跨越缓冲区边界
+        let content: Vec<u8> = (0..=255u8).cycle().take(10_000).collect();
+        for chunk in content.chunks(100) {
+            writer.write_all(chunk)?;
+        }
+        writer.flush()?;
+        assert_eq!(std::fs::read(temp_file.path())?, content);
+
+        // Drop时写入剩余数据
+        writer.write_all(b"tail")?;
+        drop(writer);
+        assert_eq!(&std::fs::read(temp_file.path())?[10_000..], b"tail");
+
+        Ok(())
+    }
+
+    // 写入budget个字节后开始失败的writer
+    struct FailingWriter {
+        out: Vec<u8>,
+        budget: usize,
+    }
+
+    impl Write for FailingWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.budget == 0 {
+                return Err(io::Error::other("Writer is full"));
+            }
+
+            let n = std::cmp::min(self.budget, buf.len());
+            self.out.extend_from_slice(&buf[..n]);
+            self.budget -= n;
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_flush_preserves_remainder_on_error() {
+        let content: Vec<u8> = (0..100u8).collect();
+        let mut writer = BufWriter::with_capacity(
+            128,
+            FailingWriter {
+                out: Vec::new(),
+                budget: 30,
+            },
+        );
+        assert_eq!(writer.write(&content).unwrap(), 100);
+        assert!(writer.get_ref().out.is_empty(), "Data should stay buffered");
+
+        // 写入30个字节后失败，缓冲区中恰好剩下未写入的70个字节
+        assert!(writer.flush().is_err());
+        assert_eq!(writer.get_ref().out, &content[..30]);
+        assert_eq!(writer.buffer, &content[30..]);
+
+        // 重试只写入剩余部分，既不重复也不丢失
+        writer.get_mut().budget = usize::MAX;
+        writer.flush().unwrap();
+        assert!(writer.buffer.is_empty());
+        assert_eq!(writer.get_ref().out, content);
+    }
+
+    #[test]
+    fn test_limited_at_limit() -> io::Result<()> {
+        let (_temp_file, reader) = reader_over(&[b'x'; 100])?;
+        let mut limited = reader.limited(100);
+
+        let mut content = Vec::new();
+        limited.read_to_end(&mut content)?;
+        assert_eq!(
+            content.len(),
+            100,
+            "Reading exactly the limit should succeed"
+        );
+        assert_eq!(limited.remaining(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_limited_past_limit() -> io::Result<()> {
+        let (_temp_file, reader) = reader_over(&[b'x'; 101])?;
+        let mut limited = reader.limited(100);
+
+        let mut content = Vec::new();
+        let err = limited.read_to_end(&mut content).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::QuotaExceeded);
+        assert_eq!(content.len(), 100, "Bytes within the limit are delivered");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_into_inner_rewound() -> io::Result<()> {
+        let content: Vec<u8> = (0..=255u8).cycle().take(10_000).collect();
+        let (_temp_file, mut reader) = reader_over(&content)?;
+
+        // 读取10个字节时缓冲区预读了4KB
+        let mut buf = [0u8; 10];
+        reader.read(&mut buf)?;
+
+        let mut file = reader.into_inner_rewound()?;
+        let mut next = [0u8; 1];
+        file.read(&mut next)?;
+        assert_eq!(next[0], content[10], "Should read the exact next byte");
+
+        // 管道不能seek，失败时取回BufReader，缓冲区中的数据还在
+        let (pipe_reader, mut pipe_writer) = simple_file::pipe()?;
+        pipe_writer.write_all(b"Hello, pipe!")?;
+        drop(pipe_writer);
+        let mut reader = BufReader::new(pipe_reader.into_file());
+        reader.read(&mut buf[..5])?;
+
+        let result = reader.into_inner_rewound();
+        assert!(result.is_err(), "Seeking a pipe should fail");
+        if let Err(e) = result {
+            assert_eq!(e.error().kind(), io::ErrorKind::NotSeekable);
+            let mut reader = e.into_inner();
+            let mut rest = String::new();
+            reader.read_to_string(&mut rest)?;
+            assert_eq!(rest, ", pipe!", "Buffered data should be kept");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_buf_writer_commit() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let file = File::open(temp_file.path(), OpenMode::Write)?;
+        let mut writer = BufWriter::new(file);
+
+        writer.write_all(b"durable record\n")?;
+        writer.commit()?;
+        assert_eq!(std::fs::read(temp_file.path())?, b"durable record\n");
+
+        Ok(())
+    }
+
+    // 每写入一次就被信号打断一次的writer，模拟EINTR
+    struct InterruptedWriter {
+        out: Vec<u8>,
+        interrupt_next: bool,
+    }
+
+    impl Write for InterruptedWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.interrupt_next = !self.interrupt_next;
+            if !self.interrupt_next {
+                return Err(io::Error::from(io::ErrorKind::Interrupted));
+            }
+
+            let n = std::cmp::min(10, buf.len());
+            self.out.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_drain_retries_interrupted() {
+        let content: Vec<u8> = (0..100u8).collect();
+
+        // 默认策略：EINTR被重试，flush照常完成
+        let mut buffer = content.clone();
+        let mut writer = InterruptedWriter {
+            out: Vec::new(),
+            interrupt_next: false,
+        };
+        drain_buffer(&mut writer, &mut buffer, true).unwrap();
+        assert!(buffer.is_empty());
+        assert_eq!(writer.out, content);
+
+        // 关闭重试：Interrupted原样返回，未写入的部分留在缓冲区
+        let mut buffer = content.clone();
+        let mut writer = InterruptedWriter {
+            out: Vec::new(),
+            interrupt_next: false,
+        };
+        let err = drain_buffer(&mut writer, &mut buffer, false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+        assert_eq!(buffer, &content[10..]);
+    }
+
+    #[test]
+    fn test_large_write_retries_interrupted() {
+        let content: Vec<u8> = (0..100u8).collect();
+
+        // 超过缓冲区大小的数据直接写入底层writer，同样重试EINTR
+        let mut writer = BufWriter::with_capacity(
+            16,
+            InterruptedWriter {
+                out: Vec::new(),
+                interrupt_next: true,
+            },
+        );
+        assert_eq!(writer.write(&content).unwrap(), 10);
+        assert_eq!(writer.get_ref().out, &content[..10]);
+
+        writer.set_retry_interrupted(false);
+        let err = writer.write(&content[10..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+    }
+
+    #[test]
+    fn test_generic_over_cursor() -> io::Result<()> {
+        let cursor = Cursor::new(b"first\nsecond\nthird".to_vec());
+        let mut reader = BufReader::with_capacity(4, cursor);
+
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        assert_eq!(line, "first\n");
+        reader.read_line(&mut line)?;
+        assert_eq!(line, "second\n");
+        reader.read_line(&mut line)?;
+        assert_eq!(line, "third");
+        assert_eq!(reader.read_line(&mut line)?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_buf_writer_over_vec() -> io::Result<()> {
+        let mut writer = BufWriter::with_capacity(16, Vec::new());
+
+        writer.write_all(b"0123456789")?;
+        assert!(writer.get_ref().is_empty(), "Data should still be buffered");
+
+        // 跨越缓冲区边界时先写出之前缓冲的数据
+        writer.write_all(b"abcdefghij")?;
+        assert_eq!(writer.get_ref(), b"0123456789");
+
+        writer.flush()?;
+        assert_eq!(writer.get_ref(), b"0123456789abcdefghij");
+
+        Ok(())
+    }
 }
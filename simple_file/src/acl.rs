@@ -0,0 +1,340 @@
+/*
+    POSIX ACL，保存在扩展属性system.posix_acl_access中，由内核解释，Linux特有，需要启用acl feature
+
+    xattr的值是小端序的二进制格式：
+    header  u32 version，固定为2
+    entry   u16 tag, u16 perm, u32 id，重复任意次
+    tag为USER_OBJ(所有者)、USER(指定用户)、GROUP_OBJ(所属组)、GROUP(指定组)、MASK、OTHER之一，
+    只有USER和GROUP使用id，其余为ACL_UNDEFINED_ID(u32::MAX)；条目按tag、id排序
+
+    只有USER_OBJ、GROUP_OBJ、OTHER三项的ACL称为最小ACL，与权限位完全等价，内核不会保存为xattr
+    有USER或GROUP条目时必须有MASK，它限制了所有组类条目的最大权限，同时显示为权限位中组的部分
+    设置ACL时内核同步修改权限位，chmod修改组的权限位时实际修改的是MASK
+
+    目录的默认ACL(system.posix_acl_default)不在此处理
+*/
+
+use std::io;
+use std::path::Path;
+
+use crate::{Error, File, Result, metadata};
+
+const ACL_XATTR: &str = "system.posix_acl_access";
+const ACL_XATTR_VERSION: u32 = 2;
+const ACL_UNDEFINED_ID: u32 = u32::MAX;
+
+const ACL_USER_OBJ: u16 = 0x01;
+const ACL_USER: u16 = 0x02;
+const ACL_GROUP_OBJ: u16 = 0x04;
+const ACL_GROUP: u16 = 0x08;
+const ACL_MASK: u16 = 0x10;
+const ACL_OTHER: u16 = 0x20;
+
+/// ACL条目的类型
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum AclTag {
+    UserObj,    // 文件所有者
+    User(u32),  // 指定uid的用户
+    GroupObj,   // 文件所属组
+    Group(u32), // 指定gid的组
+    Mask,       // 组类条目的最大权限
+    Other,      // 其他用户
+}
+
+/// 一个ACL条目，perm为rwx三位，与权限位中的一组相同(0o4读、0o2写、0o1执行)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct AclEntry {
+    pub tag: AclTag,
+    pub perm: u32,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Acl {
+    entries: Vec<AclEntry>,
+}
+
+impl Acl {
+    /// 与权限位等价的最小ACL
+    pub fn from_mode(mode: u32) -> Acl {
+        Acl {
+            entries: vec![
+                AclEntry {
+                    tag: AclTag::UserObj,
+                    perm: (mode >> 6) & 0o7,
+                },
+                AclEntry {
+                    tag: AclTag::GroupObj,
+                    perm: (mode >> 3) & 0o7,
+                },
+                AclEntry {
+                    tag: AclTag::Other,
+                    perm: mode & 0o7,
+                },
+            ],
+        }
+    }
+
+    /// 解析system.posix_acl_access的值，格式不正确时返回InvalidData
+    pub fn from_bytes(bytes: &[u8]) -> Result<Acl> {
+        let invalid = |msg: &str| Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+        if bytes.len() < 4 || !(bytes.len() - 4).is_multiple_of(8) {
+            return Err(invalid("Invalid ACL, bad length"));
+        }
+        if u32::from_le_bytes(bytes[..4].try_into().unwrap()) != ACL_XATTR_VERSION {
+            return Err(invalid("Invalid ACL, unsupported version"));
+        }
+
+        let mut entries = Vec::new();
+        for chunk in bytes[4..].chunks_exact(8) {
+            let tag = u16::from_le_bytes([chunk[0], chunk[1]]);
+            let perm = u16::from_le_bytes([chunk[2], chunk[3]]) as u32;
+            let id = u32::from_le_bytes(chunk[4..].try_into().unwrap());
+            let tag = match tag {
+                ACL_USER_OBJ => AclTag::UserObj,
+                ACL_USER => AclTag::User(id),
+                ACL_GROUP_OBJ => AclTag::GroupObj,
+                ACL_GROUP => AclTag::Group(id),
+                ACL_MASK => AclTag::Mask,
+                ACL_OTHER => AclTag::Other,
+                _ => return Err(invalid("Invalid ACL, unknown tag")),
+            };
+            entries.push(AclEntry { tag, perm });
+        }
+
+        Ok(Acl { entries })
+    }
+
+    /// 编码为system.posix_acl_access的值，条目按内核要求的顺序排列
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut entries = self.entries.clone();
+        entries.sort_by_key(|entry| entry.tag);
+
+        let mut bytes = ACL_XATTR_VERSION.to_le_bytes().to_vec();
+        for entry in entries {
+            let (tag, id) = match entry.tag {
+                AclTag::UserObj => (ACL_USER_OBJ, ACL_UNDEFINED_ID),
+                AclTag::User(uid) => (ACL_USER, uid),
+                AclTag::GroupObj => (ACL_GROUP_OBJ, ACL_UNDEFINED_ID),
+                AclTag::Group(gid) => (ACL_GROUP, gid),
+                AclTag::Mask => (ACL_MASK, ACL_UNDEFINED_ID),
+                AclTag::Other => (ACL_OTHER, ACL_UNDEFINED_ID),
+            };
+            bytes.extend_from_slice(&tag.to_le_bytes());
+            bytes.extend_from_slice(&((entry.perm & 0o7) as u16).to_le_bytes());
+            bytes.extend_from_slice(&id.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    pub fn entries(&self) -> &[AclEntry] {
+        &self.entries
+    }
+
+    /// 设置tag的权限，已有同一tag的条目时覆盖
+    pub fn set(&mut self, tag: AclTag, perm: u32) {
+        let perm = perm & 0o7;
+        match self.entries.iter_mut().find(|entry| entry.tag == tag) {
+            Some(entry) => entry.perm = perm,
+            None => self.entries.push(AclEntry { tag, perm }),
+        }
+    }
+
+    /// 删除tag的条目，返回它的权限
+    pub fn remove(&mut self, tag: AclTag) -> Option<u32> {
+        let index = self.entries.iter().position(|entry| entry.tag == tag)?;
+        Some(self.entries.remove(index).perm)
+    }
+
+    pub fn get(&self, tag: AclTag) -> Option<u32> {
+        self.entries
+            .iter()
+            .find(|entry| entry.tag == tag)
+            .map(|entry| entry.perm)
+    }
+
+    /// 是否只有USER_OBJ、GROUP_OBJ、OTHER三项，即与权限位等价
+    pub fn is_minimal(&self) -> bool {
+        self.entries.iter().all(|entry| {
+            matches!(
+                entry.tag,
+                AclTag::UserObj | AclTag::GroupObj | AclTag::Other
+            )
+        })
+    }
+
+    /// 重新计算MASK为全部组类条目(GROUP_OBJ、USER、GROUP)的并集，与setfacl的默认行为相同
+    pub fn update_mask(&mut self) {
+        let mask = self
+            .entries
+            .iter()
+            .filter(|entry| {
+                matches!(
+                    entry.tag,
+                    AclTag::User(_) | AclTag::GroupObj | AclTag::Group(_)
+                )
+            })
+            .fold(0, |mask, entry| mask | entry.perm);
+        self.set(AclTag::Mask, mask);
+    }
+
+    // 与内核的posix_acl_valid相同的检查，提前给出比EINVAL更清楚的错误
+    fn validate(&self) -> Result<()> {
+        let invalid = |msg: &str| Error::new(io::ErrorKind::InvalidInput, msg.to_string());
+
+        let mut tags: Vec<AclTag> = self.entries.iter().map(|entry| entry.tag).collect();
+        tags.sort();
+        if tags.windows(2).any(|pair| pair[0] == pair[1]) {
+            return Err(invalid("Invalid ACL, duplicate entry"));
+        }
+        for required in [AclTag::UserObj, AclTag::GroupObj, AclTag::Other] {
+            if !tags.contains(&required) {
+                return Err(invalid("Invalid ACL, missing owner, group or other entry"));
+            }
+        }
+        if !self.is_minimal() && !tags.contains(&AclTag::Mask) {
+            return Err(invalid("Invalid ACL, named entries require a mask"));
+        }
+
+        Ok(())
+    }
+}
+
+impl File {
+    /// 读取ACL，没有扩展ACL时返回与权限位等价的最小ACL
+    pub fn acl(&self) -> Result<Acl> {
+        match self.get_xattr(ACL_XATTR)? {
+            Some(bytes) => Acl::from_bytes(&bytes),
+            None => Ok(Acl::from_mode(self.metadata()?.mode())),
+        }
+    }
+
+    /// 设置ACL，同时修改权限位；需要文件所有者或root权限，文件系统未启用ACL时返回ENOTSUP
+    pub fn set_acl(&mut self, acl: &Acl) -> Result<()> {
+        acl.validate()?;
+        self.set_xattr(ACL_XATTR, &acl.to_bytes())
+    }
+}
+
+/// 读取path的ACL，跟随符号链接，没有扩展ACL时返回与权限位等价的最小ACL
+pub fn get_acl<P: AsRef<Path>>(path: P) -> Result<Acl> {
+    let path = path.as_ref();
+    match crate::get_xattr(path, ACL_XATTR)? {
+        Some(bytes) => Acl::from_bytes(&bytes),
+        None => Ok(Acl::from_mode(metadata(path)?.mode())),
+    }
+}
+
+/// 设置path的ACL，跟随符号链接
+pub fn set_acl<P: AsRef<Path>>(path: P, acl: &Acl) -> Result<()> {
+    acl.validate()?;
+    crate::set_xattr(path, ACL_XATTR, &acl.to_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Acl, AclTag, get_acl, set_acl};
+    use crate::temp::TempDir;
+    use crate::{File, OpenMode, Permissions, metadata, set_permissions, write};
+    use std::io;
+
+    #[test]
+    fn test_acl_bytes() -> io::Result<()> {
+        let mut acl = Acl::from_mode(0o750);
+        assert!(acl.is_minimal());
+        assert_eq!(acl.get(AclTag::UserObj), Some(0o7));
+        assert_eq!(acl.get(AclTag::GroupObj), Some(0o5));
+        assert_eq!(acl.get(AclTag::Other), Some(0));
+
+        acl.set(AclTag::Group(100), 0o6);
+        acl.set(AclTag::User(1000), 0o4);
+        acl.update_mask();
+        assert!(!acl.is_minimal());
+        assert_eq!(acl.get(AclTag::Mask), Some(0o7));
+
+        // 编码时排序，解析后与排序后的条目一致
+        let bytes = acl.to_bytes();
+        assert_eq!(bytes.len(), 4 + 6 * 8);
+        let parsed = Acl::from_bytes(&bytes)?;
+        let tags: Vec<AclTag> = parsed.entries().iter().map(|e| e.tag).collect();
+        assert_eq!(
+            tags,
+            [
+                AclTag::UserObj,
+                AclTag::User(1000),
+                AclTag::GroupObj,
+                AclTag::Group(100),
+                AclTag::Mask,
+                AclTag::Other
+            ]
+        );
+        assert_eq!(parsed.get(AclTag::Group(100)), Some(0o6));
+
+        assert_eq!(acl.remove(AclTag::User(1000)), Some(0o4));
+        assert_eq!(acl.remove(AclTag::User(1000)), None);
+
+        for bad in [
+            &[2u8, 0, 0][..],
+            &[1, 0, 0, 0],
+            &[2, 0, 0, 0, 0x40, 0, 0, 0, 0, 0, 0, 0],
+        ] {
+            let result = Acl::from_bytes(bad);
+            assert!(result.is_err(), "Malformed ACL should fail");
+            if let Err(e) = result {
+                assert_eq!(
+                    e.kind(),
+                    io::ErrorKind::InvalidData,
+                    "Error should be InvalidData"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_acl() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("file.txt");
+        write(&path, b"content")?;
+        set_permissions(&path, Permissions::from_mode(0o640))?;
+
+        // 没有扩展ACL时由权限位得到
+        assert_eq!(get_acl(&path)?, Acl::from_mode(0o640));
+
+        let mut acl = get_acl(&path)?;
+        acl.set(AclTag::User(12345), 0o6);
+        acl.set(AclTag::Mask, 0o6);
+        match set_acl(&path, &acl) {
+            Err(e) if e.raw_os_error() == Some(libc::ENOTSUP) => return Ok(()),
+            result => result?,
+        }
+
+        let read = get_acl(&path)?;
+        assert_eq!(read.get(AclTag::User(12345)), Some(0o6));
+        // 权限位中组的部分显示为MASK
+        assert_eq!(metadata(&path)?.permissions().mode() & 0o777, 0o660);
+
+        let mut file = File::open(&path, OpenMode::ReadWrite)?;
+        assert_eq!(file.acl()?, read);
+        file.set_acl(&Acl::from_mode(0o600))?;
+        assert!(get_acl(&path)?.is_minimal());
+        assert_eq!(metadata(&path)?.permissions().mode() & 0o777, 0o600);
+
+        // 有指定用户却没有MASK
+        let mut invalid = Acl::from_mode(0o600);
+        invalid.set(AclTag::User(12345), 0o4);
+        let result = set_acl(&path, &invalid);
+        assert!(result.is_err(), "ACL without mask should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::InvalidInput,
+                "Error should be InvalidInput"
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+/*
+    页缓存相关的提示，告诉内核接下来会如何访问文件，只影响性能，不影响读写结果
+
+    posix_fadvise(fd: i32, offset: off_t, len: off_t, advice: i32) -> i32
+    len为0表示从offset一直到文件末尾；与其他函数不同，失败时直接返回错误码而不设置errno
+
+    readahead(fd: i32, offset: off64_t, count: size_t) -> ssize_t (Linux)
+    同步地把[offset, offset + count)读入页缓存，返回后再通过read/BufReader读取就不会再等待磁盘
+*/
+
+use std::io;
+
+use crate::{File, Result, sys, to_off_t};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Advice {
+    Normal,     // 默认行为
+    Sequential, // 顺序读取，内核会加大预读窗口
+    Random,     // 随机访问，内核会关闭预读
+    WillNeed,   // 很快会用到，内核在后台预先读入页缓存
+    DontNeed,   // 不再需要，内核可以丢弃对应的页缓存(脏页不受影响)
+    NoReuse,    // 只会访问一次
+}
+
+impl Advice {
+    fn as_raw(self) -> i32 {
+        match self {
+            Advice::Normal => libc::POSIX_FADV_NORMAL,
+            Advice::Sequential => libc::POSIX_FADV_SEQUENTIAL,
+            Advice::Random => libc::POSIX_FADV_RANDOM,
+            Advice::WillNeed => libc::POSIX_FADV_WILLNEED,
+            Advice::DontNeed => libc::POSIX_FADV_DONTNEED,
+            Advice::NoReuse => libc::POSIX_FADV_NOREUSE,
+        }
+    }
+}
+
+impl File {
+    /// 对[offset, offset + len)给出访问方式的提示，len为0表示到文件末尾
+    pub fn advise(&mut self, offset: u64, len: u64, advice: Advice) -> Result<()> {
+        self.ensure_open()?;
+        let (offset, len) = (to_off_t(offset)?, to_off_t(len)?);
+
+        let result = unsafe { sys::posix_fadvise(self.fd, offset, len, advice.as_raw()) };
+        if result != 0 {
+            self.last_errno = Some(result);
+            let err = io::Error::from_raw_os_error(result);
+            return Err(self.context_error(err, "posix_fadvise"));
+        }
+
+        Ok(())
+    }
+
+    /// 预先把[offset, offset + count)读入页缓存，只支持普通文件等有页缓存的文件
+    pub fn readahead(&mut self, offset: u64, count: usize) -> Result<()> {
+        self.ensure_readable()?;
+        let offset = to_off_t(offset)?;
+
+        let result = unsafe { libc::readahead(self.fd, offset, count as libc::size_t) };
+        if result < 0 {
+            return Err(self.record_os_error("readahead"));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Advice;
+    use crate::temp::TempFile;
+    use crate::{File, OpenMode};
+    use std::io::{self, Read};
+
+    #[test]
+    fn test_advise() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        std::fs::write(temp_file.path(), vec![b'x'; 64 * 1024])?;
+
+        let mut file = File::open(temp_file.path(), OpenMode::Read)?;
+        let all = [
+            Advice::Normal,
+            Advice::Sequential,
+            Advice::Random,
+            Advice::WillNeed,
+            Advice::DontNeed,
+            Advice::NoReuse,
+        ];
+        for advice in all {
+            file.advise(0, 0, advice)?;
+        }
+
+        // 提示不影响读取结果
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)?;
+        assert_eq!(content.len(), 64 * 1024);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_readahead() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        std::fs::write(temp_file.path(), b"Hello, world!")?;
+
+        let mut file = File::open(temp_file.path(), OpenMode::Read)?;
+        file.readahead(0, 1024 * 1024)?;
+        assert_eq!(file.stream_position()?, 0, "Position should not move");
+
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+        assert_eq!(content, "Hello, world!");
+
+        // 管道没有页缓存
+        let (reader, _writer) = crate::pipe()?;
+        let mut reader = reader.into_file();
+        let result = reader.readahead(0, 4096);
+        assert!(result.is_err(), "readahead on pipe should fail");
+        assert_eq!(reader.last_errno(), Some(libc::EINVAL));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_advise_errors() -> io::Result<()> {
+        // 管道不支持posix_fadvise
+        let (reader, _writer) = crate::pipe()?;
+        let mut reader = reader.into_file();
+
+        let result = reader.advise(0, 0, Advice::Sequential);
+        assert!(result.is_err(), "fadvise on pipe should fail");
+        assert_eq!(reader.last_errno(), Some(libc::ESPIPE));
+
+        let result = File::from_fd(-1).advise(0, 0, Advice::Random);
+        assert!(result.is_err(), "Advising closed file should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::InvalidInput,
+                "Error should be InvalidInput"
+            );
+        }
+
+        Ok(())
+    }
+}
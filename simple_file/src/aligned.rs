@@ -0,0 +1,197 @@
+/*
+    按块大小对齐的缓冲区，O_DIRECT要求用户缓冲区的地址按设备块大小对齐
+
+    不能直接返回Vec<u8>：Vec释放内存时使用u8的对齐(1)，与分配时的对齐不一致是未定义行为，
+    所以这里自己管理分配与释放，再通过Deref当作[u8]使用
+*/
+
+use std::alloc::{self, Layout};
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::slice;
+
+use crate::{File, Result};
+
+pub struct AlignedBuf {
+    ptr: *mut u8,
+    len: usize,
+    layout: Layout,
+}
+
+// 与Vec<u8>一样独占这块内存
+unsafe impl Send for AlignedBuf {}
+unsafe impl Sync for AlignedBuf {}
+
+impl AlignedBuf {
+    /// 分配size个字节、起始地址按align对齐的缓冲区，内容初始化为0，align必须是2的幂
+    pub fn new(size: usize, align: usize) -> Result<AlignedBuf> {
+        // 0字节的分配是未定义行为，至少分配1个字节
+        let layout = Layout::from_size_align(size.max(1), align).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Invalid alignment, must be a power of two",
+            )
+        })?;
+
+        let ptr = unsafe { alloc::alloc_zeroed(layout) };
+        if ptr.is_null() {
+            alloc::handle_alloc_error(layout);
+        }
+
+        Ok(AlignedBuf {
+            ptr,
+            len: size,
+            layout,
+        })
+    }
+
+    pub fn align(&self) -> usize {
+        self.layout.align()
+    }
+}
+
+impl File {
+    /*
+        文件系统推荐的IO大小，即fstat中的st_blksize，用来决定缓冲区大小
+    */
+    pub fn optimal_io_size(&self) -> Result<usize> {
+        Ok(self.fstat()?.st_blksize as usize)
+    }
+
+    /*
+        O_DIRECT要求的缓冲区地址对齐
+        Linux下优先使用statx的stx_dio_mem_align，内核或文件系统不提供时退回st_blksize
+        st_blksize是推荐的IO大小而不是设备的逻辑块大小，通常是后者的整数倍，只是一个偏大的近似
+    */
+    pub fn direct_io_align(&self) -> Result<usize> {
+        #[cfg(target_os = "linux")]
+        if let Some(align) = self.extended_metadata()?.dio_mem_align() {
+            return Ok(align as usize);
+        }
+
+        self.optimal_io_size()
+    }
+
+    /// 分配一个按direct_io_align对齐的缓冲区，可直接用于O_DIRECT读写
+    /// 注意O_DIRECT还要求读写长度和文件偏移量也按块大小对齐
+    pub fn aligned_buffer(&self, size: usize) -> Result<AlignedBuf> {
+        AlignedBuf::new(size, self.direct_io_align()?)
+    }
+}
+
+impl Deref for AlignedBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl DerefMut for AlignedBuf {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+}
+
+impl Drop for AlignedBuf {
+    fn drop(&mut self) {
+        unsafe {
+            alloc::dealloc(self.ptr, self.layout);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AlignedBuf;
+    #[cfg(target_os = "linux")]
+    use crate::temp::TempDir;
+    use crate::temp::TempFile;
+    use crate::{File, OpenMode};
+    use std::io;
+
+    #[test]
+    fn test_optimal_io_size() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let file = File::open(temp_file.path(), OpenMode::Read)?;
+        let size = file.optimal_io_size()?;
+        assert!(size > 0, "Block size should be positive");
+        assert!(size.is_power_of_two(), "Block size {} should be 2^n", size);
+
+        let align = file.direct_io_align()?;
+        assert!(align.is_power_of_two(), "Alignment {} should be 2^n", align);
+
+        let buf = file.aligned_buffer(3 * size)?;
+        assert_eq!(buf.len(), 3 * size);
+        assert_eq!(buf.as_ptr() as usize % align, 0, "Buffer should be aligned");
+        assert!(buf.iter().all(|&b| b == 0), "Buffer should be zeroed");
+
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_direct_io() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("direct.bin");
+
+        let mut file = match File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .direct(true)
+            .open(&path)
+        {
+            Ok(file) => file,
+            // /tmp可能是不支持O_DIRECT的tmpfs，跳过
+            Err(e) if e.raw_os_error() == Some(libc::EINVAL) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let size = file.optimal_io_size()?;
+        let mut buf = file.aligned_buffer(2 * size)?;
+        buf.fill(b'x');
+        match file.write(&buf) {
+            Ok(n) => assert_eq!(n, 2 * size),
+            // 部分文件系统打开时接受O_DIRECT，写入时才返回EINVAL
+            Err(e) if e.raw_os_error() == Some(libc::EINVAL) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        }
+
+        // 缓冲区地址和长度都没有对齐
+        let result = file.write(&buf[1..size]);
+        assert!(result.is_err(), "Unaligned direct write should fail");
+        assert_eq!(file.last_errno(), Some(libc::EINVAL));
+
+        let mut read_back = file.aligned_buffer(2 * size)?;
+        assert_eq!(file.read_at(&mut read_back, 0)?, 2 * size);
+        assert!(
+            read_back.iter().all(|&b| b == b'x'),
+            "Data should round trip"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_aligned_buf_invalid_align() {
+        let result = AlignedBuf::new(4096, 3000);
+        assert!(result.is_err(), "Non power of two alignment should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::InvalidInput,
+                "Error should be InvalidInput"
+            );
+        }
+    }
+
+    #[test]
+    fn test_aligned_buf_empty() -> io::Result<()> {
+        let buf = AlignedBuf::new(0, 512)?;
+        assert!(buf.is_empty());
+        assert_eq!(buf.align(), 512);
+
+        Ok(())
+    }
+}
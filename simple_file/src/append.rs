@@ -0,0 +1,232 @@
+/*
+    带批量fdatasync的追加写入，适合日志、WAL之类按记录追加的文件
+
+    每条记录都fdatasync最安全，但每次都要等待磁盘，吞吐量很低；完全不fdatasync则崩溃时可能丢失任意多的数据
+    SyncPolicy在两者之间取舍：累计到一定字节数、记录数或时间后才fdatasync一次，崩溃时最多丢失最后一批
+
+    文件以O_APPEND打开，每次write都原子地追加到文件末尾，多个进程同时追加时不会相互覆盖
+    但一条记录不保证由一次write写完：磁盘将满、被信号打断时write可能只写入一部分，剩余部分由下一次write追加，
+    其他进程的记录可能插入两部分之间，需要严格按记录隔离时应在记录中带上长度或校验和
+    fdatasync只同步数据和读取数据所需的元信息(例如文件大小)，比fsync少一次mtime等元信息的写入
+*/
+
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::{Error, File, OpenMode, Result};
+
+/// 何时调用fdatasync
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SyncPolicy {
+    Always,                  // 每条记录之后
+    EveryBytes(u64),         // 未同步的字节数达到N之后
+    EveryRecords(u64),       // 未同步的记录数达到N之后
+    EveryInterval(Duration), // 距离上一次同步超过指定时间后，只在append时检查，没有后台线程
+    Never,                   // 只在调用sync、into_inner或Drop时同步
+}
+
+pub struct AppendSync {
+    file: File,
+    policy: SyncPolicy,
+    pending_bytes: u64,
+    pending_records: u64,
+    last_sync: Instant,
+}
+
+impl AppendSync {
+    /// 以追加方式打开path，不存在时创建
+    pub fn open<P: AsRef<Path>>(path: P, policy: SyncPolicy) -> Result<AppendSync> {
+        Ok(AppendSync::new(File::open(path, OpenMode::Append)?, policy))
+    }
+
+    /// 包装一个已打开的File，file应当以追加方式打开
+    pub fn new(file: File, policy: SyncPolicy) -> AppendSync {
+        AppendSync {
+            file,
+            policy,
+            pending_bytes: 0,
+            pending_records: 0,
+            last_sync: Instant::now(),
+        }
+    }
+
+    /// 追加一条记录，按照策略决定是否fdatasync
+    /// 写入中途失败时，已经写入的部分同样计入pending_bytes，并算作一条未同步的记录
+    pub fn append(&mut self, record: &[u8]) -> Result<()> {
+        let (written, result) = self.write_record(record);
+        if written > 0 {
+            self.pending_bytes += written as u64;
+            self.pending_records += 1;
+        }
+        result?;
+
+        let due = match self.policy {
+            SyncPolicy::Always => true,
+            SyncPolicy::EveryBytes(n) => self.pending_bytes >= n,
+            SyncPolicy::EveryRecords(n) => self.pending_records >= n,
+            SyncPolicy::EveryInterval(interval) => self.last_sync.elapsed() >= interval,
+            SyncPolicy::Never => false,
+        };
+        if due {
+            self.sync()?;
+        }
+
+        Ok(())
+    }
+
+    // 与File::write_all相同，但返回实际写入的字节数，失败时调用方仍需要统计已写入的部分
+    fn write_record(&mut self, record: &[u8]) -> (usize, Result<()>) {
+        let mut written = 0;
+        while written < record.len() {
+            match self.file.write(&record[written..]) {
+                Ok(0) => {
+                    let err = Error::new(io::ErrorKind::WriteZero, "Failed to write whole record");
+                    return (written, Err(err));
+                }
+                Ok(n) => written += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return (written, Err(e)),
+            }
+        }
+
+        (written, Ok(()))
+    }
+
+    /// 立即fdatasync，之前追加的全部记录都已写入磁盘
+    pub fn sync(&mut self) -> Result<()> {
+        self.file.sync_data()?;
+        self.pending_bytes = 0;
+        self.pending_records = 0;
+        self.last_sync = Instant::now();
+
+        Ok(())
+    }
+
+    /// 上一次同步之后追加的字节数，崩溃时最多丢失这么多数据
+    pub fn pending_bytes(&self) -> u64 {
+        self.pending_bytes
+    }
+
+    /// 上一次同步之后追加的记录数
+    pub fn pending_records(&self) -> u64 {
+        self.pending_records
+    }
+
+    pub fn policy(&self) -> SyncPolicy {
+        self.policy
+    }
+
+    pub fn get_ref(&self) -> &File {
+        &self.file
+    }
+
+    /// 同步剩余的记录后取回File
+    pub fn into_inner(mut self) -> Result<File> {
+        if self.pending_records > 0 {
+            self.sync()?;
+        }
+
+        Ok(std::mem::replace(
+            &mut self.file,
+            File::from_fd(crate::INVALID_FD),
+        ))
+    }
+}
+
+// Drop时尽力同步剩余的记录，无法返回错误，需要确认结果时应先调用sync
+impl Drop for AppendSync {
+    fn drop(&mut self) {
+        if self.pending_records > 0 && self.file.fd != crate::INVALID_FD {
+            let _ = self.file.sync_data();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AppendSync, SyncPolicy};
+    use crate::temp::TempDir;
+    use std::io;
+    use std::time::Duration;
+
+    #[test]
+    fn test_append_sync_policies() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("wal.log");
+
+        let mut log = AppendSync::open(&path, SyncPolicy::Always)?;
+        log.append(b"first\n")?;
+        assert_eq!(log.pending_records(), 0, "Always should sync each record");
+
+        let mut log = AppendSync::open(&path, SyncPolicy::EveryRecords(3))?;
+        log.append(b"a\n")?;
+        log.append(b"b\n")?;
+        assert_eq!(log.pending_records(), 2);
+        log.append(b"c\n")?;
+        assert_eq!(log.pending_records(), 0, "Third record should trigger sync");
+
+        let mut log = AppendSync::open(&path, SyncPolicy::EveryBytes(10))?;
+        log.append(b"12345")?;
+        assert_eq!(log.pending_bytes(), 5);
+        log.append(b"67890\n")?;
+        assert_eq!(log.pending_bytes(), 0, "10 bytes should trigger sync");
+
+        let mut log = AppendSync::open(&path, SyncPolicy::EveryInterval(Duration::ZERO))?;
+        log.append(b"tick\n")?;
+        assert_eq!(log.pending_records(), 0);
+
+        let mut log = AppendSync::open(&path, SyncPolicy::Never)?;
+        log.append(b"x\n")?;
+        log.append(b"y\n")?;
+        assert_eq!(log.pending_records(), 2);
+        log.sync()?;
+        assert_eq!(log.pending_bytes(), 0);
+        log.append(b"z\n")?;
+        let file = log.into_inner()?;
+        assert!(file.path().is_some());
+
+        assert_eq!(
+            crate::read_to_string(&path)?,
+            "first\na\nb\nc\n1234567890\ntick\nx\ny\nz\n"
+        );
+
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_append_partial_write() -> io::Result<()> {
+        // 非阻塞管道的缓冲区只剩一页时，超过PIPE_BUF的记录只能写入一部分
+        let (_reader, writer) = crate::pipe()?;
+        let mut writer = writer.into_file();
+        writer.set_nonblocking(true)?;
+        writer.write_all(&[0u8; 15 * 4096])?;
+
+        let mut log = AppendSync::new(writer, SyncPolicy::Never);
+        let result = log.append(&[1u8; 2 * 4096]);
+        assert!(result.is_err(), "Full pipe should fail");
+        if let Err(e) = result {
+            assert_eq!(e.kind(), io::ErrorKind::WouldBlock);
+        }
+        assert_eq!(log.pending_bytes(), 4096, "Written part should be counted");
+        assert_eq!(log.pending_records(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_append_sync_keeps_existing() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("app.log");
+        crate::write(&path, b"existing\n")?;
+
+        {
+            let mut log = AppendSync::open(&path, SyncPolicy::EveryRecords(100))?;
+            log.append(b"appended\n")?;
+        }
+        assert_eq!(crate::read_to_string(&path)?, "existing\nappended\n");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,208 @@
+/*
+    原子地替换文件内容：读者看到的要么是完整的旧内容，要么是完整的新内容，崩溃后也不会出现写了一半的文件
+
+    step1: 在目标所在的目录中创建临时文件，保证与目标位于同一个文件系统，rename才是原子的
+    step2: 写入新内容，fsync临时文件
+    step3: rename覆盖目标
+    step4: fsync目标所在的目录，rename本身才能在断电后保留
+    任何一步失败时临时文件被删除，目标保持不变
+
+    目标是符号链接时替换链接最终指向的文件，链接本身保持不变，否则rename会把链接换成普通文件
+*/
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::temp::TempFile;
+use crate::{DEFAULT_FILE_PERMSSIONS, Result, error};
+
+// 展开符号链接的层数上限，与Linux的MAXSYMLINKS相同
+const MAX_SYMLINKS: usize = 40;
+
+/// 把data原子地写入path，path已存在时保留它的权限，否则以0o644创建(经过umask过滤)
+pub fn write_atomic<P: AsRef<Path>>(path: P, data: &[u8]) -> Result<()> {
+    let mut writer = AtomicWriter::new(path)?;
+    writer.write_all(data)?;
+    writer.commit()
+}
+
+/// 流式写入的write_atomic，commit之前目标不受影响，没有commit就被Drop时丢弃写入的内容
+pub struct AtomicWriter {
+    temp: TempFile,
+    path: PathBuf,
+}
+
+impl AtomicWriter {
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<AtomicWriter> {
+        let path = resolve_symlinks(path.as_ref())?;
+        let dir = crate::dir::parent_dir(&path);
+
+        let temp = match crate::metadata(&path) {
+            // mkstemp创建的文件权限为0600，改为与目标一致
+            Ok(meta) => {
+                let mut temp = TempFile::new_in(dir)?;
+                temp.file_mut().set_permissions(meta.permissions())?;
+                temp
+            }
+            // 新文件与open创建的一样经过umask过滤，fchmod不会
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                TempFile::new_in_with_mode(dir, DEFAULT_FILE_PERMSSIONS as u32)?
+            }
+            Err(e) => return Err(e),
+        };
+
+        Ok(AtomicWriter { temp, path })
+    }
+
+    /// 目标路径，创建时传入的路径是符号链接时为链接最终指向的路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// fsync后rename覆盖目标，再fsync目标所在的目录
+    pub fn commit(self) -> Result<()> {
+        self.temp.persist_sync(&self.path)?;
+        Ok(())
+    }
+}
+
+// 逐级展开path最后一级的符号链接，得到最终要替换的文件；链接指向不存在的文件时返回它指向的路径
+fn resolve_symlinks(path: &Path) -> Result<PathBuf> {
+    let mut path = path.to_path_buf();
+
+    for _ in 0..MAX_SYMLINKS {
+        match crate::symlink_metadata(&path) {
+            Ok(meta) if meta.is_symlink() => {
+                // 相对路径的链接相对于链接所在的目录，绝对路径的链接join后直接替换
+                let target = crate::read_link(&path)?;
+                path = crate::dir::parent_dir(&path).join(target);
+            }
+            Ok(_) => return Ok(path),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(path),
+            Err(e) => return Err(e),
+        }
+    }
+
+    Err(error::with_context(
+        io::Error::from_raw_os_error(libc::ELOOP),
+        "readlink",
+        Some(&path),
+    ))
+}
+
+impl Write for AtomicWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(self.temp.file_mut().write(buf)?)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        Ok(self.temp.file_mut().write_all(buf)?)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AtomicWriter, write_atomic};
+    use crate::temp::TempDir;
+    use crate::{
+        File, Permissions, metadata, read, read_dir, set_permissions, symlink, symlink_metadata,
+    };
+    use std::io::{self, Write};
+
+    #[test]
+    fn test_write_atomic() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("config.toml");
+
+        write_atomic(&path, b"version = 1")?;
+        assert_eq!(read(&path)?, b"version = 1");
+        // 新文件的权限与open以0o644创建的文件相同，都经过umask过滤
+        let created = dir.path().join("created");
+        File::options()
+            .write(true)
+            .create(true)
+            .mode(0o644)
+            .open(&created)?;
+        assert_eq!(
+            metadata(&path)?.permissions().mode() & 0o777,
+            metadata(&created)?.permissions().mode() & 0o777
+        );
+        crate::remove_file(&created)?;
+
+        // 覆盖时保留原来的权限
+        set_permissions(&path, Permissions::from_mode(0o600))?;
+        write_atomic(&path, b"version = 2")?;
+        assert_eq!(read(&path)?, b"version = 2");
+        assert_eq!(metadata(&path)?.permissions().mode() & 0o777, 0o600);
+
+        assert_eq!(read_dir(dir.path())?.count(), 1, "No temp file left");
+
+        let result = write_atomic(dir.path().join("missing/config.toml"), b"x");
+        assert!(result.is_err(), "Missing directory should fail");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_atomic_symlink() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let target = dir.path().join("target.conf");
+        let link = dir.path().join("link.conf");
+        write_atomic(&target, b"old")?;
+        symlink("target.conf", &link)?;
+
+        // 替换链接指向的文件，链接本身保留
+        write_atomic(&link, b"new")?;
+        assert!(symlink_metadata(&link)?.is_symlink(), "Link should be kept");
+        assert_eq!(read(&target)?, b"new");
+        assert_eq!(
+            AtomicWriter::new(&link)?.path(),
+            dir.path().join("target.conf")
+        );
+
+        // 悬空的链接创建它指向的文件
+        let dangling = dir.path().join("dangling");
+        symlink("created.conf", &dangling)?;
+        write_atomic(&dangling, b"created")?;
+        assert!(symlink_metadata(&dangling)?.is_symlink());
+        assert_eq!(read(dir.path().join("created.conf"))?, b"created");
+
+        // 链接形成环
+        symlink("loop", dir.path().join("loop"))?;
+        let result = write_atomic(dir.path().join("loop"), b"x");
+        assert!(result.is_err(), "Symlink loop should fail");
+        if let Err(e) = result {
+            assert_eq!(e.raw_os_error(), Some(libc::ELOOP), "Error should be ELOOP");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_atomic_writer() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("data.bin");
+        write_atomic(&path, b"old")?;
+
+        let mut writer = AtomicWriter::new(&path)?;
+        writer.write_all(b"new ")?;
+        writer.write_all(b"content")?;
+        // commit之前目标不变
+        assert_eq!(read(&path)?, b"old");
+        writer.commit()?;
+        assert_eq!(read(&path)?, b"new content");
+
+        // 没有commit就丢弃
+        let mut writer = AtomicWriter::new(&path)?;
+        writer.write_all(b"discarded")?;
+        drop(writer);
+        assert_eq!(read(&path)?, b"new content");
+        assert_eq!(read_dir(dir.path())?.count(), 1, "No temp file left");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,937 @@
+/*
+    目录的创建与删除
+
+    mkdir(path: *const c_char, mode: mode_t) -> i32
+    mode会再经过umask过滤，父目录不存在时返回ENOENT，路径已存在(无论是不是目录)返回EEXIST
+    rmdir(path: *const c_char) -> i32
+    只能删除空目录，非空时返回ENOTEMPTY
+
+    递归删除基于目录fd进行，每一层都用unlinkat删除相对于目录fd的名字，
+    不会在删除过程中重新解析完整路径，遍历时被替换成符号链接的目录也不会被跟随
+    unlinkat(dirfd: i32, path: *const c_char, flags: i32) -> i32
+    flags为AT_REMOVEDIR时等价于rmdir，否则等价于unlink
+*/
+
+use std::ffi::{CStr, CString, OsStr};
+use std::fmt;
+use std::io;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, IntoRawFd, OwnedFd, RawFd};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::{
+    Error, File, FileType, Metadata, OpenMode, OpenOptions, Result, error, sys, to_c_path,
+};
+
+const DEFAULT_DIR_PERMISSIONS: u32 = 0o777; // 默认目录权限，实际权限由umask决定，通常为0o755
+const DIR_FLAGS: i32 = libc::O_RDONLY | libc::O_DIRECTORY | libc::O_CLOEXEC;
+
+/// 创建目录的选项，默认只创建最后一级，权限为0o777(经过umask过滤)
+#[derive(Clone, Debug)]
+pub struct DirBuilder {
+    recursive: bool,
+    mode: u32,
+}
+
+impl DirBuilder {
+    pub fn new() -> DirBuilder {
+        DirBuilder {
+            recursive: false,
+            mode: DEFAULT_DIR_PERMISSIONS,
+        }
+    }
+
+    /// 同时创建不存在的父目录，目录已存在时不报错
+    pub fn recursive(&mut self, recursive: bool) -> &mut DirBuilder {
+        self.recursive = recursive;
+        self
+    }
+
+    /// 新建目录的权限，递归创建时每一级父目录都使用同样的权限
+    pub fn mode(&mut self, mode: u32) -> &mut DirBuilder {
+        self.mode = mode;
+        self
+    }
+
+    pub fn create<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if self.recursive {
+            create_dir_all_with_mode(path, self.mode)
+        } else {
+            mkdir(path, self.mode)
+        }
+    }
+}
+
+impl Default for DirBuilder {
+    fn default() -> DirBuilder {
+        DirBuilder::new()
+    }
+}
+
+/// 创建一级目录，父目录必须已经存在，路径已存在时返回AlreadyExists
+pub fn create_dir<P: AsRef<Path>>(path: P) -> Result<()> {
+    DirBuilder::new().create(path)
+}
+
+/// 创建目录以及所有不存在的父目录，目录已存在时直接返回成功
+pub fn create_dir_all<P: AsRef<Path>>(path: P) -> Result<()> {
+    DirBuilder::new().recursive(true).create(path)
+}
+
+/// 删除一个空目录
+pub fn remove_dir<P: AsRef<Path>>(path: P) -> Result<()> {
+    let path = path.as_ref();
+    let c_style_str_path = to_c_path(path)?;
+
+    let result = unsafe { libc::rmdir(c_style_str_path.as_ptr()) };
+    if result < 0 {
+        return Err(error::with_context(
+            io::Error::last_os_error(),
+            "rmdir",
+            Some(path),
+        ));
+    }
+
+    Ok(())
+}
+
+/*
+    删除目录以及其中的全部内容
+    path本身是符号链接时只删除链接，不会删除它指向的目录
+*/
+pub fn remove_dir_all<P: AsRef<Path>>(path: P) -> Result<()> {
+    let path = path.as_ref();
+    let c_style_str_path = to_c_path(path)?;
+
+    let fd = open_dir_nofollow(libc::AT_FDCWD, &c_style_str_path);
+    if fd < 0 {
+        let err = io::Error::last_os_error();
+        // 不是目录(包括符号链接)时按普通文件删除
+        if matches!(err.raw_os_error(), Some(libc::ENOTDIR | libc::ELOOP)) {
+            return crate::remove_file(path);
+        }
+        return Err(error::with_context(err, "open", Some(path)));
+    }
+
+    let result = remove_dir_contents(fd, path);
+    unsafe { libc::close(fd) };
+    result?;
+
+    remove_dir(path)
+}
+
+fn mkdir(path: &Path, mode: u32) -> Result<()> {
+    let c_style_str_path = to_c_path(path)?;
+
+    let result = unsafe { libc::mkdir(c_style_str_path.as_ptr(), mode as libc::mode_t) };
+    if result < 0 {
+        return Err(error::with_context(
+            io::Error::last_os_error(),
+            "mkdir",
+            Some(path),
+        ));
+    }
+
+    Ok(())
+}
+
+// 先尝试直接创建，父目录不存在时递归创建父目录后再重试
+fn create_dir_all_with_mode(path: &Path, mode: u32) -> Result<()> {
+    let err = match mkdir(path, mode) {
+        Ok(()) => return Ok(()),
+        Err(e) => e,
+    };
+
+    match err.raw_os_error() {
+        // 已存在的是目录才算成功，与其他进程并发创建同一个目录时也会走到这里
+        Some(libc::EEXIST) if crate::is_dir(path)? => Ok(()),
+        Some(libc::ENOENT) => {
+            let parent = match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => return Err(err),
+            };
+            create_dir_all_with_mode(parent, mode)?;
+
+            match mkdir(path, mode) {
+                Err(e) if e.raw_os_error() == Some(libc::EEXIST) && crate::is_dir(path)? => Ok(()),
+                result => result,
+            }
+        }
+        _ => Err(err),
+    }
+}
+
+// 以O_NOFOLLOW打开目录，name是符号链接时返回ELOOP(macOS)或ENOTDIR(Linux)
+fn open_dir_nofollow(dirfd: i32, name: &CStr) -> i32 {
+    crate::retry_eintr(true, || unsafe {
+        libc::openat(
+            dirfd,
+            name.as_ptr(),
+            libc::O_RDONLY | libc::O_DIRECTORY | libc::O_NOFOLLOW | libc::O_CLOEXEC,
+        )
+    })
+}
+
+// 删除dirfd下的全部内容，path只用于错误信息
+fn remove_dir_contents(dirfd: i32, path: &Path) -> Result<()> {
+    for name in entry_names(dirfd, path)? {
+        let child = path.join(OsStr::from_bytes(name.to_bytes()));
+
+        if unsafe { libc::unlinkat(dirfd, name.as_ptr(), 0) } == 0 {
+            continue;
+        }
+        let err = io::Error::last_os_error();
+        match err.raw_os_error() {
+            // 已经被其他进程删除
+            Some(libc::ENOENT) => continue,
+            // 目录不能unlink，Linux返回EISDIR，macOS返回EPERM
+            Some(libc::EISDIR | libc::EPERM) => {}
+            _ => return Err(error::with_context(err, "unlinkat", Some(&child))),
+        }
+
+        let fd = open_dir_nofollow(dirfd, &name);
+        if fd < 0 {
+            // EPERM的原因不是目录，返回原来的错误
+            return Err(error::with_context(err, "unlinkat", Some(&child)));
+        }
+        let result = remove_dir_contents(fd, &child);
+        unsafe { libc::close(fd) };
+        result?;
+
+        if unsafe { libc::unlinkat(dirfd, name.as_ptr(), libc::AT_REMOVEDIR) } < 0 {
+            return Err(error::with_context(
+                io::Error::last_os_error(),
+                "unlinkat",
+                Some(&child),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// 读出目录中的全部名字，先全部读完再删除，避免边遍历边修改目录
+fn entry_names(dirfd: i32, path: &Path) -> Result<Vec<CString>> {
+    // fdopendir会接管传入的fd，closedir时一起关闭，所以先dup一份
+    let fd = unsafe { libc::fcntl(dirfd, libc::F_DUPFD_CLOEXEC, 0) };
+    if fd < 0 {
+        return Err(error::with_context(
+            io::Error::last_os_error(),
+            "fcntl",
+            Some(path),
+        ));
+    }
+
+    ReadDir::from_fd(fd, path.to_path_buf())?
+        .map(|entry| entry.map(|entry| entry.name))
+        .collect()
+}
+
+/*
+    打开的目录句柄，所有操作都相对于目录fd进行(mkdirat、unlinkat、renameat、fstatat)
+    目录本身被移动或重命名后，句柄仍然指向同一个目录；路径中的上级目录被替换成符号链接也不会影响后续操作
+    只持有Dir的代码只能访问这个目录下的文件(".."和绝对路径除外)，适合把访问范围限制在某个目录中
+*/
+pub struct Dir {
+    file: File,
+}
+
+impl Dir {
+    /// 以O_DIRECTORY打开目录，path不是目录时返回NotADirectory
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Dir> {
+        let path = path.as_ref();
+        let c_style_str_path = to_c_path(path)?;
+
+        let fd = crate::retry_eintr(true, || unsafe {
+            libc::open(c_style_str_path.as_ptr(), DIR_FLAGS)
+        });
+        if fd < 0 {
+            return Err(error::with_context(
+                io::Error::last_os_error(),
+                "open",
+                Some(path),
+            ));
+        }
+
+        let mut file = File::from_fd(fd);
+        file.path = Some(path.to_path_buf());
+
+        Ok(Dir { file })
+    }
+
+    /// 打开时使用的路径，只用于错误信息，之后的操作不会再解析它
+    pub fn path(&self) -> Option<&Path> {
+        self.file.path()
+    }
+
+    /// 打开目录下的文件，等价于File::open_at
+    pub fn open_at<P: AsRef<Path>>(&self, path: P, mode: OpenMode) -> Result<File> {
+        self.file.open_at(path, mode)
+    }
+
+    /// 按options打开目录下的文件
+    pub fn open_with<P: AsRef<Path>>(&self, path: P, options: &OpenOptions) -> Result<File> {
+        options.open_at(&self.file, path)
+    }
+
+    /*
+        按options打开目录下的文件，路径不能离开这个目录，也不能经过任何符号链接
+        绝对路径、越过目录的".."返回EXDEV，遇到符号链接返回ELOOP
+        用于处理不可信的路径(例如HTTP请求中的文件名)，防止"../../etc/passwd"之类的路径穿越
+    */
+    pub fn open_beneath<P: AsRef<Path>>(&self, path: P, options: &OpenOptions) -> Result<File> {
+        options.open_beneath(&self.file, path.as_ref())
+    }
+
+    /// 打开目录下的子目录
+    pub fn open_dir_at<P: AsRef<Path>>(&self, path: P) -> Result<Dir> {
+        self.openat_dir(path.as_ref(), DIR_FLAGS)
+    }
+
+    // 打开目录下的子目录，path的最后一级是符号链接时返回错误(ELOOP或ENOTDIR)
+    pub(crate) fn open_dir_at_nofollow(&self, path: &Path) -> Result<Dir> {
+        self.openat_dir(path, DIR_FLAGS | libc::O_NOFOLLOW)
+    }
+
+    fn openat_dir(&self, path: &Path, flags: i32) -> Result<Dir> {
+        let c_style_str_path = to_c_path(path)?;
+
+        let fd = crate::retry_eintr(true, || unsafe {
+            libc::openat(self.file.fd, c_style_str_path.as_ptr(), flags)
+        });
+        if fd < 0 {
+            return Err(self.at_error("openat", path));
+        }
+
+        let mut file = File::from_fd(fd);
+        file.path = self.file.join_path(path);
+
+        Ok(Dir { file })
+    }
+
+    /*
+        在目录下创建子目录，封装POSIX mkdirat
+        mkdirat(dirfd: i32, path: *const c_char, mode: mode_t) -> i32
+    */
+    pub fn create_dir_at<P: AsRef<Path>>(&self, path: P, mode: u32) -> Result<()> {
+        let path = path.as_ref();
+        let c_style_str_path = to_c_path(path)?;
+
+        let result = unsafe {
+            libc::mkdirat(
+                self.file.fd,
+                c_style_str_path.as_ptr(),
+                mode as libc::mode_t,
+            )
+        };
+        if result < 0 {
+            return Err(self.at_error("mkdirat", path));
+        }
+
+        Ok(())
+    }
+
+    /// 删除目录下的文件或符号链接，path是目录时返回错误，删除目录使用remove_dir_at
+    pub fn unlink_at<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.unlinkat(path.as_ref(), 0)
+    }
+
+    /// 删除目录下的空目录
+    pub fn remove_dir_at<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.unlinkat(path.as_ref(), libc::AT_REMOVEDIR)
+    }
+
+    fn unlinkat(&self, path: &Path, flags: i32) -> Result<()> {
+        let c_style_str_path = to_c_path(path)?;
+
+        let result = unsafe { libc::unlinkat(self.file.fd, c_style_str_path.as_ptr(), flags) };
+        if result < 0 {
+            return Err(self.at_error("unlinkat", path));
+        }
+
+        Ok(())
+    }
+
+    /*
+        把self下的from重命名为to_dir下的to，封装POSIX renameat
+        renameat(olddirfd: i32, oldpath: *const c_char, newdirfd: i32, newpath: *const c_char) -> i32
+        to_dir可以是self本身，两个目录必须在同一个文件系统上
+    */
+    pub fn rename_at<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        from: P,
+        to_dir: &Dir,
+        to: Q,
+    ) -> Result<()> {
+        let old = to_c_path(from.as_ref())?;
+        let new = to_c_path(to.as_ref())?;
+
+        let result =
+            unsafe { libc::renameat(self.file.fd, old.as_ptr(), to_dir.file.fd, new.as_ptr()) };
+        if result < 0 {
+            return Err(self.at_error("renameat", from.as_ref()));
+        }
+
+        Ok(())
+    }
+
+    /// 目录本身的元信息，等价于fstat
+    pub fn metadata(&self) -> Result<Metadata> {
+        self.file.metadata()
+    }
+
+    /// 目录下文件的元信息，跟随符号链接
+    pub fn metadata_at<P: AsRef<Path>>(&self, path: P) -> Result<Metadata> {
+        self.fstatat(path.as_ref(), 0)
+    }
+
+    /// 目录下文件的元信息，不跟随符号链接
+    pub fn symlink_metadata_at<P: AsRef<Path>>(&self, path: P) -> Result<Metadata> {
+        self.fstatat(path.as_ref(), libc::AT_SYMLINK_NOFOLLOW)
+    }
+
+    fn fstatat(&self, path: &Path, flags: i32) -> Result<Metadata> {
+        let c_style_str_path = to_c_path(path)?;
+
+        let mut stat: sys::stat = unsafe { std::mem::zeroed() };
+        let result =
+            unsafe { sys::fstatat(self.file.fd, c_style_str_path.as_ptr(), &mut stat, flags) };
+        if result < 0 {
+            return Err(self.at_error("fstatat", path));
+        }
+
+        Ok(Metadata::from_stat(stat))
+    }
+
+    /// 列出目录中的条目，每次调用都从头开始读取
+    pub fn read_dir(&self) -> Result<ReadDir> {
+        // 重新打开"."得到独立的读取位置，dup出的fd与self共享位置，第二次读取会直接到达结尾
+        let dir = self.open_dir_at(".")?;
+        let path = self.file.path.clone().unwrap_or_default();
+
+        ReadDir::from_fd(dir.file.into_raw_fd(), path)
+    }
+
+    /*
+        把目录本身写入磁盘，封装fsync(dirfd)
+        文件的fsync只保证文件内容，目录中新建、删除、重命名的条目属于目录的数据，
+        只有目录被fsync后，rename才能在崩溃或断电后保留
+    */
+    pub fn sync_all(&self) -> Result<()> {
+        self.file.sync_all()
+    }
+
+    pub fn try_clone(&self) -> Result<Dir> {
+        Ok(Dir {
+            file: self.file.try_clone()?,
+        })
+    }
+
+    // 相对于目录fd的操作失败，错误中带上拼接后的路径和目录fd
+    fn at_error(&self, op: &'static str, path: &Path) -> Error {
+        error::with_fd_context(
+            io::Error::last_os_error(),
+            op,
+            self.file.join_path(path).as_deref(),
+            Some(self.file.fd),
+        )
+    }
+}
+
+impl fmt::Debug for Dir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dir")
+            .field("fd", &self.file.fd)
+            .field("path", &self.path())
+            .finish()
+    }
+}
+
+impl AsRawFd for Dir {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+impl AsFd for Dir {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+}
+
+impl From<Dir> for OwnedFd {
+    fn from(dir: Dir) -> OwnedFd {
+        OwnedFd::from(dir.file)
+    }
+}
+
+// path所在的目录，相对路径"file"的父目录为"."
+pub(crate) fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+// fsync path所在的目录，在path被创建或rename到位之后调用
+pub(crate) fn sync_parent(path: &Path) -> Result<()> {
+    Dir::open(parent_dir(path))?.sync_all()
+}
+
+/*
+    读取目录中的条目，封装POSIX opendir/readdir
+    fdopendir(fd: i32) -> *mut DIR
+    readdir(dirp: *mut DIR) -> *mut dirent
+    返回的条目不包括"."和".."，顺序由文件系统决定
+    readdir到达结尾和出错都返回NULL，需要调用前把errno清零来区分
+*/
+pub fn read_dir<P: AsRef<Path>>(path: P) -> Result<ReadDir> {
+    let path = path.as_ref();
+    let c_style_str_path = to_c_path(path)?;
+
+    let fd = crate::retry_eintr(true, || unsafe {
+        libc::open(c_style_str_path.as_ptr(), DIR_FLAGS)
+    });
+    if fd < 0 {
+        return Err(error::with_context(
+            io::Error::last_os_error(),
+            "open",
+            Some(path),
+        ));
+    }
+
+    ReadDir::from_fd(fd, path.to_path_buf())
+}
+
+// 已打开的目录流，ReadDir和它产生的DirEntry共享，最后一个引用释放时closedir
+struct DirStream {
+    dirp: *mut libc::DIR,
+    path: PathBuf,
+}
+
+// readdir只在ReadDir::next(&mut self)中调用，DirEntry只通过dirfd读取fd，可以在线程间传递和共享
+unsafe impl Send for DirStream {}
+unsafe impl Sync for DirStream {}
+
+impl Drop for DirStream {
+    fn drop(&mut self) {
+        unsafe { libc::closedir(self.dirp) };
+    }
+}
+
+/// read_dir返回的迭代器，每一项是一个目录条目
+pub struct ReadDir {
+    stream: Arc<DirStream>,
+    end: bool,
+}
+
+impl ReadDir {
+    // 接管fd，失败时关闭它
+    fn from_fd(fd: i32, path: PathBuf) -> Result<ReadDir> {
+        let dirp = unsafe { libc::fdopendir(fd) };
+        if dirp.is_null() {
+            let err = io::Error::last_os_error();
+            unsafe { libc::close(fd) };
+            return Err(error::with_context(err, "fdopendir", Some(&path)));
+        }
+
+        Ok(ReadDir {
+            stream: Arc::new(DirStream { dirp, path }),
+            end: false,
+        })
+    }
+}
+
+impl Iterator for ReadDir {
+    type Item = Result<DirEntry>;
+
+    fn next(&mut self) -> Option<Result<DirEntry>> {
+        while !self.end {
+            sys::clear_errno();
+            let entry = unsafe { sys::readdir(self.stream.dirp) };
+            if entry.is_null() {
+                // 出错后不再继续读取，避免同一个错误无限重复
+                self.end = true;
+                let err = io::Error::last_os_error();
+                return match err.raw_os_error() {
+                    Some(0) => None,
+                    _ => Some(Err(error::with_context(
+                        err,
+                        "readdir",
+                        Some(&self.stream.path),
+                    ))),
+                };
+            }
+
+            // dirent中的内容在下一次readdir时失效，需要立即复制出来
+            let (name, d_type) =
+                unsafe { (CStr::from_ptr((*entry).d_name.as_ptr()), (*entry).d_type) };
+            if name.to_bytes() == b"." || name.to_bytes() == b".." {
+                continue;
+            }
+
+            return Some(Ok(DirEntry {
+                stream: Arc::clone(&self.stream),
+                name: name.to_owned(),
+                d_type,
+            }));
+        }
+
+        None
+    }
+}
+
+impl fmt::Debug for ReadDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ReadDir").field(&self.stream.path).finish()
+    }
+}
+
+/// 目录中的一个条目，持有目录流的引用，file_type/metadata相对于目录fd查询
+pub struct DirEntry {
+    stream: Arc<DirStream>,
+    name: CString,
+    d_type: u8,
+}
+
+impl DirEntry {
+    /// read_dir传入的路径与文件名拼接得到的路径
+    pub fn path(&self) -> PathBuf {
+        self.stream.path.join(self.file_name())
+    }
+
+    pub fn file_name(&self) -> &OsStr {
+        OsStr::from_bytes(self.name.to_bytes())
+    }
+
+    /*
+        条目的类型，不跟随符号链接
+        大多数文件系统在dirent的d_type中直接给出类型，不需要额外的系统调用
+        d_type为DT_UNKNOWN时(例如部分XFS、网络文件系统)退回fstatat查询
+    */
+    pub fn file_type(&self) -> Result<FileType> {
+        let kind = match self.d_type {
+            libc::DT_REG => libc::S_IFREG,
+            libc::DT_DIR => libc::S_IFDIR,
+            libc::DT_LNK => libc::S_IFLNK,
+            libc::DT_FIFO => libc::S_IFIFO,
+            libc::DT_SOCK => libc::S_IFSOCK,
+            libc::DT_BLK => libc::S_IFBLK,
+            libc::DT_CHR => libc::S_IFCHR,
+            _ => return Ok(self.metadata()?.file_type()),
+        };
+
+        // macOS上S_IF*为u16
+        #[allow(clippy::unnecessary_cast)]
+        let mode = kind as u32;
+
+        Ok(FileType::from_mode(mode))
+    }
+
+    /*
+        条目的元信息，不跟随符号链接，等价于对path()调用symlink_metadata
+        fstatat(dirfd: i32, path: *const c_char, buf: *mut stat, flags: i32) -> i32
+        相对于目录fd查询，目录本身被移动后仍然查到的是同一个条目
+    */
+    pub fn metadata(&self) -> Result<Metadata> {
+        let mut stat: sys::stat = unsafe { std::mem::zeroed() };
+        let result = unsafe {
+            sys::fstatat(
+                libc::dirfd(self.stream.dirp),
+                self.name.as_ptr(),
+                &mut stat,
+                libc::AT_SYMLINK_NOFOLLOW,
+            )
+        };
+        if result < 0 {
+            return Err(error::with_context(
+                io::Error::last_os_error(),
+                "fstatat",
+                Some(&self.path()),
+            ));
+        }
+
+        Ok(Metadata::from_stat(stat))
+    }
+}
+
+impl fmt::Debug for DirEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DirEntry").field(&self.path()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Dir, DirBuilder, create_dir, create_dir_all, read_dir, remove_dir, remove_dir_all,
+    };
+    use crate::temp::TempDir;
+    use crate::{OpenMode, OpenOptions, is_dir, metadata, mkfifo, symlink, write};
+    use std::ffi::OsString;
+    use std::io::{self, Read};
+
+    #[test]
+    fn test_create_dir() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("sub");
+        create_dir(&path)?;
+        assert!(is_dir(&path)?);
+
+        let result = create_dir(&path);
+        assert!(result.is_err(), "Existing directory should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::AlreadyExists,
+                "Error should be AlreadyExists"
+            );
+        }
+
+        let result = create_dir(dir.path().join("missing/sub"));
+        assert!(result.is_err(), "Missing parent should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::NotFound,
+                "Error should be NotFound"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_dir_all() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("a/b/c");
+        create_dir_all(&path)?;
+        assert!(is_dir(&path)?);
+
+        // 已存在时直接成功
+        create_dir_all(&path)?;
+
+        // 路径中有一级是普通文件
+        let file = dir.path().join("file");
+        write(&file, b"content")?;
+        assert!(create_dir_all(&file).is_err(), "File is not a directory");
+        assert!(create_dir_all(file.join("sub")).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_dir_builder_mode() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("x/y");
+        DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(&path)?;
+
+        // umask只会去掉权限位，0o700不受通常的umask影响
+        assert_eq!(metadata(&path)?.permissions().mode() & 0o777, 0o700);
+        assert_eq!(
+            metadata(dir.path().join("x"))?.permissions().mode() & 0o777,
+            0o700
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_dir() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("sub");
+        create_dir(&path)?;
+        write(path.join("file"), b"content")?;
+
+        let result = remove_dir(&path);
+        assert!(result.is_err(), "Non-empty directory should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::DirectoryNotEmpty,
+                "Error should be DirectoryNotEmpty"
+            );
+        }
+
+        crate::remove_file(path.join("file"))?;
+        remove_dir(&path)?;
+        assert!(!crate::exists(&path)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_dir_all() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let root = dir.path().join("root");
+        create_dir_all(root.join("a/b/c"))?;
+        write(root.join("a/file"), b"content")?;
+        write(root.join("a/b/c/file"), b"content")?;
+
+        // 指向外部目录的符号链接只删除链接本身
+        let outside = dir.path().join("outside");
+        create_dir(&outside)?;
+        write(outside.join("keep"), b"content")?;
+        symlink(&outside, root.join("a/link"))?;
+
+        remove_dir_all(&root)?;
+        assert!(!crate::exists(&root)?);
+        assert!(
+            crate::exists(outside.join("keep"))?,
+            "Link target should be kept"
+        );
+
+        // 路径本身是符号链接
+        let link = dir.path().join("link");
+        symlink(&outside, &link)?;
+        remove_dir_all(&link)?;
+        assert!(!crate::exists(&link)?);
+        assert!(is_dir(&outside)?);
+
+        let result = remove_dir_all(&root);
+        assert!(result.is_err(), "Missing directory should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::NotFound,
+                "Error should be NotFound"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_dir() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        write(dir.path().join("file.txt"), b"content")?;
+        create_dir(dir.path().join("sub"))?;
+        symlink("file.txt", dir.path().join("link"))?;
+        mkfifo(dir.path().join("fifo"), 0o644)?;
+
+        let mut entries = read_dir(dir.path())?.collect::<crate::Result<Vec<_>>>()?;
+        entries.sort_by(|a, b| a.file_name().cmp(b.file_name()));
+
+        let names: Vec<OsString> = entries.iter().map(|e| e.file_name().to_owned()).collect();
+        assert_eq!(names, ["fifo", "file.txt", "link", "sub"], "No . or ..");
+
+        assert!(entries[0].file_type()?.is_fifo());
+        assert!(entries[1].file_type()?.is_file());
+        // 不跟随符号链接
+        assert!(entries[2].file_type()?.is_symlink());
+        assert!(entries[2].metadata()?.is_symlink());
+        assert!(entries[3].file_type()?.is_dir());
+
+        assert_eq!(entries[1].path(), dir.path().join("file.txt"));
+        assert_eq!(entries[1].metadata()?.len(), 7);
+
+        // 条目在ReadDir之后仍然可以使用
+        let entry = read_dir(dir.path())?
+            .next()
+            .expect("Directory is not empty")?;
+        assert!(entry.metadata().is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_dir_errors() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        assert_eq!(read_dir(dir.path())?.count(), 0, "Empty directory");
+
+        let file = dir.path().join("file.txt");
+        write(&file, b"content")?;
+        let result = read_dir(&file);
+        assert!(result.is_err(), "File is not a directory");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::NotADirectory,
+                "Error should be NotADirectory"
+            );
+        }
+
+        let result = read_dir(dir.path().join("missing"));
+        assert!(result.is_err(), "Missing directory should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::NotFound,
+                "Error should be NotFound"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_dir_at() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = Dir::open(temp_dir.path())?;
+
+        dir.create_dir_at("sub", 0o755)?;
+        assert!(dir.metadata_at("sub")?.is_dir());
+
+        let mut file = dir.open_at("sub/file.txt", OpenMode::Write)?;
+        file.write_all(b"content")?;
+        assert_eq!(
+            file.path(),
+            Some(temp_dir.path().join("sub/file.txt").as_path())
+        );
+
+        let sub = dir.open_dir_at("sub")?;
+        sub.rename_at("file.txt", &dir, "moved.txt")?;
+        let mut content = String::new();
+        dir.open_at("moved.txt", OpenMode::Read)?
+            .read_to_string(&mut content)?;
+        assert_eq!(content, "content");
+
+        dir.sync_all()?;
+        sub.sync_all()?;
+
+        // 目录被重命名后，句柄仍然指向原来的目录
+        let renamed = temp_dir.path().join("renamed");
+        std::fs::rename(temp_dir.path().join("sub"), &renamed)?;
+        sub.open_with("new.txt", OpenOptions::new().write(true).create_new(true))?;
+        assert!(crate::exists(renamed.join("new.txt"))?);
+
+        symlink("moved.txt", temp_dir.path().join("link"))?;
+        assert!(dir.metadata_at("link")?.is_file());
+        assert!(dir.symlink_metadata_at("link")?.is_symlink());
+
+        // 每次read_dir都从头开始
+        assert_eq!(sub.read_dir()?.count(), 1);
+        assert_eq!(sub.read_dir()?.count(), 1);
+
+        dir.unlink_at("link")?;
+        let result = dir.unlink_at("renamed");
+        assert!(result.is_err(), "Directory should not be unlinked");
+        sub.unlink_at("new.txt")?;
+        dir.remove_dir_at("renamed")?;
+        assert!(!crate::exists(&renamed)?);
+
+        let result = dir.metadata_at("missing");
+        assert!(result.is_err(), "Missing file should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::NotFound,
+                "Error should be NotFound"
+            );
+            assert!(
+                e.to_string().contains("missing"),
+                "Error should show the path"
+            );
+        }
+
+        let result = Dir::open(temp_dir.path().join("moved.txt"));
+        assert!(result.is_err(), "File is not a directory");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,240 @@
+/*
+    系统调用失败时的结构化错误，记录失败的系统调用、路径、fd和errno
+    例如"open /etc/shadow: Permission denied (os error 13)"
+    单独的errno只能说明发生了什么，不能说明是哪个文件、哪一步出的错
+
+    公开接口返回simple_file::Result<T>，即Result<T, simple_file::Error>，通过Error::raw_os_error取得errno
+    参数校验等不是由系统调用产生的错误也用Error表示，此时operation为None
+
+    Read/Write等标准trait只能返回io::Error，Error可以通过?或into转换为io::Error：
+    不带上下文的错误原样转换，带上下文的错误作为io::Error的内部错误，ErrorKind与原始错误相同，
+    可以用Error::from_io取回；io::Error转换回Error时同样会取出内部的Error，不会丢失上下文
+    此时io::Error::raw_os_error()返回None，需要通过errno(&io::Error)获取errno
+*/
+
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// simple_file中所有公开接口的返回值
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub struct Error {
+    op: Option<&'static str>,
+    path: Option<PathBuf>,
+    fd: Option<i32>,
+    source: io::Error,
+}
+
+impl Error {
+    // 不是由系统调用产生的错误，例如参数校验失败
+    pub(crate) fn new<E>(kind: io::ErrorKind, error: E) -> Error
+    where
+        E: Into<Box<dyn error::Error + Send + Sync>>,
+    {
+        Error::from(io::Error::new(kind, error))
+    }
+
+    /// 失败的系统调用，例如"open"、"read"、"ftruncate"，不是由系统调用产生的错误(例如InvalidInput)为None
+    pub fn operation(&self) -> Option<&'static str> {
+        self.op
+    }
+
+    /// 涉及的路径，通过fd得到的File没有路径
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// 涉及的文件描述符，基于路径的操作(例如open、stat)没有fd
+    pub fn fd(&self) -> Option<i32> {
+        self.fd
+    }
+
+    /// 系统调用失败时的errno，与io::Error::raw_os_error相同
+    pub fn raw_os_error(&self) -> Option<i32> {
+        self.source.raw_os_error()
+    }
+
+    pub fn kind(&self) -> io::ErrorKind {
+        self.source.kind()
+    }
+
+    /// 不带上下文的原始错误
+    pub fn io_error(&self) -> &io::Error {
+        &self.source
+    }
+
+    /// 从io::Error中取出结构化的错误，不带上下文的io::Error返回None
+    pub fn from_io(err: &io::Error) -> Option<&Error> {
+        err.get_ref()?.downcast_ref::<Error>()
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.op, &self.path) {
+            (Some(op), Some(path)) => write!(f, "{} {}: {}", op, path.display(), self.source),
+            (Some(op), None) => write!(f, "{}: {}", op, self.source),
+            (None, _) => self.source.fmt(f),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self.op {
+            Some(_) => Some(&self.source),
+            None => self.source.source(),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        if Error::from_io(&err).is_some() {
+            // 上面已经确认内部错误的类型
+            return *err.into_inner().unwrap().downcast::<Error>().unwrap();
+        }
+
+        Error {
+            op: None,
+            path: None,
+            fd: None,
+            source: err,
+        }
+    }
+}
+
+impl From<std::ffi::NulError> for Error {
+    fn from(err: std::ffi::NulError) -> Error {
+        Error::from(io::Error::from(err))
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> io::Error {
+        match err.op {
+            Some(_) => io::Error::new(err.kind(), err),
+            None => err.source,
+        }
+    }
+}
+
+// 为err附带系统调用名、路径和fd，保留原来的ErrorKind
+pub(crate) fn with_fd_context(
+    err: io::Error,
+    op: &'static str,
+    path: Option<&Path>,
+    fd: Option<i32>,
+) -> Error {
+    Error {
+        op: Some(op),
+        path: path.map(Path::to_path_buf),
+        fd,
+        source: err,
+    }
+}
+
+// 基于路径、没有fd的操作
+pub(crate) fn with_context(err: io::Error, op: &'static str, path: Option<&Path>) -> Error {
+    with_fd_context(err, op, path, None)
+}
+
+/*
+    取出io::Error中的errno，包括作为内部错误包装的Error
+    Read/Write等标准trait返回的错误带有上下文时，io::Error::raw_os_error()返回None，需要通过这里获取
+*/
+pub fn errno(err: &io::Error) -> Option<i32> {
+    err.raw_os_error()
+        .or_else(|| Error::from_io(err).and_then(Error::raw_os_error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, errno, with_context};
+    use crate::temp::TempDir;
+    use crate::{File, OpenMode, metadata};
+    use std::io;
+    use std::path::Path;
+
+    #[test]
+    fn test_error_context() -> io::Result<()> {
+        let err = with_context(
+            io::Error::from_raw_os_error(libc::EACCES),
+            "open",
+            Some(Path::new("/etc/shadow")),
+        );
+        assert_eq!(
+            err.kind(),
+            io::ErrorKind::PermissionDenied,
+            "Kind should be kept"
+        );
+        assert_eq!(err.raw_os_error(), Some(libc::EACCES));
+        assert!(err.to_string().starts_with("open /etc/shadow: "));
+
+        let dir = TempDir::new()?;
+        let missing = dir.path().join("missing");
+        let result = File::open(&missing, OpenMode::Read);
+        assert!(result.is_err(), "Opening missing file should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::NotFound,
+                "Error should be NotFound"
+            );
+            assert!(
+                e.to_string().contains(missing.to_str().unwrap()),
+                "Error should contain the path"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_structured_error() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("file.txt");
+        std::fs::write(&path, b"Hello, world!")?;
+
+        // 只读打开的文件不能写入
+        let mut file = File::open(&path, OpenMode::Read)?;
+        let err = file.write(b"x").unwrap_err();
+        assert_eq!(err.operation(), Some("write"));
+        assert_eq!(err.path(), Some(path.as_path()));
+        assert_eq!(err.fd(), Some(file.fd));
+        assert_eq!(err.raw_os_error(), Some(libc::EBADF));
+
+        // 转换为io::Error后可以取回，再转换回来时上下文不丢失
+        let io_err = io::Error::from(err);
+        assert_eq!(io_err.kind(), file.write(b"x").unwrap_err().kind());
+        let inner = Error::from_io(&io_err).expect("Syscall error should be structured");
+        assert_eq!(inner.raw_os_error(), Some(libc::EBADF));
+        assert_eq!(io_err.raw_os_error(), None);
+        assert_eq!(errno(&io_err), Some(libc::EBADF));
+        let err = Error::from(io_err);
+        assert_eq!(err.operation(), Some("write"));
+        assert_eq!(err.raw_os_error(), Some(libc::EBADF));
+
+        // 基于路径的操作没有fd
+        let err = metadata(dir.path().join("missing")).unwrap_err();
+        assert_eq!(err.operation(), Some("stat"));
+        assert_eq!(err.fd(), None);
+        assert_eq!(err.raw_os_error(), Some(libc::ENOENT));
+
+        // 参数校验失败不是系统调用错误，转换为io::Error时原样返回
+        let result = File::open("", OpenMode::Read);
+        assert!(result.is_err(), "Opening empty path should fail");
+        if let Err(e) = result {
+            assert_eq!(e.operation(), None, "Invalid input is not a syscall error");
+            assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+            let io_err = io::Error::from(e);
+            assert!(Error::from_io(&io_err).is_none());
+            assert_eq!(io_err.kind(), io::ErrorKind::InvalidInput);
+        }
+
+        Ok(())
+    }
+}
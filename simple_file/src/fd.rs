@@ -0,0 +1,232 @@
+/*
+    与std::os::fd互操作，使File可以交给nix、polling、进程创建等需要底层描述符的代码
+
+    AsRawFd/AsFd只借用描述符，IntoRawFd/OwnedFd转移所有权，之后File不再负责关闭它
+
+    Linux下/proc/self/fd/N是指向fd所打开文件的符号链接，readlink它就能从fd反查路径
+*/
+
+use std::fmt;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd};
+#[cfg(target_os = "linux")]
+use std::path::PathBuf;
+
+#[cfg(target_os = "linux")]
+use crate::Result;
+use crate::{File, INVALID_FD};
+
+impl File {
+    // 接管一个外部的描述符，Linux下通过F_GETFL判断它是否以O_PATH打开
+    fn from_owned_raw_fd(fd: RawFd) -> File {
+        #[cfg(target_os = "linux")]
+        let path_only = {
+            let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+            flags >= 0 && flags & libc::O_PATH != 0
+        };
+        #[cfg(not(target_os = "linux"))]
+        let path_only = false;
+
+        let mut file = File::from_fd(fd);
+        file.path_only = path_only;
+
+        file
+    }
+
+    /*
+        fd当前指向的路径，文件被rename后返回新路径，被删除时结尾带有" (deleted)"
+        与path()不同，通过fd、tmpfile得到的File也能查到，管道、套接字返回"pipe:[N]"之类的名字
+    */
+    #[cfg(target_os = "linux")]
+    pub fn resolved_path(&self) -> Result<PathBuf> {
+        self.ensure_open()?;
+        crate::read_link(format!("/proc/self/fd/{}", self.fd))
+    }
+}
+
+// 只输出已经记录的信息，Debug不应该有系统调用，需要fd当前指向的路径时调用resolved_path
+impl fmt::Debug for File {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("File");
+        d.field("fd", &self.fd);
+        if let Some(path) = self.path() {
+            d.field("path", &path);
+        }
+        if self.path_only {
+            d.field("path_only", &true);
+        }
+        d.finish()
+    }
+}
+
+impl AsRawFd for File {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+// 安全代码拿到的File总是持有有效的fd：打开失败不会得到File，关闭只发生在Drop/into_raw_fd消耗self时
+impl AsFd for File {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        unsafe { BorrowedFd::borrow_raw(self.fd) }
+    }
+}
+
+impl FromRawFd for File {
+    /// 与OwnedFd一样，fd必须是打开的描述符，传入-1会panic
+    unsafe fn from_raw_fd(fd: RawFd) -> File {
+        assert_ne!(fd, INVALID_FD, "fd must not be -1");
+        File::from_owned_raw_fd(fd)
+    }
+}
+
+impl IntoRawFd for File {
+    fn into_raw_fd(mut self) -> RawFd {
+        // 置为INVALID_FD，Drop时就不会关闭已经交出去的描述符
+        std::mem::replace(&mut self.fd, INVALID_FD)
+    }
+}
+
+impl From<OwnedFd> for File {
+    fn from(fd: OwnedFd) -> File {
+        File::from_owned_raw_fd(fd.into_raw_fd())
+    }
+}
+
+impl From<File> for OwnedFd {
+    fn from(file: File) -> OwnedFd {
+        unsafe { OwnedFd::from_raw_fd(file.into_raw_fd()) }
+    }
+}
+
+// 与std::fs::File互相转换，描述符的所有权经由OwnedFd转移，不会被重复关闭
+impl From<std::fs::File> for File {
+    fn from(file: std::fs::File) -> File {
+        File::from(OwnedFd::from(file))
+    }
+}
+
+impl From<File> for std::fs::File {
+    fn from(file: File) -> std::fs::File {
+        std::fs::File::from(OwnedFd::from(file))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(target_os = "linux")]
+    use crate::temp::TempDir;
+    use crate::temp::TempFile;
+    use crate::{File, OpenMode};
+    use std::io::{self, Read, Write};
+    use std::os::fd::{AsFd, AsRawFd, FromRawFd, IntoRawFd, OwnedFd};
+
+    #[test]
+    fn test_raw_fd_round_trip() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let file = File::open(temp_file.path(), OpenMode::ReadWrite)?;
+        let raw = file.as_raw_fd();
+        assert_eq!(file.as_fd().as_raw_fd(), raw);
+
+        // 交出描述符后File的Drop不会关闭它
+        let fd = file.into_raw_fd();
+        assert_eq!(fd, raw);
+        let mut file = unsafe { File::from_raw_fd(fd) };
+        file.write_all(b"Hello, world!")?;
+
+        assert_eq!(std::fs::read(temp_file.path())?, b"Hello, world!");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_owned_fd_conversion() -> io::Result<()> {
+        let mut temp_file = TempFile::new()?;
+        temp_file.file_mut().write_all(b"Hello, world!")?;
+
+        let file = File::open(temp_file.path(), OpenMode::Read)?;
+        let owned: OwnedFd = file.into();
+        let mut file = File::from(owned);
+
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+        assert_eq!(content, "Hello, world!");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_std_file_conversion() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+
+        let std_file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(temp_file.path())?;
+        let mut file = File::from(std_file);
+        file.write_all(b"Hello, ")?;
+
+        // 读写位置随描述符一起保留
+        let mut std_file = std::fs::File::from(file);
+        std_file.write_all(b"world!")?;
+        drop(std_file);
+
+        assert_eq!(std::fs::read(temp_file.path())?, b"Hello, world!");
+
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_resolved_path() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("before.txt");
+        let file = File::open(&path, OpenMode::Write)?;
+        assert_eq!(file.resolved_path()?, path);
+
+        // 重命名后fd指向新的路径，打开时记录的path不变
+        let renamed = dir.path().join("after.txt");
+        std::fs::rename(&path, &renamed)?;
+        assert_eq!(file.resolved_path()?, renamed);
+        assert_eq!(file.path(), Some(path.as_path()));
+
+        // 通过fd得到的File没有path，只能通过resolved_path查询，Debug不会去解析
+        let file = File::from(std::fs::File::open(&renamed)?);
+        assert_eq!(file.path(), None);
+        assert_eq!(file.resolved_path()?, renamed);
+        let debug = format!("{:?}", file);
+        assert!(
+            !debug.contains("after.txt"),
+            "Debug should not resolve the path"
+        );
+
+        let result = File::from_fd(-1).resolved_path();
+        assert!(result.is_err(), "Closed file has no path");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_debug() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let file = File::open(temp_file.path(), OpenMode::Read)?;
+        let debug = format!("{:?}", file);
+        assert!(debug.starts_with("File { fd: "), "Debug should show the fd");
+        assert!(debug.contains(&format!("{:?}", temp_file.path())));
+
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_owned_fd_keeps_path_only() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let file = File::open_path(temp_file.path())?;
+        let mut file = File::from(OwnedFd::from(file));
+
+        let mut buf = [0u8; 4];
+        let result = file.read(&mut buf);
+        assert!(result.is_err(), "O_PATH fd should still be rejected");
+
+        Ok(())
+    }
+}
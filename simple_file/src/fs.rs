@@ -0,0 +1,1326 @@
+/*
+    基于路径的便捷函数，对应std::fs中的同名自由函数
+    内部全部通过本crate的File实现
+*/
+
+use std::ffi::{CStr, OsString};
+use std::io;
+use std::os::unix::ffi::OsStringExt;
+use std::path::{Path, PathBuf};
+
+use crate::metadata::owner_ids;
+use crate::temp::TempFile;
+use crate::{Error, File, FileTimes, Metadata, Permissions, Result, error, sys, to_c_path};
+
+const DEFAULT_COPY_CHUNK_SIZE: usize = 128 * 1024; // 默认复制块大小 128KB
+
+/// 读取文件的全部字节
+/// step1: 只读打开
+/// step2: fstat获取文件大小，一次性分配好缓冲区，避免按倍数扩容
+/// step3: 读到EOF为止，/proc等st_size为0或不准确的文件也能完整读取
+pub fn read<P: AsRef<Path>>(path: P) -> Result<Vec<u8>> {
+    let mut file = File::open_read(path)?;
+    let size = file.metadata().map_or(0, |meta| meta.len() as usize);
+
+    let mut bytes = Vec::new();
+    file.read_to_end_with_capacity(&mut bytes, size)?;
+
+    Ok(bytes)
+}
+
+/// 读取文件的全部内容为String，读完后统一校验一次UTF-8，非UTF-8内容返回InvalidData
+pub fn read_to_string<P: AsRef<Path>>(path: P) -> Result<String> {
+    let bytes = read(path)?;
+
+    String::from_utf8(bytes)
+        .map_err(|_| Error::new(io::ErrorKind::InvalidData, "Invalid UTF-8 data"))
+}
+
+/// 将data全部写入文件，文件不存在则创建，存在则截断
+pub fn write<P: AsRef<Path>>(path: P, data: &[u8]) -> Result<()> {
+    let mut file = File::create(path)?;
+    // write可能只写入一部分，write_all会循环直到全部写完
+    file.write_all(data)
+}
+
+/// 将src的全部内容复制到dst，dst不存在则创建，存在则截断，dst的权限设置为与src相同
+/// 返回复制的字节数
+/// Linux下使用sendfile在内核中完成复制，src不支持sendfile时退回read/write循环
+pub fn copy<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> Result<u64> {
+    let (mut reader, mut writer) = open_copy_pair(src.as_ref(), dst.as_ref())?;
+
+    #[cfg(target_os = "linux")]
+    if let Some(total) = copy_by_sendfile(&mut reader, &mut writer)? {
+        return Ok(total);
+    }
+
+    copy_by_read_write(
+        &mut reader,
+        &mut writer,
+        DEFAULT_COPY_CHUNK_SIZE,
+        &mut |_| {},
+    )
+}
+
+/// 与copy相同，但由调用方指定每次read/write的块大小，块大小不能为0，总是在用户态复制
+pub fn copy_with_buffer_size<P: AsRef<Path>, Q: AsRef<Path>>(
+    src: P,
+    dst: Q,
+    chunk: usize,
+) -> Result<u64> {
+    if chunk == 0 {
+        return Err(Error::new(
+            io::ErrorKind::InvalidInput,
+            "Invalid chunk size, zero not allowed",
+        ));
+    }
+
+    let (mut reader, mut writer) = open_copy_pair(src.as_ref(), dst.as_ref())?;
+    copy_by_read_write(&mut reader, &mut writer, chunk, &mut |_| {})
+}
+
+// 打开复制的两端，并把src的权限(包括setuid等位)应用到dst上，与std::fs::copy一致
+fn open_copy_pair(src: &Path, dst: &Path) -> Result<(File, File)> {
+    let reader = File::open_read(src)?;
+    let perm = reader.metadata()?.permissions();
+
+    let mut writer = File::create(dst)?;
+    writer.set_permissions(perm)?;
+
+    Ok((reader, writer))
+}
+
+// 每写入一块调用一次progress，参数为已经复制的字节数
+fn copy_by_read_write(
+    reader: &mut File,
+    writer: &mut File,
+    chunk: usize,
+    progress: &mut dyn FnMut(u64),
+) -> Result<u64> {
+    let mut buf = vec![0u8; chunk];
+    let mut total = 0u64;
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => return Ok(total),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+
+        writer.write_all(&buf[..n])?;
+        total += n as u64;
+        progress(total);
+    }
+}
+
+/*
+    sendfile(out_fd: i32, in_fd: i32, offset: *mut off_t, count: size_t) -> ssize_t
+    offset为NULL时从in_fd的当前位置读取并推进位置，返回0表示已到末尾
+    单次最多传输0x7ffff000字节，所以需要循环；不依赖st_size，/proc等大小未知的文件也能复制完整
+    第一次调用就返回EINVAL/ENOSYS说明in_fd不支持sendfile，返回None由调用方退回read/write
+*/
+#[cfg(target_os = "linux")]
+fn copy_by_sendfile(reader: &mut File, writer: &mut File) -> Result<Option<u64>> {
+    const MAX_SENDFILE: usize = 0x7fff_f000;
+
+    let mut total = 0u64;
+    loop {
+        let n = crate::retry_eintr(true, || unsafe {
+            libc::sendfile(writer.fd, reader.fd, std::ptr::null_mut(), MAX_SENDFILE)
+        });
+        if n < 0 {
+            let err = writer.record_os_error("sendfile");
+            if total == 0 && matches!(err.raw_os_error(), Some(libc::EINVAL | libc::ENOSYS)) {
+                return Ok(None);
+            }
+            return Err(err);
+        }
+        if n == 0 {
+            return Ok(Some(total));
+        }
+        total += n as u64;
+    }
+}
+
+/*
+    reflink复制：dst与src共享磁盘块(写时复制)，瞬间完成且不占用额外空间，之后任意一方被修改才会分开
+    Linux: ioctl(dst_fd, FICLONE, src_fd)，btrfs、XFS(reflink=1)、bcachefs支持
+    macOS: clonefile(src: *const c_char, dst: *const c_char, flags: u32) -> i32，APFS支持
+
+    dst必须不存在，已存在时返回AlreadyExists；dst的权限与src相同
+    文件系统不支持时返回Unsupported(EOPNOTSUPP)，跨文件系统时返回CrossesDevices(EXDEV)，调用方可以据此退回copy
+*/
+#[cfg(target_os = "linux")]
+pub fn reflink<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> Result<()> {
+    let reader = File::open_read(src)?;
+    let mut writer = File::create_new(dst.as_ref())?;
+    writer.set_permissions(reader.metadata()?.permissions())?;
+
+    let result = unsafe { libc::ioctl(writer.fd, libc::FICLONE, reader.fd) };
+    if result < 0 {
+        let err = writer.record_os_error("ioctl(FICLONE)");
+        // 失败时不留下空的dst
+        if let Ok(path) = to_c_path(dst.as_ref()) {
+            unsafe { libc::unlink(path.as_ptr()) };
+        }
+        return Err(err);
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+pub fn reflink<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> Result<()> {
+    let from = to_c_path(src.as_ref())?;
+    let to = to_c_path(dst.as_ref())?;
+
+    let result = unsafe { libc::clonefile(from.as_ptr(), to.as_ptr(), 0) };
+    if result < 0 {
+        return Err(error::with_context(
+            io::Error::last_os_error(),
+            "clonefile",
+            Some(src.as_ref()),
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn reflink<P: AsRef<Path>, Q: AsRef<Path>>(_src: P, _dst: Q) -> Result<()> {
+    Err(Error::new(
+        io::ErrorKind::Unsupported,
+        "reflink is not supported on this platform",
+    ))
+}
+
+/*
+    stat(path: *const c_char, buf: *mut stat) -> i32
+    只查询路径对应的元信息，不需要打开文件，没有fd，也不会截断或更新atime
+*/
+fn stat(path: &Path) -> Result<sys::stat> {
+    let c_style_str_path = to_c_path(path)?;
+
+    let mut stat: sys::stat = unsafe { std::mem::zeroed() };
+    let result = unsafe { sys::stat(c_style_str_path.as_ptr(), &mut stat) };
+    if result < 0 {
+        return Err(error::with_context(
+            io::Error::last_os_error(),
+            "stat",
+            Some(path),
+        ));
+    }
+
+    Ok(stat)
+}
+
+// 与stat相同，但路径本身是符号链接时返回链接自身的信息，不跟随
+fn lstat(path: &Path) -> Result<sys::stat> {
+    let c_style_str_path = to_c_path(path)?;
+
+    let mut stat: sys::stat = unsafe { std::mem::zeroed() };
+    let result = unsafe { sys::lstat(c_style_str_path.as_ptr(), &mut stat) };
+    if result < 0 {
+        return Err(error::with_context(
+            io::Error::last_os_error(),
+            "lstat",
+            Some(path),
+        ));
+    }
+
+    Ok(stat)
+}
+
+/// 获取路径的元信息，符号链接会被跟随
+pub fn metadata<P: AsRef<Path>>(path: P) -> Result<Metadata> {
+    Ok(Metadata::from_stat(stat(path.as_ref())?))
+}
+
+/// 获取路径的元信息，路径是符号链接时返回链接自身的信息
+pub fn symlink_metadata<P: AsRef<Path>>(path: P) -> Result<Metadata> {
+    Ok(Metadata::from_stat(lstat(path.as_ref())?))
+}
+
+/// 修改路径对应文件的权限，符号链接会被跟随，封装POSIX chmod
+pub fn set_permissions<P: AsRef<Path>>(path: P, perm: Permissions) -> Result<()> {
+    let c_style_str_path = to_c_path(path.as_ref())?;
+
+    let result = unsafe { libc::chmod(c_style_str_path.as_ptr(), perm.mode() as libc::mode_t) };
+    if result < 0 {
+        return Err(error::with_context(
+            io::Error::last_os_error(),
+            "chmod",
+            Some(path.as_ref()),
+        ));
+    }
+
+    Ok(())
+}
+
+/// 修改路径对应文件的所有者和所属组，符号链接会被跟随，为None的一项保持不变，封装POSIX chown
+pub fn chown<P: AsRef<Path>>(path: P, uid: Option<u32>, gid: Option<u32>) -> Result<()> {
+    let c_style_str_path = to_c_path(path.as_ref())?;
+    let (uid, gid) = owner_ids(uid, gid);
+
+    let result = unsafe { libc::chown(c_style_str_path.as_ptr(), uid, gid) };
+    if result < 0 {
+        return Err(error::with_context(
+            io::Error::last_os_error(),
+            "chown",
+            Some(path.as_ref()),
+        ));
+    }
+
+    Ok(())
+}
+
+/// 与chown相同，但路径是符号链接时修改链接本身，封装POSIX lchown
+pub fn lchown<P: AsRef<Path>>(path: P, uid: Option<u32>, gid: Option<u32>) -> Result<()> {
+    let c_style_str_path = to_c_path(path.as_ref())?;
+    let (uid, gid) = owner_ids(uid, gid);
+
+    let result = unsafe { libc::lchown(c_style_str_path.as_ptr(), uid, gid) };
+    if result < 0 {
+        return Err(error::with_context(
+            io::Error::last_os_error(),
+            "lchown",
+            Some(path.as_ref()),
+        ));
+    }
+
+    Ok(())
+}
+
+/*
+    重命名或移动文件/目录，封装POSIX rename
+    rename(old: *const c_char, new: *const c_char) -> i32
+    to已存在时被原子地替换，任何时刻to要么是旧文件要么是新文件；from与to必须位于同一个文件系统(否则EXDEV)
+*/
+pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<()> {
+    let old = to_c_path(from.as_ref())?;
+    let new = to_c_path(to.as_ref())?;
+
+    let result = unsafe { libc::rename(old.as_ptr(), new.as_ptr()) };
+    if result < 0 {
+        return Err(error::with_context(
+            io::Error::last_os_error(),
+            "rename",
+            Some(from.as_ref()),
+        ));
+    }
+
+    Ok(())
+}
+
+/// 与rename相同，但to已存在时失败(AlreadyExists)而不是替换，检查与重命名是原子的，适合"发布"一个新文件
+#[cfg(target_os = "linux")]
+pub fn rename_noreplace<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<()> {
+    renameat2(from.as_ref(), to.as_ref(), libc::RENAME_NOREPLACE)
+}
+
+/// 原子地交换两个路径，两者都必须存在，可以是不同类型(例如一个文件和一个目录)，适合整体替换一个目录
+#[cfg(target_os = "linux")]
+pub fn rename_exchange<P: AsRef<Path>, Q: AsRef<Path>>(a: P, b: Q) -> Result<()> {
+    renameat2(a.as_ref(), b.as_ref(), libc::RENAME_EXCHANGE)
+}
+
+/*
+    renameat2(olddirfd: i32, oldpath: *const c_char, newdirfd: i32, newpath: *const c_char, flags: u32) -> i32 (Linux 3.15)
+    不支持这些flags的文件系统返回EINVAL
+*/
+#[cfg(target_os = "linux")]
+fn renameat2(from: &Path, to: &Path, flags: libc::c_uint) -> Result<()> {
+    let old = to_c_path(from)?;
+    let new = to_c_path(to)?;
+
+    let result = unsafe {
+        libc::renameat2(
+            libc::AT_FDCWD,
+            old.as_ptr(),
+            libc::AT_FDCWD,
+            new.as_ptr(),
+            flags,
+        )
+    };
+    if result < 0 {
+        return Err(error::with_context(
+            io::Error::last_os_error(),
+            "renameat2",
+            Some(from),
+        ));
+    }
+
+    Ok(())
+}
+
+/*
+    移动文件，先尝试rename，src与dst位于不同的文件系统(EXDEV)时退回复制+删除
+    复制时保留权限、访问/修改时间、扩展属性(包括ACL，Linux)，有权限时保留所有者
+
+    与rename的语义保持一致：先复制到dst所在目录中的临时文件，fsync后再rename覆盖dst，最后才删除src
+    中途失败时只删除临时文件，已存在的dst和src都保持不变
+    src是符号链接时移动链接本身，同样先以临时名字创建再rename；跨文件系统移动目录不支持，返回CrossesDevices
+*/
+pub fn move_file<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> Result<()> {
+    move_file_with_progress(src, dst, |_, _| {})
+}
+
+/// 与move_file相同，跨文件系统复制时每写入一块调用一次progress(已复制的字节数, 总字节数)
+pub fn move_file_with_progress<P: AsRef<Path>, Q: AsRef<Path>, F: FnMut(u64, u64)>(
+    src: P,
+    dst: Q,
+    mut progress: F,
+) -> Result<()> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+
+    let err = match rename(src, dst) {
+        Err(e) if e.raw_os_error() == Some(libc::EXDEV) => e,
+        result => return result,
+    };
+
+    let meta = symlink_metadata(src)?;
+    if meta.is_dir() {
+        return Err(err);
+    }
+    if meta.is_symlink() {
+        return move_symlink(src, dst);
+    }
+
+    move_by_copy(src, dst, &mut progress)
+}
+
+// 复制到dst所在目录中的临时文件，rename覆盖dst；失败时TempFile在Drop中删除临时文件
+fn move_by_copy(src: &Path, dst: &Path, progress: &mut dyn FnMut(u64, u64)) -> Result<()> {
+    let mut reader = File::open_read(src)?;
+    let mut temp = TempFile::new_in(crate::dir::parent_dir(dst))?;
+    let writer = temp.file_mut();
+
+    let meta = reader.metadata()?;
+    let total = meta.len();
+    copy_by_read_write(
+        &mut reader,
+        writer,
+        DEFAULT_COPY_CHUNK_SIZE,
+        &mut |copied| progress(copied, total),
+    )?;
+
+    // 只有root能把文件交给其他用户，没有权限时保留为当前用户
+    match writer.set_owner(Some(meta.uid()), Some(meta.gid())) {
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {}
+        result => result?,
+    }
+    // chown会清除setuid/setgid位，mkstemp创建的文件权限为0600，都需要重新设置
+    writer.set_permissions(meta.permissions())?;
+    // ACL也保存在扩展属性中，在设置权限之后复制，避免被chmod修改
+    #[cfg(target_os = "linux")]
+    copy_xattrs(&reader, writer)?;
+    writer.set_times(
+        FileTimes::new()
+            .set_accessed(meta.accessed())
+            .set_modified(meta.modified()),
+    )?;
+
+    temp.persist_sync(dst)?;
+    remove_file(src)
+}
+
+/*
+    复制全部扩展属性，目标文件系统不支持扩展属性(ENOTSUP)时跳过
+    没有权限设置的命名空间(例如普通用户的trusted.*、security.*)跳过，与cp -a相同
+*/
+#[cfg(target_os = "linux")]
+fn copy_xattrs(reader: &File, writer: &mut File) -> Result<()> {
+    for name in reader.list_xattrs()? {
+        let Some(value) = reader.get_xattr(&name)? else {
+            continue; // 列出之后被删除
+        };
+        match writer.set_xattr(&name, &value) {
+            Err(e) if e.raw_os_error() == Some(libc::ENOTSUP) => return Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {}
+            result => result?,
+        }
+    }
+
+    Ok(())
+}
+
+// 在dst所在目录中以临时名字创建同样的符号链接，再rename覆盖dst
+fn move_symlink(src: &Path, dst: &Path) -> Result<()> {
+    let target = read_link(src)?;
+    let dir = crate::dir::parent_dir(dst);
+
+    for n in 0.. {
+        let temp = dir.join(format!(".tmp{}.{}", std::process::id(), n));
+        match symlink(&target, &temp) {
+            Ok(()) => {
+                if let Err(e) = rename(&temp, dst) {
+                    let _ = remove_file(&temp);
+                    return Err(e);
+                }
+                break;
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+
+    crate::dir::sync_parent(dst)?;
+    remove_file(src)
+}
+
+/*
+    删除文件，封装POSIX unlink
+    unlink(path: *const c_char) -> i32
+    删除的是目录项，已经打开这个文件的fd仍然可以继续读写，最后一个fd关闭后才释放空间
+    路径是符号链接时删除链接本身；路径是目录时Linux返回EISDIR，macOS返回EPERM，这里统一为IsADirectory
+*/
+pub fn remove_file<P: AsRef<Path>>(path: P) -> Result<()> {
+    let path = path.as_ref();
+    let c_style_str_path = to_c_path(path)?;
+
+    let result = unsafe { libc::unlink(c_style_str_path.as_ptr()) };
+    if result < 0 {
+        let mut err = io::Error::last_os_error();
+        if err.raw_os_error() == Some(libc::EPERM)
+            && lstat(path).is_ok_and(|stat| Metadata::from_stat(stat).is_dir())
+        {
+            err = io::Error::from_raw_os_error(libc::EISDIR);
+        }
+        return Err(error::with_context(err, "unlink", Some(path)));
+    }
+
+    Ok(())
+}
+
+/*
+    创建硬链接，封装POSIX link
+    link(oldpath: *const c_char, newpath: *const c_char) -> i32
+    两个路径指向同一个inode，不能跨文件系统(EXDEV)，dst已存在时返回EEXIST
+*/
+pub fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> Result<()> {
+    let old = to_c_path(src.as_ref())?;
+    let new = to_c_path(dst.as_ref())?;
+
+    let result = unsafe { libc::link(old.as_ptr(), new.as_ptr()) };
+    if result < 0 {
+        return Err(error::with_context(
+            io::Error::last_os_error(),
+            "link",
+            Some(dst.as_ref()),
+        ));
+    }
+
+    Ok(())
+}
+
+/*
+    创建符号链接，封装POSIX symlink
+    symlink(target: *const c_char, linkpath: *const c_char) -> i32
+    target原样写入链接，不检查是否存在，相对路径相对于链接所在的目录解析
+*/
+pub fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(target: P, link: Q) -> Result<()> {
+    let target = to_c_path(target.as_ref())?;
+    let c_style_str_link = to_c_path(link.as_ref())?;
+
+    let result = unsafe { libc::symlink(target.as_ptr(), c_style_str_link.as_ptr()) };
+    if result < 0 {
+        return Err(error::with_context(
+            io::Error::last_os_error(),
+            "symlink",
+            Some(link.as_ref()),
+        ));
+    }
+
+    Ok(())
+}
+
+/*
+    读取符号链接的内容，封装POSIX readlink
+    readlink(path: *const c_char, buf: *mut c_char, size: size_t) -> ssize_t
+    结果不以空字符结尾，返回值等于size时可能被截断，需要换更大的缓冲区重试
+    path不是符号链接时返回EINVAL
+*/
+pub fn read_link<P: AsRef<Path>>(path: P) -> Result<PathBuf> {
+    let path = path.as_ref();
+    let c_style_str_path = to_c_path(path)?;
+
+    let mut buf = Vec::<u8>::with_capacity(libc::PATH_MAX as usize);
+    loop {
+        let n = unsafe {
+            libc::readlink(
+                c_style_str_path.as_ptr(),
+                buf.as_mut_ptr() as *mut libc::c_char,
+                buf.capacity(),
+            )
+        };
+        if n < 0 {
+            return Err(error::with_context(
+                io::Error::last_os_error(),
+                "readlink",
+                Some(path),
+            ));
+        }
+
+        // 返回值小于缓冲区大小才能确定没有被截断
+        if (n as usize) < buf.capacity() {
+            unsafe { buf.set_len(n as usize) };
+            return Ok(PathBuf::from(OsString::from_vec(buf)));
+        }
+        buf.reserve(buf.capacity() * 2);
+    }
+}
+
+/*
+    解析为绝对路径，封装POSIX realpath
+    realpath(path: *const c_char, resolved: *mut c_char) -> *mut c_char
+    展开所有符号链接以及"."和".."，resolved传NULL时由libc用malloc分配结果，调用方负责free
+    任何一级路径不存在(包括悬空的符号链接)都返回ENOENT
+*/
+pub fn canonicalize<P: AsRef<Path>>(path: P) -> Result<PathBuf> {
+    let path = path.as_ref();
+    let c_style_str_path = to_c_path(path)?;
+
+    let resolved = unsafe { libc::realpath(c_style_str_path.as_ptr(), std::ptr::null_mut()) };
+    if resolved.is_null() {
+        return Err(error::with_context(
+            io::Error::last_os_error(),
+            "realpath",
+            Some(path),
+        ));
+    }
+
+    let bytes = unsafe { CStr::from_ptr(resolved) }.to_bytes().to_vec();
+    unsafe { libc::free(resolved as *mut libc::c_void) };
+
+    Ok(PathBuf::from(OsString::from_vec(bytes)))
+}
+
+/// access检查的权限
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AccessMode {
+    Exists, // 只检查路径能否解析到，F_OK
+    Read,
+    Write,
+    Execute, // 对目录而言是能否进入(搜索)
+}
+
+/*
+    检查当前进程对path是否有指定的权限，封装POSIX faccessat
+    faccessat(dirfd: i32, path: *const c_char, mode: i32, flags: i32) -> i32
+    flags为AT_EACCESS时按有效用户ID检查，与open实际使用的身份一致，而access按真实用户ID检查
+    root对读写总是返回有权限，执行权限需要至少有一个x位
+
+    检查与之后的操作之间文件可能被修改(TOCTOU)，结果只能作为提示
+    需要可靠结果时应直接进行操作并处理返回的错误，不能用它做安全检查
+*/
+pub fn access<P: AsRef<Path>>(path: P, mode: AccessMode) -> Result<bool> {
+    let path = path.as_ref();
+    let c_style_str_path = to_c_path(path)?;
+
+    let mode = match mode {
+        AccessMode::Exists => libc::F_OK,
+        AccessMode::Read => libc::R_OK,
+        AccessMode::Write => libc::W_OK,
+        AccessMode::Execute => libc::X_OK,
+    };
+    let result = unsafe {
+        libc::faccessat(
+            libc::AT_FDCWD,
+            c_style_str_path.as_ptr(),
+            mode,
+            libc::AT_EACCESS,
+        )
+    };
+    if result < 0 {
+        let err = io::Error::last_os_error();
+        // 无权限、路径不存在、只读文件系统属于"不可访问"，其余错误(如ELOOP、EIO)照常返回
+        return match err.raw_os_error() {
+            Some(libc::EACCES | libc::ENOENT | libc::ENOTDIR | libc::EROFS | libc::EPERM) => {
+                Ok(false)
+            }
+            _ => Err(error::with_context(err, "faccessat", Some(path))),
+        };
+    }
+
+    Ok(true)
+}
+
+/*
+    创建命名管道(FIFO)，封装POSIX mkfifo
+    mkfifo(path: *const c_char, mode: mode_t) -> i32
+    实际权限为mode & !umask；与匿名管道不同，没有亲缘关系的进程也可以通过路径打开同一个管道
+    打开时的阻塞行为见OpenOptions::nonblocking
+*/
+pub fn mkfifo<P: AsRef<Path>>(path: P, mode: u32) -> Result<()> {
+    let c_style_str_path = to_c_path(path.as_ref())?;
+
+    let result = unsafe { libc::mkfifo(c_style_str_path.as_ptr(), mode as libc::mode_t) };
+    if result < 0 {
+        return Err(error::with_context(
+            io::Error::last_os_error(),
+            "mkfifo",
+            Some(path.as_ref()),
+        ));
+    }
+
+    Ok(())
+}
+
+// stat的结果，路径不存在(ENOENT)时为None，其余错误(例如EACCES)照常返回
+fn try_stat(path: &Path) -> Result<Option<sys::stat>> {
+    match stat(path) {
+        Ok(stat) => Ok(Some(stat)),
+        Err(e) if e.raw_os_error() == Some(libc::ENOENT) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// 路径是否存在，符号链接会被跟随，指向不存在目标的链接视为不存在
+pub fn exists<P: AsRef<Path>>(path: P) -> Result<bool> {
+    Ok(try_stat(path.as_ref())?.is_some())
+}
+
+/// 路径是否存在且为普通文件
+pub fn is_file<P: AsRef<Path>>(path: P) -> Result<bool> {
+    Ok(try_stat(path.as_ref())?.is_some_and(|stat| Metadata::from_stat(stat).is_file()))
+}
+
+/// 路径是否存在且为目录
+pub fn is_dir<P: AsRef<Path>>(path: P) -> Result<bool> {
+    Ok(try_stat(path.as_ref())?.is_some_and(|stat| Metadata::from_stat(stat).is_dir()))
+}
+
+/*
+    两个路径是否位于同一个文件系统，比较stat得到的st_dev，符号链接会被跟随
+    位于同一文件系统时rename和hard_link才可能成功，否则需要复制；目标还不存在时可以传入它的父目录
+    同一个文件系统的不同bind mount的st_dev相同，但rename仍会以EXDEV失败
+*/
+pub fn same_filesystem<P: AsRef<Path>, Q: AsRef<Path>>(a: P, b: Q) -> Result<bool> {
+    let a = Metadata::from_stat(stat(a.as_ref())?);
+    let b = Metadata::from_stat(stat(b.as_ref())?);
+    Ok(a.dev() == b.dev())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        AccessMode, access, canonicalize, copy, copy_with_buffer_size, exists, hard_link, is_dir,
+        is_file, metadata, read, read_link, read_to_string, reflink, remove_file, rename,
+        same_filesystem, set_permissions, symlink, write,
+    };
+    use super::{
+        DEFAULT_COPY_CHUNK_SIZE, copy_by_read_write, move_by_copy, move_file, open_copy_pair,
+    };
+    use crate::temp::TempDir;
+    use crate::{File, FileTimes, OpenMode, Permissions};
+    use std::io;
+    use std::path::Path;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_copy_small_and_large_chunk() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let src = dir.path().join("src.bin");
+        let content: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&src, &content)?;
+
+        // 7字节的块，普通文件每次读满一块，恰好需要1429次循环才能复制完
+        let tiny = dir.path().join("tiny.bin");
+        let n = copy_with_buffer_size(&src, &tiny, 7)?;
+        assert_eq!(n, content.len() as u64, "Should copy all bytes");
+
+        let (mut reader, mut writer) = open_copy_pair(&src, &dir.path().join("counted.bin"))?;
+        let mut chunks = Vec::new();
+        copy_by_read_write(&mut reader, &mut writer, 7, &mut |copied| {
+            chunks.push(copied)
+        })?;
+        assert_eq!(chunks.len(), 1429, "One write per 7-byte chunk");
+        assert_eq!(chunks.last(), Some(&(content.len() as u64)));
+
+        // 读到EOF后停止，之后再复制不会产生新的块
+        let n = copy_by_read_write(&mut reader, &mut writer, 7, &mut |_| {
+            panic!("Reader is already at EOF")
+        })?;
+        assert_eq!(n, 0, "Nothing left after EOF");
+
+        let large = dir.path().join("large.bin");
+        let n = copy_with_buffer_size(&src, &large, 1 << 20)?;
+        assert_eq!(n, content.len() as u64, "Should copy all bytes");
+
+        assert_eq!(
+            std::fs::read(&tiny)?,
+            content,
+            "Tiny chunk copy should match"
+        );
+        assert_eq!(
+            std::fs::read(&large)?,
+            content,
+            "Large chunk copy should match"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_default_chunk() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let src = dir.path().join("src.txt");
+        let dst = dir.path().join("dst.txt");
+        std::fs::write(&src, b"Hello, world!")?;
+
+        let n = copy(&src, &dst)?;
+        assert_eq!(n, 13, "Should copy all bytes");
+        assert_eq!(std::fs::read(&dst)?, b"Hello, world!");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_preserves_permissions() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let src = dir.path().join("script.sh");
+        let dst = dir.path().join("copy.sh");
+        std::fs::write(&src, b"#!/bin/sh\n")?;
+        set_permissions(&src, Permissions::from_mode(0o751))?;
+
+        copy(&src, &dst)?;
+        assert_eq!(
+            metadata(&dst)?.permissions().mode(),
+            0o751,
+            "Mode should be copied"
+        );
+
+        // 已存在的dst也会被修改为src的权限
+        set_permissions(&src, Permissions::from_mode(0o600))?;
+        copy_with_buffer_size(&src, &dst, 4)?;
+        assert_eq!(metadata(&dst)?.permissions().mode(), 0o600);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reflink() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let src = dir.path().join("src.bin");
+        let dst = dir.path().join("dst.bin");
+        std::fs::write(&src, b"Hello, reflink!")?;
+        set_permissions(&src, Permissions::from_mode(0o640))?;
+
+        match reflink(&src, &dst) {
+            // ext4、tmpfs等不支持reflink，跳过，失败时不留下dst
+            Err(e)
+                if e.kind() == io::ErrorKind::Unsupported
+                    || e.raw_os_error() == Some(libc::EXDEV) =>
+            {
+                assert!(!exists(&dst)?, "Failed reflink should not leave dst");
+                return Ok(());
+            }
+            result => result?,
+        }
+
+        assert_eq!(std::fs::read(&dst)?, b"Hello, reflink!");
+        assert_eq!(metadata(&dst)?.permissions().mode(), 0o640);
+
+        // dst已存在时不覆盖
+        let result = reflink(&src, &dst);
+        assert!(result.is_err(), "Existing dst should fail");
+        if let Err(e) = result {
+            assert_eq!(e.kind(), io::ErrorKind::AlreadyExists);
+        }
+
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_copy_size_unknown() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let dst = dir.path().join("status");
+
+        // st_size为0的文件同样要复制到EOF
+        let n = copy("/proc/self/status", &dst)?;
+        assert!(n > 0, "Should copy past st_size");
+        assert_eq!(std::fs::metadata(&dst)?.len(), n);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let from = dir.path().join("from.txt");
+        let to = dir.path().join("to.txt");
+        std::fs::write(&from, b"new")?;
+        std::fs::write(&to, b"old")?;
+
+        // 已存在的目标被替换
+        rename(&from, &to)?;
+        assert!(!exists(&from)?);
+        assert_eq!(read(&to)?, b"new");
+
+        let result = rename(&from, &to);
+        assert!(result.is_err(), "Missing source should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::NotFound,
+                "Error should be NotFound"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_rename_noreplace_and_exchange() -> io::Result<()> {
+        use super::{rename_exchange, rename_noreplace};
+
+        let dir = TempDir::new()?;
+        let a = dir.path().join("a.txt");
+        let b = dir.path().join("b.txt");
+        std::fs::write(&a, b"A")?;
+        std::fs::write(&b, b"B")?;
+
+        let result = rename_noreplace(&a, &b);
+        assert!(result.is_err(), "Existing target should not be replaced");
+        if let Err(e) = result {
+            assert_eq!(e.kind(), io::ErrorKind::AlreadyExists);
+        }
+        assert_eq!(read(&b)?, b"B", "Target should be untouched");
+
+        rename_exchange(&a, &b)?;
+        assert_eq!((read(&a)?, read(&b)?), (b"B".to_vec(), b"A".to_vec()));
+
+        let c = dir.path().join("c.txt");
+        rename_noreplace(&a, &c)?;
+        assert_eq!(read(&c)?, b"B");
+
+        let result = rename_exchange(&a, &b);
+        assert!(result.is_err(), "Exchange needs both paths to exist");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_file() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("file.txt");
+        write(&path, b"content")?;
+
+        remove_file(&path)?;
+        assert!(!exists(&path)?, "File should be removed");
+
+        let result = remove_file(&path);
+        assert!(result.is_err(), "Removing missing file should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::NotFound,
+                "Error should be NotFound"
+            );
+        }
+
+        let result = remove_file(dir.path());
+        assert!(result.is_err(), "Removing directory should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::IsADirectory,
+                "Error should be IsADirectory"
+            );
+        }
+
+        // 删除符号链接不影响指向的文件
+        let target = dir.path().join("target.txt");
+        let link = dir.path().join("link");
+        write(&target, b"content")?;
+        std::os::unix::fs::symlink(&target, &link)?;
+        remove_file(&link)?;
+        assert!(exists(&target)?, "Target should be kept");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_links() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let src = dir.path().join("src.txt");
+        write(&src, b"content")?;
+
+        // 硬链接与原文件共享inode
+        let hard = dir.path().join("hard.txt");
+        hard_link(&src, &hard)?;
+        assert_eq!(metadata(&hard)?.ino(), metadata(&src)?.ino());
+        assert_eq!(metadata(&src)?.nlink(), 2);
+
+        let result = hard_link(&src, &hard);
+        assert!(result.is_err(), "Existing destination should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::AlreadyExists,
+                "Error should be AlreadyExists"
+            );
+        }
+
+        // 相对路径的符号链接原样保存
+        let link = dir.path().join("link");
+        symlink("src.txt", &link)?;
+        assert_eq!(read_link(&link)?, Path::new("src.txt"));
+        assert_eq!(read(&link)?, b"content");
+
+        // 指向不存在的目标也能创建
+        let dangling = dir.path().join("dangling");
+        symlink(dir.path().join("missing"), &dangling)?;
+        assert_eq!(read_link(&dangling)?, dir.path().join("missing"));
+
+        let result = read_link(&src);
+        assert!(result.is_err(), "Regular file is not a symlink");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::InvalidInput,
+                "Error should be InvalidInput"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_canonicalize() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        // tempdir本身可能位于符号链接之下(macOS的/tmp)，先解析出真实路径
+        let root = canonicalize(dir.path())?;
+        assert!(root.is_absolute());
+
+        std::fs::create_dir(root.join("sub"))?;
+        write(root.join("sub/file.txt"), b"content")?;
+        symlink("sub", root.join("link"))?;
+
+        let resolved = canonicalize(root.join("link/../sub/./file.txt"))?;
+        assert_eq!(resolved, root.join("sub/file.txt"));
+        assert_eq!(canonicalize(root.join("link"))?, root.join("sub"));
+
+        // 悬空的符号链接无法解析
+        symlink("missing", root.join("dangling"))?;
+        for path in [root.join("dangling"), root.join("sub/missing/file.txt")] {
+            let result = canonicalize(&path);
+            assert!(result.is_err(), "Missing component should fail");
+            if let Err(e) = result {
+                assert_eq!(
+                    e.kind(),
+                    io::ErrorKind::NotFound,
+                    "Error should be NotFound"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_access() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("file.txt");
+        write(&path, b"content")?;
+        set_permissions(&path, Permissions::from_mode(0o644))?;
+
+        assert!(access(&path, AccessMode::Exists)?);
+        assert!(access(&path, AccessMode::Read)?);
+        assert!(access(&path, AccessMode::Write)?);
+        assert!(!access(&path, AccessMode::Execute)?, "No x bit set");
+        assert!(
+            access(dir.path(), AccessMode::Execute)?,
+            "Directory is searchable"
+        );
+
+        let missing = dir.path().join("missing");
+        assert!(!access(&missing, AccessMode::Exists)?);
+        assert!(!access(missing.join("file"), AccessMode::Read)?);
+
+        set_permissions(&path, Permissions::from_mode(0o755))?;
+        assert!(access(&path, AccessMode::Execute)?);
+
+        // root拥有CAP_DAC_OVERRIDE，只读文件仍然可写，跳过
+        if unsafe { libc::geteuid() } == 0 {
+            return Ok(());
+        }
+        set_permissions(&path, Permissions::from_mode(0o400))?;
+        assert!(access(&path, AccessMode::Read)?);
+        assert!(!access(&path, AccessMode::Write)?, "Read-only file");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_file() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let src = dir.path().join("src.txt");
+        let dst = dir.path().join("dst.txt");
+        write(&src, b"content")?;
+
+        // 同一文件系统内直接rename
+        let ino = metadata(&src)?.ino();
+        move_file(&src, &dst)?;
+        assert!(!exists(&src)?);
+        assert_eq!(metadata(&dst)?.ino(), ino, "Rename should keep the inode");
+
+        let result = move_file(&src, &dst);
+        assert!(result.is_err(), "Missing source should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::NotFound,
+                "Error should be NotFound"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_by_copy() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let src = dir.path().join("src.bin");
+        let dst = dir.path().join("dst.bin");
+        let data = vec![7u8; DEFAULT_COPY_CHUNK_SIZE * 2 + 10];
+        write(&src, &data)?;
+        set_permissions(&src, Permissions::from_mode(0o640))?;
+        let mtime = UNIX_EPOCH + Duration::new(1_000_000_000, 500);
+        let mut file = File::open(&src, OpenMode::ReadWrite)?;
+        file.set_times(FileTimes::new().set_modified(mtime))?;
+        drop(file);
+
+        let mut calls = Vec::new();
+        move_by_copy(&src, &dst, &mut |copied, total| calls.push((copied, total)))?;
+        assert!(!exists(&src)?, "Source should be removed");
+        assert_eq!(read(&dst)?, data);
+
+        let meta = metadata(&dst)?;
+        assert_eq!(meta.permissions().mode() & 0o777, 0o640);
+        assert_eq!(meta.modified(), mtime, "mtime should be preserved");
+
+        let total = data.len() as u64;
+        assert_eq!(calls.len(), 3, "One call per chunk");
+        assert_eq!(calls.last(), Some(&(total, total)));
+
+        // 真正跨文件系统时同样有效，/dev/shm通常是tmpfs
+        if let Ok(shm) = TempDir::new_in("/dev/shm") {
+            let across = shm.path().join("moved.bin");
+            move_file(&dst, &across)?;
+            assert!(!exists(&dst)?);
+            assert_eq!(read(&across)?, data);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_by_copy_existing_dst() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let src = dir.path().join("src.txt");
+        let dst = dir.path().join("dst.txt");
+        write(&dst, b"old")?;
+
+        // 复制失败时已存在的dst保持不变，也不会留下临时文件
+        let sub = dir.path().join("sub");
+        crate::create_dir(&sub)?;
+        let result = move_by_copy(&sub, &dst, &mut |_, _| {});
+        assert!(result.is_err(), "Copying a directory should fail");
+        assert_eq!(read(&dst)?, b"old", "dst should be kept on failure");
+        assert!(exists(&sub)?, "Source should be kept on failure");
+        assert_eq!(
+            std::fs::read_dir(dir.path())?.count(),
+            2,
+            "No temp file left"
+        );
+
+        write(&src, b"new")?;
+        #[cfg(target_os = "linux")]
+        crate::xattr::set_xattr(&src, "user.simple_file", b"value")?;
+        move_by_copy(&src, &dst, &mut |_, _| {})?;
+        assert!(!exists(&src)?);
+        assert_eq!(read(&dst)?, b"new", "dst should be replaced");
+        #[cfg(target_os = "linux")]
+        assert_eq!(
+            crate::xattr::get_xattr(&dst, "user.simple_file")?,
+            Some(b"value".to_vec()),
+            "xattrs should be copied"
+        );
+
+        // 跨文件系统移动符号链接，dst已存在时同样被替换
+        if let Ok(shm) = TempDir::new_in("/dev/shm") {
+            let link = shm.path().join("link");
+            symlink("target", &link)?;
+            move_file(&link, &dst)?;
+            assert!(!exists(&link)?);
+            assert_eq!(read_link(&dst)?, Path::new("target"));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_zero_chunk_rejected() {
+        let result = copy_with_buffer_size("src.txt", "dst.txt", 0);
+        assert!(result.is_err(), "Zero chunk size should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::InvalidInput,
+                "Error should be InvalidInput"
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_to_string() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("utf8.txt");
+        std::fs::write(&path, "Hello, 世界!")?;
+
+        assert_eq!(read_to_string(&path)?, "Hello, 世界!");
+        assert_eq!(read(&path)?, "Hello, 世界!".as_bytes());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_to_string_invalid_utf8() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("binary.bin");
+        std::fs::write(&path, [0x48, 0x69, 0xff, 0xfe])?;
+
+        let result = read_to_string(&path);
+        assert!(result.is_err(), "Non UTF-8 content should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::InvalidData,
+                "Error should be InvalidData"
+            );
+        }
+        // 按字节读取不受影响
+        assert_eq!(read(&path)?, [0x48, 0x69, 0xff, 0xfe]);
+
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_read_size_unknown() -> io::Result<()> {
+        // /proc下的文件st_size为0，内容在读取时才生成
+        assert_eq!(metadata("/proc/self/status")?.len(), 0);
+        let content = read_to_string("/proc/self/status")?;
+        assert!(content.starts_with("Name:"), "Should read past st_size");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_to_string_empty_file() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("empty.txt");
+        std::fs::write(&path, b"")?;
+
+        assert_eq!(read_to_string(&path)?, "");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("out.bin");
+        let content: Vec<u8> = (0..=255u8).cycle().take(100_000).collect();
+
+        write(&path, &content)?;
+        assert_eq!(
+            std::fs::read(&path)?,
+            content,
+            "Written content should match"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_truncates_existing() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("out.txt");
+        std::fs::write(&path, b"a much longer previous content")?;
+
+        write(&path, b"short")?;
+        assert_eq!(
+            std::fs::read(&path)?,
+            b"short",
+            "Old tail should be truncated"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exists() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("file.txt");
+        std::fs::write(&path, b"content")?;
+
+        assert!(exists(&path)?, "Existing file should exist");
+        assert!(is_file(&path)?, "Regular file should be a file");
+        assert!(!is_dir(&path)?, "Regular file should not be a dir");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exists_nonexistent() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("missing.txt");
+
+        assert!(!exists(&path)?, "Missing path should not exist");
+        assert!(!is_file(&path)?);
+        assert!(!is_dir(&path)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exists_directory() -> io::Result<()> {
+        let dir = TempDir::new()?;
+
+        assert!(exists(dir.path())?, "Directory should exist");
+        assert!(is_dir(dir.path())?, "Directory should be a dir");
+        assert!(!is_file(dir.path())?, "Directory should not be a file");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_same_filesystem() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let file = dir.path().join("file.txt");
+        write(&file, b"content")?;
+
+        assert!(same_filesystem(dir.path(), &file)?);
+        assert!(same_filesystem(&file, &file)?);
+
+        // procfs总是独立的文件系统
+        #[cfg(target_os = "linux")]
+        assert!(!same_filesystem(dir.path(), "/proc")?);
+
+        let result = same_filesystem(dir.path(), dir.path().join("missing"));
+        assert!(result.is_err(), "Missing path should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::NotFound,
+                "Error should be NotFound"
+            );
+        }
+
+        Ok(())
+    }
+}
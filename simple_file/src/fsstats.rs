@@ -0,0 +1,175 @@
+/*
+    文件系统统计信息，封装POSIX statvfs/fstatvfs
+
+    statvfs(path: *const c_char, buf: *mut statvfs) -> i32
+    fstatvfs(fd: i32, buf: *mut statvfs) -> i32
+    path可以是文件系统中的任意文件，返回的是它所在的整个文件系统的信息
+
+    块数以f_frsize(分配单位)为单位，f_bsize只是建议的I/O大小，二者在部分文件系统上不同
+    f_bfree包括只有root能使用的保留块(ext4默认保留5%)，普通用户实际能用的是f_bavail
+    Linux下使用statvfs64，32位平台上块数也不会溢出
+*/
+
+// statvfs各字段的类型随平台不同(例如macOS上f_blocks为u32)，这里统一转换
+#![allow(clippy::unnecessary_cast)]
+
+use std::io;
+use std::path::Path;
+
+use crate::{File, Result, error, sys, to_c_path};
+
+/// path所在文件系统的统计信息
+pub fn fs_stats<P: AsRef<Path>>(path: P) -> Result<FsStats> {
+    let path = path.as_ref();
+    let c_style_str_path = to_c_path(path)?;
+
+    let mut buf: sys::statvfs = unsafe { std::mem::zeroed() };
+    let result = unsafe { sys::statvfs(c_style_str_path.as_ptr(), &mut buf) };
+    if result < 0 {
+        return Err(error::with_context(
+            io::Error::last_os_error(),
+            "statvfs",
+            Some(path),
+        ));
+    }
+
+    Ok(FsStats::from_statvfs(&buf))
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FsStats {
+    block_size: u64,
+    fragment_size: u64,
+    blocks: u64,
+    blocks_free: u64,
+    blocks_available: u64,
+    files: u64,
+    files_free: u64,
+    name_max: u64,
+    read_only: bool,
+}
+
+impl FsStats {
+    fn from_statvfs(buf: &sys::statvfs) -> FsStats {
+        // 部分文件系统f_frsize为0，此时以f_bsize为单位
+        let fragment_size = if buf.f_frsize == 0 {
+            buf.f_bsize as u64
+        } else {
+            buf.f_frsize as u64
+        };
+
+        FsStats {
+            block_size: buf.f_bsize as u64,
+            fragment_size,
+            blocks: buf.f_blocks as u64,
+            blocks_free: buf.f_bfree as u64,
+            blocks_available: buf.f_bavail as u64,
+            files: buf.f_files as u64,
+            files_free: buf.f_ffree as u64,
+            name_max: buf.f_namemax as u64,
+            read_only: buf.f_flag as u64 & libc::ST_RDONLY as u64 != 0,
+        }
+    }
+
+    /// 总空间，单位字节
+    pub fn total_space(&self) -> u64 {
+        self.blocks.saturating_mul(self.fragment_size)
+    }
+
+    /// 空闲空间，包括只有root能使用的保留空间
+    pub fn free_space(&self) -> u64 {
+        self.blocks_free.saturating_mul(self.fragment_size)
+    }
+
+    /// 普通用户可用的空间，写入前检查空间是否足够时应使用这个值
+    pub fn available_space(&self) -> u64 {
+        self.blocks_available.saturating_mul(self.fragment_size)
+    }
+
+    /// 建议的I/O块大小(f_bsize)
+    pub fn block_size(&self) -> u64 {
+        self.block_size
+    }
+
+    /// 分配单位(f_frsize)，以上空间都是它的整数倍
+    pub fn fragment_size(&self) -> u64 {
+        self.fragment_size
+    }
+
+    /// inode总数，不使用固定inode表的文件系统(例如btrfs)可能为0
+    pub fn total_inodes(&self) -> u64 {
+        self.files
+    }
+
+    /// 空闲的inode数，为0时即使还有空间也无法创建新文件
+    pub fn free_inodes(&self) -> u64 {
+        self.files_free
+    }
+
+    /// 文件名(一级路径)的最大长度，单位字节
+    pub fn max_name_len(&self) -> u64 {
+        self.name_max
+    }
+
+    /// 是否以只读方式挂载
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+}
+
+impl File {
+    /// 通过fstatvfs获取已打开文件所在文件系统的统计信息
+    pub fn fs_stats(&self) -> Result<FsStats> {
+        self.ensure_open()?;
+
+        let mut buf: sys::statvfs = unsafe { std::mem::zeroed() };
+        let result = unsafe { sys::fstatvfs(self.fd, &mut buf) };
+        if result < 0 {
+            return Err(self.os_error("fstatvfs"));
+        }
+
+        Ok(FsStats::from_statvfs(&buf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fs_stats;
+    use crate::temp::TempDir;
+    use crate::{File, OpenMode, write};
+    use std::io;
+
+    #[test]
+    fn test_fs_stats() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("file.txt");
+        write(&path, b"content")?;
+
+        let stats = fs_stats(dir.path())?;
+        assert!(stats.total_space() > 0);
+        assert!(stats.free_space() <= stats.total_space());
+        assert!(stats.available_space() <= stats.free_space());
+        assert!(stats.block_size() > 0);
+        assert_eq!(stats.total_space() % stats.fragment_size(), 0);
+        assert!(stats.max_name_len() >= 14, "POSIX requires at least 14");
+        assert!(!stats.is_read_only());
+
+        // 同一文件系统中的文件得到相同的总量
+        let file = File::open(&path, OpenMode::Read)?;
+        let by_fd = file.fs_stats()?;
+        assert_eq!(by_fd.total_space(), stats.total_space());
+        assert_eq!(by_fd.max_name_len(), stats.max_name_len());
+
+        let result = fs_stats(dir.path().join("missing"));
+        assert!(result.is_err(), "Missing path should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::NotFound,
+                "Error should be NotFound"
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,356 @@
+/*
+    通配符匹配路径，例如匹配logs目录下任意深度的全部.gz文件
+
+    支持的语法，与shell一致：
+    *       匹配一级路径中任意个字符，不跨越'/'
+    ?       匹配一个字符
+    [abc]   匹配括号中的任意一个字符，[a-z]表示范围，[!abc]或[^abc]表示取反
+    **      单独作为一级路径时，匹配零级或多级目录
+
+    以'.'开头的文件名只能被以'.'开头的模式匹配，*、?、[...]和**都不会匹配隐藏文件，与shell的默认行为相同
+
+    模式开头不含通配符的部分(例如"logs/")直接作为遍历的起点，只遍历起点之下的目录树
+    不含**时遍历深度不超过模式的级数，不跟随符号链接进入目录
+*/
+
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::{Error, Result, walk};
+
+/// 返回与pattern匹配的全部路径，按字典序排列，没有匹配时返回空列表
+/// 例如glob("logs/**/*.gz")
+pub fn glob(pattern: &str) -> Result<Vec<PathBuf>> {
+    let pattern_path = Path::new(pattern);
+
+    // 拆分出不含通配符的起点和需要匹配的部分
+    let mut base = PathBuf::new();
+    let mut segments = Vec::new();
+    for component in pattern_path.components() {
+        let part = component.as_os_str().to_str().unwrap_or_default();
+        let literal = !matches!(component, Component::Normal(_)) || !has_wildcard(part);
+        if segments.is_empty() && literal {
+            base.push(component);
+        } else if part == "**" {
+            segments.push(Segment::AnyDirs);
+        } else {
+            segments.push(Segment::Name(parse(part)?));
+        }
+    }
+
+    // 没有通配符，存在即匹配
+    if segments.is_empty() {
+        return Ok(match crate::symlink_metadata(&base) {
+            Ok(_) => vec![base],
+            Err(_) => Vec::new(),
+        });
+    }
+
+    let relative = base.as_os_str().is_empty();
+    let root = if relative {
+        Path::new(".")
+    } else {
+        base.as_path()
+    };
+    if !crate::is_dir(root)? {
+        return Ok(Vec::new());
+    }
+
+    let max_depth = if segments.contains(&Segment::AnyDirs) {
+        usize::MAX
+    } else {
+        segments.len()
+    };
+
+    // 模式中没有以'.'开头的部分时，隐藏目录里的内容不可能被匹配，遍历时直接跳过
+    let prune_hidden = !segments.iter().any(|segment| match segment {
+        Segment::Name(tokens) => tokens.first() == Some(&Token::Char('.')),
+        Segment::AnyDirs => false,
+    });
+    let walker = walk(root)
+        .max_depth(max_depth)
+        .filter(move |e| e.depth() == 0 || !prune_hidden || !is_hidden(e.file_name().to_str()));
+
+    let mut matches = Vec::new();
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            // 没有权限读取的目录跳过，与shell的行为相同
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => continue,
+            Err(e) => return Err(e),
+        };
+        if entry.depth() == 0 {
+            continue;
+        }
+
+        let path = entry.path().strip_prefix(root).unwrap_or(entry.path());
+        let names: Option<Vec<&str>> = path.iter().map(|name| name.to_str()).collect();
+        // 非UTF-8的文件名不会被匹配
+        if let Some(names) = names
+            && match_segments(&segments, &names)
+        {
+            matches.push(if relative {
+                path.to_path_buf()
+            } else {
+                entry.into_path()
+            });
+        }
+    }
+    matches.sort();
+
+    Ok(matches)
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Segment {
+    AnyDirs,
+    Name(Vec<Token>),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    Char(char),
+    AnyChar,
+    AnyChars,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+fn has_wildcard(part: &str) -> bool {
+    part.contains(['*', '?', '['])
+}
+
+fn is_hidden(name: Option<&str>) -> bool {
+    name.is_some_and(|name| name.starts_with('.'))
+}
+
+// 把一级路径的模式解析为token，'['没有对应的']'时返回InvalidInput
+fn parse(part: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = part.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '?' => Token::AnyChar,
+            // 连续的*与单个*等价
+            '*' if tokens.last() == Some(&Token::AnyChars) => continue,
+            '*' => Token::AnyChars,
+            '[' => {
+                let negated = chars.next_if(|&c| c == '!' || c == '^').is_some();
+                let mut ranges = Vec::new();
+                let mut closed = false;
+                // ']'紧跟在'['之后时作为普通字符
+                let mut first = true;
+                while let Some(c) = chars.next() {
+                    if c == ']' && !first {
+                        closed = true;
+                        break;
+                    }
+                    first = false;
+                    let end = match chars.peek() {
+                        Some('-') => {
+                            chars.next();
+                            match chars.next_if(|&c| c != ']') {
+                                Some(end) => end,
+                                // "[a-]"中的'-'是普通字符
+                                None => {
+                                    ranges.push(('-', '-'));
+                                    c
+                                }
+                            }
+                        }
+                        _ => c,
+                    };
+                    ranges.push((c, end));
+                }
+                if !closed {
+                    return Err(Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "Invalid pattern, unclosed '['",
+                    ));
+                }
+                Token::Class { negated, ranges }
+            }
+            c => Token::Char(c),
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+// **匹配零级或多级目录，用回溯逐级尝试，跳过的目录中不能有隐藏目录
+fn match_segments(segments: &[Segment], names: &[&str]) -> bool {
+    match segments.split_first() {
+        None => names.is_empty(),
+        Some((Segment::AnyDirs, rest)) => (0..=names.len())
+            .take_while(|&skip| skip == 0 || !is_hidden(Some(names[skip - 1])))
+            .any(|skip| match_segments(rest, &names[skip..])),
+        Some((Segment::Name(tokens), rest)) => match names.split_first() {
+            Some((name, names)) => match_name(tokens, name) && match_segments(rest, names),
+            None => false,
+        },
+    }
+}
+
+// 匹配一级路径，以'.'开头的文件名必须由模式中的'.'匹配
+fn match_name(tokens: &[Token], name: &str) -> bool {
+    if name.starts_with('.') && tokens.first() != Some(&Token::Char('.')) {
+        return false;
+    }
+
+    let chars: Vec<char> = name.chars().collect();
+    match_tokens(tokens, &chars)
+}
+
+/*
+    逐个字符匹配，遇到*时记下位置，先让它匹配空串；之后失配时回到最近的*，让它多匹配一个字符再继续
+    只需要回到最近的*：后面的*可以匹配任意内容，前面的*多匹配的部分总能由最近的*代替
+    最坏O(n*m)，递归回溯在"a*a*a*a*b"这类模式上是指数级的
+*/
+fn match_tokens(tokens: &[Token], chars: &[char]) -> bool {
+    let (mut t, mut c) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None; // (*所在的token位置, *之后开始匹配的字符位置)
+
+    while c < chars.len() {
+        match tokens.get(t) {
+            Some(Token::AnyChars) => {
+                backtrack = Some((t, c));
+                t += 1;
+            }
+            Some(token) if match_char(token, chars[c]) => {
+                t += 1;
+                c += 1;
+            }
+            _ => match backtrack {
+                Some((star, start)) => {
+                    backtrack = Some((star, start + 1));
+                    t = star + 1;
+                    c = start + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    // 字符用完后，剩下的token只能是*
+    tokens[t..].iter().all(|token| *token == Token::AnyChars)
+}
+
+fn match_char(token: &Token, c: char) -> bool {
+    match token {
+        Token::Char(expected) => *expected == c,
+        Token::AnyChar => true,
+        Token::AnyChars => true,
+        Token::Class { negated, ranges } => {
+            ranges.iter().any(|&(start, end)| start <= c && c <= end) != *negated
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{glob, match_name, parse};
+    use crate::temp::TempDir;
+    use crate::{create_dir_all, write};
+    use std::io;
+    use std::path::PathBuf;
+
+    fn matches(pattern: &str, name: &str) -> bool {
+        match_name(&parse(pattern).unwrap(), name)
+    }
+
+    #[test]
+    fn test_match_name() {
+        assert!(matches("*.gz", "access.log.gz"));
+        assert!(!matches("*.gz", "access.log"));
+        assert!(matches("a?c", "abc"));
+        assert!(!matches("a?c", "ac"));
+        assert!(matches("**", "anything"));
+        assert!(matches("[abc]x", "bx"));
+        assert!(matches("[a-c0-9]", "7"));
+        assert!(!matches("[!a-c]", "b"));
+        assert!(matches("[^a-c]", "d"));
+        assert!(matches("[]]", "]"));
+        assert!(matches("[a-]", "-"));
+        assert!(
+            matches("日志*", "日志.txt"),
+            "Should match by char, not byte"
+        );
+        assert!(matches("*a*b*c", "xaybzc"));
+        assert!(!matches("*a*b*c", "xaybzcd"));
+        assert!(matches("a*", "a"));
+
+        // 递归回溯在这里是指数级的
+        let name = "a".repeat(100);
+        assert!(!matches(&format!("{}b", "a*".repeat(20)), &name));
+        assert!(matches(&"a*".repeat(20), &name));
+
+        // 隐藏文件
+        assert!(!matches("*", ".hidden"));
+        assert!(!matches("?hidden", ".hidden"));
+        assert!(matches(".*", ".hidden"));
+
+        assert!(parse("[abc").is_err(), "Unclosed class should fail");
+    }
+
+    #[test]
+    fn test_glob() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let root = dir.path();
+        create_dir_all(root.join("logs/2024/01"))?;
+        create_dir_all(root.join("logs/.cache"))?;
+        write(root.join("logs/app.gz"), b"")?;
+        write(root.join("logs/app.log"), b"")?;
+        write(root.join("logs/2024/a.gz"), b"")?;
+        write(root.join("logs/2024/01/b.gz"), b"")?;
+        write(root.join("logs/.cache/c.gz"), b"")?;
+
+        let pattern = |p: &str| root.join(p).to_str().unwrap().to_string();
+        let paths =
+            |names: &[&str]| -> Vec<PathBuf> { names.iter().map(|n| root.join(n)).collect() };
+
+        // **匹配零级或多级目录，不进入隐藏目录
+        assert_eq!(
+            glob(&pattern("logs/**/*.gz"))?,
+            paths(&["logs/2024/01/b.gz", "logs/2024/a.gz", "logs/app.gz"])
+        );
+        assert_eq!(glob(&pattern("logs/*.gz"))?, paths(&["logs/app.gz"]));
+        assert_eq!(glob(&pattern("logs/*/*.gz"))?, paths(&["logs/2024/a.gz"]));
+        assert_eq!(
+            glob(&pattern("logs/app.[gl]*"))?,
+            paths(&["logs/app.gz", "logs/app.log"])
+        );
+        assert_eq!(glob(&pattern("logs/20??"))?, paths(&["logs/2024"]));
+
+        // 没有通配符时存在即匹配
+        assert_eq!(glob(&pattern("logs/app.log"))?, paths(&["logs/app.log"]));
+        assert!(glob(&pattern("logs/missing"))?.is_empty());
+        assert!(glob(&pattern("missing/*.gz"))?.is_empty());
+        assert!(glob(&pattern("logs/*.txt"))?.is_empty());
+
+        // 显式以'.'开头的模式可以匹配隐藏文件
+        assert_eq!(
+            glob(&pattern("logs/.*/*.gz"))?,
+            paths(&["logs/.cache/c.gz"])
+        );
+        assert_eq!(
+            glob(&pattern("logs/.cache/*"))?,
+            paths(&["logs/.cache/c.gz"])
+        );
+
+        let result = glob(&pattern("logs/[a"));
+        assert!(result.is_err(), "Invalid pattern should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::InvalidInput,
+                "Error should be InvalidInput"
+            );
+        }
+
+        Ok(())
+    }
+}
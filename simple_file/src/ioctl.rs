@@ -0,0 +1,188 @@
+/*
+    ioctl是设备文件、特殊文件系统的"万能"接口，请求码和参数的类型由驱动自己定义
+
+    ioctl(fd: i32, request: c_ulong, ...) -> i32
+    第三个参数通常是指向结构体或整数的指针，内核按请求码读写它
+    类型不匹配会导致内核读写错误的内存，所以通用的ioctl只能是unsafe的，常用的请求在这里提供安全的封装
+
+    块设备(Linux)：BLKGETSIZE64得到以字节为单位的设备大小，普通文件的fstat只能看到st_size为0
+    BLKSSZGET得到逻辑块大小，O_DIRECT读写块设备时偏移量和长度需要按它对齐
+*/
+
+use crate::{File, Result};
+
+impl File {
+    /// 发起一次ioctl，失败时与其他方法一样记录last_errno并附带fd和路径
+    ///
+    /// # Safety
+    /// arg必须指向request所要求类型的、有效的内存
+    pub unsafe fn ioctl(&mut self, request: libc::c_ulong, arg: *mut libc::c_void) -> Result<i32> {
+        self.ensure_open()?;
+
+        let result = unsafe { libc::ioctl(self.fd, request as _, arg) };
+        if result < 0 {
+            return Err(self.record_os_error("ioctl"));
+        }
+
+        Ok(result)
+    }
+
+    /// 管道、套接字、终端中可以立即读取而不阻塞的字节数，普通文件为剩余未读的字节数(FIONREAD)
+    pub fn bytes_available(&mut self) -> Result<usize> {
+        let mut n: libc::c_int = 0;
+        unsafe { self.ioctl(libc::FIONREAD as _, &mut n as *mut _ as *mut libc::c_void)? };
+
+        Ok(n as usize)
+    }
+
+    /// 终端的窗口大小(行数, 列数)，不是终端时返回ENOTTY(TIOCGWINSZ)
+    pub fn window_size(&mut self) -> Result<(u16, u16)> {
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        unsafe {
+            self.ioctl(
+                libc::TIOCGWINSZ as _,
+                &mut size as *mut _ as *mut libc::c_void,
+            )?
+        };
+
+        Ok((size.ws_row, size.ws_col))
+    }
+
+    /// 块设备的大小，单位字节，不是块设备时返回ENOTTY(BLKGETSIZE64)
+    #[cfg(target_os = "linux")]
+    pub fn block_device_size(&mut self) -> Result<u64> {
+        let mut size: u64 = 0;
+        unsafe { self.ioctl(BLKGETSIZE64, &mut size as *mut _ as *mut libc::c_void)? };
+
+        Ok(size)
+    }
+
+    /// 块设备的逻辑块大小，通常为512或4096(BLKSSZGET)
+    #[cfg(target_os = "linux")]
+    pub fn logical_block_size(&mut self) -> Result<u32> {
+        let mut size: libc::c_int = 0;
+        unsafe {
+            self.ioctl(
+                libc::BLKSSZGET as _,
+                &mut size as *mut _ as *mut libc::c_void,
+            )?
+        };
+
+        Ok(size as u32)
+    }
+}
+
+// libc没有提供BLKGETSIZE64，按_IOR(0x12, 114, size_t)计算，读方向的编码随架构不同
+#[cfg(all(
+    target_os = "linux",
+    any(
+        target_arch = "mips",
+        target_arch = "mips64",
+        target_arch = "powerpc",
+        target_arch = "powerpc64",
+        target_arch = "sparc64"
+    )
+))]
+const IOC_READ: libc::c_ulong = 2 << 29;
+#[cfg(all(
+    target_os = "linux",
+    not(any(
+        target_arch = "mips",
+        target_arch = "mips64",
+        target_arch = "powerpc",
+        target_arch = "powerpc64",
+        target_arch = "sparc64"
+    ))
+))]
+const IOC_READ: libc::c_ulong = 2 << 30;
+#[cfg(target_os = "linux")]
+const BLKGETSIZE64: libc::c_ulong =
+    IOC_READ | ((size_of::<libc::size_t>() as libc::c_ulong) << 16) | (0x12 << 8) | 114;
+
+#[cfg(test)]
+mod tests {
+    use crate::temp::TempFile;
+    use crate::{File, OpenMode};
+    use std::io;
+    use std::os::fd::FromRawFd;
+
+    #[test]
+    fn test_bytes_available() -> io::Result<()> {
+        let (reader, writer) = crate::pipe()?;
+        let (mut reader, mut writer) = (reader.into_file(), writer.into_file());
+
+        assert_eq!(
+            reader.bytes_available()?,
+            0,
+            "Empty pipe has nothing to read"
+        );
+        writer.write_all(b"Hello")?;
+        assert_eq!(reader.bytes_available()?, 5);
+
+        let mut buf = [0u8; 2];
+        reader.read_exact(&mut buf)?;
+        assert_eq!(
+            reader.bytes_available()?,
+            3,
+            "Read bytes should be consumed"
+        );
+
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_block_device() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let mut file = File::open(temp_file.path(), OpenMode::Read)?;
+        let result = file.block_device_size();
+        assert!(result.is_err(), "Regular file is not a block device");
+        if let Err(e) = result {
+            assert_eq!(
+                e.raw_os_error(),
+                Some(libc::ENOTTY),
+                "Error should be ENOTTY"
+            );
+        }
+
+        // 需要能打开某个块设备，例如以root运行时的loop设备，否则跳过
+        let mut device = match File::open("/dev/loop0", OpenMode::Read) {
+            Ok(device) => device,
+            Err(_) => return Ok(()),
+        };
+        assert!(device.metadata()?.file_type().is_block_device());
+
+        let block_size = device.logical_block_size()?;
+        assert!(block_size.is_power_of_two() && block_size >= 512);
+        let size = device.block_device_size()?;
+        assert_eq!(size % block_size as u64, 0, "Size should be whole blocks");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_window_size() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let mut file = File::open(temp_file.path(), OpenMode::Read)?;
+
+        let result = file.window_size();
+        assert!(result.is_err(), "Regular file is not a terminal");
+        if let Err(e) = result {
+            assert_eq!(
+                e.raw_os_error(),
+                Some(libc::ENOTTY),
+                "Error should be ENOTTY"
+            );
+        }
+        assert_eq!(file.last_errno(), Some(libc::ENOTTY));
+
+        // 环境中没有/dev/ptmx时跳过
+        let fd = unsafe { libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY) };
+        if fd >= 0 {
+            let mut pty = unsafe { File::from_raw_fd(fd) };
+            pty.window_size()?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,8 +1,90 @@
-use libc::{O_CREAT, O_RDONLY, O_RDWR, O_TRUNC, O_WRONLY, close, open, read, write};
+use libc::{O_CLOEXEC, O_CREAT, O_RDONLY, O_RDWR, O_TRUNC, O_WRONLY, close, open};
 use std::ffi::CString;
 use std::io;
-use std::io::{Read, Write};
-use std::path::Path;
+use std::io::{IoSlice, IoSliceMut, Read, Seek, SeekFrom, Write};
+use std::mem::MaybeUninit;
+use std::path::{Path, PathBuf};
+use std::slice;
+
+#[cfg(all(target_os = "linux", feature = "acl"))]
+mod acl;
+#[cfg(target_os = "linux")]
+mod advice;
+mod aligned;
+mod append;
+mod atomic;
+mod dir;
+mod error;
+mod fd;
+mod fs;
+mod fsstats;
+mod glob;
+mod ioctl;
+mod lock;
+mod lockfile;
+#[cfg(target_os = "linux")]
+mod memfd;
+mod metadata;
+mod mmap;
+mod options;
+mod pipe;
+#[cfg(target_os = "linux")]
+mod space;
+#[cfg(target_os = "linux")]
+mod splice;
+#[cfg(target_os = "linux")]
+mod statx;
+mod stdio;
+mod sys;
+pub mod temp;
+mod times;
+mod usage;
+mod vectored;
+mod walk;
+#[cfg(target_os = "linux")]
+mod xattr;
+
+#[cfg(all(target_os = "linux", feature = "acl"))]
+pub use acl::{Acl, AclEntry, AclTag, get_acl, set_acl};
+#[cfg(target_os = "linux")]
+pub use advice::Advice;
+pub use aligned::AlignedBuf;
+pub use append::{AppendSync, SyncPolicy};
+pub use atomic::{AtomicWriter, write_atomic};
+pub use dir::{
+    Dir, DirBuilder, DirEntry, ReadDir, create_dir, create_dir_all, read_dir, remove_dir,
+    remove_dir_all,
+};
+pub use error::{Error, Result, errno};
+pub use fs::{
+    AccessMode, access, canonicalize, chown, copy, copy_with_buffer_size, exists, hard_link,
+    is_dir, is_file, lchown, metadata, mkfifo, move_file, move_file_with_progress, read, read_link,
+    read_to_string, reflink, remove_file, rename, same_filesystem, set_permissions, symlink,
+    symlink_metadata, write,
+};
+#[cfg(target_os = "linux")]
+pub use fs::{rename_exchange, rename_noreplace};
+pub use fsstats::{FsStats, fs_stats};
+pub use glob::glob;
+pub use lock::{FileLock, LockInfo, LockKind, RangeLock};
+pub use lockfile::LockFile;
+#[cfg(target_os = "linux")]
+pub use memfd::Seals;
+pub use metadata::{FileType, Metadata, Permissions};
+pub use mmap::{Mmap, MmapMut};
+pub use options::OpenOptions;
+pub use pipe::{PipeReader, PipeWriter, pipe};
+#[cfg(target_os = "linux")]
+pub use statx::{ExtendedMetadata, extended_metadata};
+pub use stdio::{Stderr, Stdin, Stdout, stderr, stdin, stdout};
+pub use times::FileTimes;
+pub use usage::{DiskUsage, DiskUsageOptions, disk_usage};
+pub use walk::{Walk, WalkEntry, walk};
+#[cfg(target_os = "linux")]
+pub use xattr::{
+    get_xattr, lget_xattr, list_xattrs, llist_xattrs, lremove_xattr, lset_xattr, remove_xattr,
+    set_xattr,
+};
 
 /////////表示文件打开模式////////////////////
 #[derive(Clone, Copy)]
@@ -10,12 +92,17 @@ pub enum OpenMode {
     Read,
     Write,
     ReadWrite,
+    Append, // 只写，每次写入都追加到文件末尾，不会截断已有内容
 }
 
 /////////////////////////////////////////////
 #[allow(dead_code)]
 pub struct File {
     fd: i32,
+    path: Option<PathBuf>, // 打开时使用的路径，用于错误信息，通过fd得到的File没有路径
+    path_only: bool,       // 是否为O_PATH句柄，只能用于路径相关操作，不能读写
+    last_errno: Option<i32>, // 最近一次失败操作的errno，便于排查偶发的EAGAIN/EINTR
+    retry_interrupted: bool, // 系统调用被信号打断(EINTR)时是否自动重试
 }
 
 const INVALID_FD: i32 = -1;
@@ -41,259 +128,1124 @@ https://pubs.opengroup.org/onlinepubs/007904875/functions/open.html 这里是对
 
 */
 impl File {
+    fn from_fd(fd: i32) -> File {
+        File {
+            fd,
+            path: None,
+            path_only: false,
+            last_errno: None,
+            retry_interrupted: true,
+        }
+    }
+
+    /*
+        read/write等系统调用被信号打断(EINTR)时默认自动重试
+        设置为false后把Interrupted错误原样返回，便于调用方在信号到来时中断阻塞的读写
+    */
+    pub fn set_retry_interrupted(&mut self, retry: bool) {
+        self.retry_interrupted = retry;
+    }
+
+    /// 打开时使用的路径，通过fd、tmpfile等方式得到的File为None
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    // 为err附带系统调用名、路径和fd
+    fn context_error(&self, err: io::Error, op: &'static str) -> Error {
+        error::with_fd_context(err, op, self.path(), Some(self.fd))
+    }
+
+    // 取出errno，附带系统调用名、路径和fd，作为crate的Error返回
+    fn os_error(&self, op: &'static str) -> Error {
+        self.context_error(io::Error::last_os_error(), op)
+    }
+
+    // 与os_error相同，同时把errno记录到last_errno中
+    fn record_os_error(&mut self, op: &'static str) -> Error {
+        let err = self.os_error(op);
+        self.last_errno = err.raw_os_error();
+        err
+    }
+
+    // 相对于self打开path时，新文件的路径；self没有路径时只有绝对路径是已知的
+    fn join_path(&self, path: &Path) -> Option<PathBuf> {
+        match &self.path {
+            Some(dir) => Some(dir.join(path)),
+            None => path.is_absolute().then(|| path.to_path_buf()),
+        }
+    }
+
+    // ensure_*的错误信息，有路径时带上路径
+    fn state_error(&self, msg: &str) -> Error {
+        let msg = match &self.path {
+            Some(path) => format!("{}: {}", msg, path.display()),
+            None => msg.to_string(),
+        };
+        Error::from(io::Error::new(io::ErrorKind::InvalidInput, msg))
+    }
+
+    /// 该File上最近一次失败的系统调用的errno，从未失败过则为None
+    pub fn last_errno(&self) -> Option<i32> {
+        self.last_errno
+    }
+
+    // 已关闭的File不允许任何操作
+    fn ensure_open(&self) -> Result<()> {
+        if self.fd == INVALID_FD {
+            return Err(self.state_error("File is closed"));
+        }
+
+        Ok(())
+    }
+
+    // O_PATH打开的File只表示一个位置，不能读写
+    fn ensure_readable(&self) -> Result<()> {
+        self.ensure_open()?;
+        if self.path_only {
+            return Err(self.state_error("File opened with O_PATH can not be read"));
+        }
+
+        Ok(())
+    }
+
+    fn ensure_writable(&self) -> Result<()> {
+        self.ensure_open()?;
+        if self.path_only {
+            return Err(self.state_error("File opened with O_PATH can not be written"));
+        }
+
+        Ok(())
+    }
+
+    /// 以mode打开文件，需要创建文件时使用默认权限0o644
+    pub fn open<P: AsRef<Path>>(path: P, mode: OpenMode) -> Result<File> {
+        File::open_with_mode(path, mode, DEFAULT_FILE_PERMSSIONS as u32)
+    }
+
+    /// 只读打开，等价于File::open(path, OpenMode::Read)
+    pub fn open_read<P: AsRef<Path>>(path: P) -> Result<File> {
+        File::open(path, OpenMode::Read)
+    }
+
+    /// 只写打开，不存在则创建，存在则截断，等价于File::open(path, OpenMode::Write)
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<File> {
+        File::open(path, OpenMode::Write)
+    }
+
+    /// 只写打开/dev/null，写入的数据全部被丢弃，可以替代不需要的输出
+    pub fn null_sink() -> Result<File> {
+        OpenOptions::new().write(true).open("/dev/null")
+    }
+
+    /// 只读打开/dev/null，读取总是立即返回EOF
+    pub fn null_source() -> Result<File> {
+        File::open_read("/dev/null")
+    }
+
+    /// 与open相同，但创建文件时使用perm作为权限，例如私密文件0o600、脚本0o755
+    /// perm只在文件被创建时生效，实际权限为perm & !umask
     /// step1: 构建c-style文件路径字符串
     /// step2: 组装打开模式
     /// step3: unsafe封装POSIX open函数
     /// step4: 返回结果File
-    pub fn open<P: AsRef<Path>>(path: P, mode: OpenMode) -> io::Result<File> {
-        let path = path.as_ref();
-        if path.as_os_str().is_empty() {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "Invalid path, empty not allowed",
+    pub fn open_with_mode<P: AsRef<Path>>(path: P, mode: OpenMode, perm: u32) -> Result<File> {
+        let c_style_str_path = to_c_path(path.as_ref())?;
+        let flags = open_flags(mode);
+
+        let fd = retry_eintr(true, || unsafe {
+            open(c_style_str_path.as_ptr(), flags, perm as libc::c_uint)
+        });
+
+        if fd == INVALID_FD {
+            return Err(error::with_context(
+                io::Error::last_os_error(),
+                "open",
+                Some(path.as_ref()),
             ));
         }
-        let c_style_str_path = CString::new(
-            path.to_str()
-                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid path"))?,
-        )?;
 
-        let flags = match mode {
-            OpenMode::Read => O_RDONLY,
-            OpenMode::Write => O_WRONLY | O_CREAT | O_TRUNC,
-            OpenMode::ReadWrite => O_RDWR | O_CREAT,
-        };
+        let mut file = File::from_fd(fd);
+        file.path = Some(path.as_ref().to_path_buf());
+
+        Ok(file)
+    }
+
+    /*
+        以读写方式创建一个新文件，使用O_CREAT | O_EXCL，检查与创建是一个原子操作
+        文件已存在(包括指向任何位置的符号链接)时返回AlreadyExists，可用于实现锁文件或"只写一次"的语义
+    */
+    pub fn create_new<P: AsRef<Path>>(path: P) -> Result<File> {
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(path)
+    }
+
+    /*
+        O_PATH (Linux) 打开的句柄不具备读写能力，只表示文件系统中的一个位置
+        可以作为openat/fstatat等*at系列函数的dirfd使用，遍历目录树时无需读写权限
+    */
+    #[cfg(target_os = "linux")]
+    pub fn open_path<P: AsRef<Path>>(path: P) -> Result<File> {
+        let c_style_str_path = to_c_path(path.as_ref())?;
+
+        let fd = retry_eintr(true, || unsafe {
+            open(c_style_str_path.as_ptr(), libc::O_PATH | O_CLOEXEC)
+        });
+
+        if fd == INVALID_FD {
+            return Err(error::with_context(
+                io::Error::last_os_error(),
+                "open",
+                Some(path.as_ref()),
+            ));
+        }
+
+        let mut file = File::from_fd(fd);
+        file.path = Some(path.as_ref().to_path_buf());
+        file.path_only = true;
+
+        Ok(file)
+    }
+
+    /*
+        以self作为目录，打开相对于它的路径，封装POSIX openat
+        openat(dirfd: i32, path: *const c_char, flags: i32, mode: mode_t) -> i32
+        path为绝对路径时dirfd被忽略，与open等价
+    */
+    pub fn open_at<P: AsRef<Path>>(&self, path: P, mode: OpenMode) -> Result<File> {
+        self.ensure_open()?;
 
-        let fd = unsafe { open(c_style_str_path.as_ptr(), flags, DEFAULT_FILE_PERMSSIONS) };
+        let c_style_str_path = to_c_path(path.as_ref())?;
+        let flags = open_flags(mode);
 
+        let fd = retry_eintr(self.retry_interrupted, || unsafe {
+            libc::openat(
+                self.fd,
+                c_style_str_path.as_ptr(),
+                flags,
+                DEFAULT_FILE_PERMSSIONS,
+            )
+        });
+
+        let path = self.join_path(path.as_ref());
         if fd == INVALID_FD {
-            return Err(io::Error::last_os_error());
+            return Err(error::with_fd_context(
+                io::Error::last_os_error(),
+                "openat",
+                path.as_deref(),
+                Some(self.fd),
+            ));
         }
 
-        Ok(File { fd })
+        let mut file = File::from_fd(fd);
+        file.path = path;
+        file.retry_interrupted = self.retry_interrupted;
+
+        Ok(file)
     }
 
     /*
         实现read方法，同样通过封装posix read syscall实现
-        注意这里的io::Result<T>其实是std::result::Result<T, E>的alias别名
-        因为而io中的错误实在太常见了，简化目的创建了一个别名
+        注意这里的Result<T>其实是std::result::Result<T, simple_file::Error>的alias别名
+        与io::Result<T>一样，错误实在太常见了，简化目的创建了一个别名；impl Read中的read把它转换为io::Error
 
         read(fd: i32, buf: *mut c_void, count: size_t) -> ssize_t
     */
-    pub fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        if self.fd == INVALID_FD {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "File is closed",
-            ));
-        }
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.ensure_readable()?;
 
         let len = buf.len();
-        let result = unsafe {
+        let result = retry_eintr(self.retry_interrupted, || unsafe {
             // fd， 缓冲区，读取大小，字节为基本单位
-            read(self.fd, buf.as_mut_ptr() as *mut _, len as libc::size_t)
-        };
+            libc::read(self.fd, buf.as_mut_ptr() as *mut _, len as libc::size_t)
+        });
 
         if result < 0 {
-            return Err(io::Error::last_os_error());
+            return Err(self.record_os_error("read"));
         }
 
         Ok(result as usize)
     }
 
+    /*
+        read返回0有两种含义：缓冲区长度为0，或者到达了EOF
+        read_or_eof把二者区分开：缓冲区非空且read返回0时为Ok(None)，其余情况为Ok(Some(n))
+    */
+    pub fn read_or_eof(&mut self, buf: &mut [u8]) -> Result<Option<usize>> {
+        let n = self.read(buf)?;
+        if n == 0 && !buf.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(n))
+    }
+
     /*
        实现写入方法， 依旧通过封装POSIX write syscall实现
 
        write(fd: i32, buf: *const c_void, count: size_t) -> ssize_t
     */
-    pub fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        if self.fd == INVALID_FD {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "File is closed",
-            ));
-        }
+    pub fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.ensure_writable()?;
 
         let len = buf.len();
-        let result = unsafe { write(self.fd, buf.as_ptr() as *const _, len as libc::size_t) };
+        let result = retry_eintr(self.retry_interrupted, || unsafe {
+            libc::write(self.fd, buf.as_ptr() as *const _, len as libc::size_t)
+        });
 
         if result < 0 {
-            return Err(io::Error::last_os_error());
+            return Err(self.record_os_error("write"));
         }
 
         Ok(result as usize)
     }
-}
 
-/*
-    POSIX open函数是需要手动释放资源的， 所以也要有对等的rust实现
-    Rust通过RAII（资源获取即初始化）进行自动的资源释放，通过实现Drop trat即可
-*/
-impl Drop for File {
-    fn drop(&mut self) {
-        if self.fd != INVALID_FD {
-            unsafe {
-                close(self.fd);
+    /*
+        循环调用write直到buf全部写入，管道、套接字以及被信号打断时write可能只写入一部分
+        被信号打断(EINTR)时按retry_interrupted决定是否重试，write返回0时返回WriteZero
+    */
+    pub fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.write(buf) {
+                Ok(0) => {
+                    return Err(Error::new(
+                        io::ErrorKind::WriteZero,
+                        "Failed to write whole buffer",
+                    ));
+                }
+                Ok(n) => buf = &buf[n..],
+                Err(e) if self.retry_interrupted && e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
             }
-
-            self.fd = INVALID_FD; // 避免重复关闭
         }
-    }
-}
 
-impl Read for File {
-    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.read(buf)
+        Ok(())
     }
-}
 
-impl Write for File {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.write(buf)
-    }
+    /*
+        循环调用read直到填满buf，被信号打断(EINTR)时按retry_interrupted决定是否重试
+        填满之前遇到EOF时返回UnexpectedEof，此时buf中已读取部分的内容是不确定的
+    */
+    pub fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.read(buf) {
+                Ok(0) => {
+                    return Err(Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "Failed to fill whole buffer",
+                    ));
+                }
+                Ok(n) => buf = &mut buf[n..],
+                Err(e) if self.retry_interrupted && e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
 
-    fn flush(&mut self) -> io::Result<()> {
         Ok(())
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::{File, OpenMode};
-    use std::io::{self, Read, Write};
-    use tempfile::NamedTempFile;
 
-    #[test]
-    fn test_open_read() -> io::Result<()> {
-        // 创建一个临时文件并写入内容
-        let mut temp_file = NamedTempFile::new()?;
-        temp_file.write_all(b"Hello, world!")?;
+    /*
+        封装POSIX lseek，返回移动后的偏移量
+        lseek(fd: i32, offset: off_t, whence: i32) -> off_t
+        whence: SEEK_SET从头开始，SEEK_CUR从当前位置开始，SEEK_END从文件末尾开始
+    */
+    fn lseek(&self, offset: i64, whence: i32) -> Result<u64> {
+        self.ensure_open()?;
 
-        let file = File::open(temp_file.path(), OpenMode::Read)?;
-        assert!(file.fd >= 0, "File descriptor should be valid");
+        let result = unsafe { sys::lseek(self.fd, offset as sys::off_t, whence) };
+        if result < 0 {
+            return Err(self.os_error("lseek"));
+        }
 
-        Ok(())
+        Ok(result as u64)
     }
 
-    #[test]
-    fn test_open_write() -> io::Result<()> {
-        let temp_file = NamedTempFile::new()?;
-        let file = File::open(temp_file.path(), OpenMode::Write)?;
-        assert!(file.fd >= 0, "File descriptor should be valid");
+    /// 移动读写位置，返回移动后距文件开头的偏移量
+    pub fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let (offset, whence) = match pos {
+            SeekFrom::Start(offset) => (
+                i64::try_from(offset).map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "Invalid seek offset")
+                })?,
+                libc::SEEK_SET,
+            ),
+            SeekFrom::End(offset) => (offset, libc::SEEK_END),
+            SeekFrom::Current(offset) => (offset, libc::SEEK_CUR),
+        };
 
-        Ok(())
+        self.lseek(offset, whence)
     }
 
-    #[test]
-    fn test_open_read_write() -> io::Result<()> {
-        let temp_file = NamedTempFile::new()?;
-        let file = File::open(temp_file.path(), OpenMode::ReadWrite)?;
-        assert!(file.fd >= 0, "File descriptor should be valid");
+    /// 当前读写位置，等价于seek(SeekFrom::Current(0))
+    pub fn stream_position(&mut self) -> Result<u64> {
+        self.lseek(0, libc::SEEK_CUR)
+    }
 
+    /// 回到文件开头，等价于seek(SeekFrom::Start(0))
+    pub fn rewind(&mut self) -> Result<()> {
+        self.lseek(0, libc::SEEK_SET)?;
         Ok(())
     }
 
-    #[test]
-    fn test_open_nonexistent_file() {
-        let result = File::open("nonexistent.txt", OpenMode::Read);
-        assert!(result.is_err(), "Opening nonexistent file should fail");
-        if let Err(e) = result {
-            assert_eq!(
-                e.kind(),
-                io::ErrorKind::NotFound,
-                "Error should be NotFound"
-            );
+    /*
+        封装POSIX fstat，获取文件的元信息，例如大小、权限、类型等
+        fstat(fd: i32, buf: *mut stat) -> i32
+    */
+    fn fstat(&self) -> Result<sys::stat> {
+        self.ensure_open()?;
+
+        let mut stat: sys::stat = unsafe { std::mem::zeroed() };
+        let result = unsafe { sys::fstat(self.fd, &mut stat) };
+        if result < 0 {
+            return Err(self.os_error("fstat"));
         }
+
+        Ok(stat)
     }
 
-    #[test]
-    fn test_open_invalid_path() {
-        let result = File::open("", OpenMode::Read);
-        assert!(result.is_err(), "Opening empty path should fail");
-        if let Err(e) = result {
-            assert_eq!(
-                e.kind(),
-                io::ErrorKind::InvalidInput,
-                "Error should be InvalidInput"
-            );
+    /// 当前读写位置是否已到达文件末尾
+    /// step1: fstat获取文件大小，只有普通文件的st_size是内容长度，管道、设备等返回InvalidInput
+    /// step2: lseek(0, SEEK_CUR)获取当前位置
+    /// step3: 比较二者
+    pub fn at_eof(&mut self) -> Result<bool> {
+        let metadata = self.metadata()?;
+        if !metadata.is_file() {
+            return Err(self.state_error("EOF is only known for regular files"));
         }
+        let position = self.stream_position()?;
+
+        Ok(position >= metadata.len())
     }
 
-    // 测试读取
-    #[test]
-    fn test_read_content() -> io::Result<()> {
-        let mut temp_file = NamedTempFile::new()?;
-        let content = b"Hello, world!";
-        temp_file.write_all(content)?;
+    /*
+        把文件截断到当前读写位置，封装POSIX ftruncate
+        ftruncate(fd: i32, length: off_t) -> i32
+        ReadWrite模式不会截断文件，用更短的内容覆盖写入后，可以用它去掉旧内容残留的尾巴
+    */
+    pub fn truncate_to_position(&mut self) -> Result<()> {
+        let position = self.lseek(0, libc::SEEK_CUR)?;
+        self.set_len(position)
+    }
 
-        let mut file = File::open(temp_file.path(), OpenMode::Read)?;
-        let mut buf = [0u8; 128];
-        let n = file.read(&mut buf)?;
-        assert_eq!(n, content.len(), "Should read exact number of bytes");
-        assert_eq!(&buf[..n], content, "Read content should match");
+    /*
+        把文件长度设置为size，封装POSIX ftruncate
+        比原来短时截掉多余的部分，比原来长时用0填充(通常是不占磁盘空间的空洞)
+        不会改变当前读写位置，文件必须以可写方式打开
+    */
+    pub fn set_len(&mut self, size: u64) -> Result<()> {
+        self.ensure_open()?;
+        let size = to_off_t(size)?;
+
+        let result = unsafe { sys::ftruncate(self.fd, size) };
+        if result < 0 {
+            return Err(self.record_os_error("ftruncate"));
+        }
 
         Ok(())
     }
 
-    #[test]
-    fn test_read_empty_file() -> io::Result<()> {
-        let temp_file = NamedTempFile::new()?;
-        let mut file = File::open(temp_file.path(), OpenMode::Read)?;
-        let mut buf = [0u8; 128];
-        let n = file.read(&mut buf)?;
-        assert_eq!(n, 0, "Reading empty file should return 0 bytes");
+    /*
+        复制文件描述符，得到一个生命周期独立的File，封装fcntl(F_DUPFD_CLOEXEC)
+        fcntl(fd: i32, cmd: i32, min_fd: i32) -> i32
+        与dup不同，新描述符带有FD_CLOEXEC，不会被exec出的子进程继承
+        两个File共享同一个打开文件描述(读写位置、打开方式)，任意一个关闭不影响另一个
+    */
+    pub fn try_clone(&self) -> Result<File> {
+        self.ensure_open()?;
 
-        Ok(())
+        let fd = unsafe { libc::fcntl(self.fd, libc::F_DUPFD_CLOEXEC, 0) };
+        if fd == INVALID_FD {
+            return Err(self.os_error("fcntl"));
+        }
+
+        let mut file = File::from_fd(fd);
+        file.path = self.path.clone();
+        file.path_only = self.path_only;
+        file.retry_interrupted = self.retry_interrupted;
+
+        Ok(file)
     }
 
-    #[test]
-    fn test_read_empty_buffer() -> io::Result<()> {
-        let mut temp_file = NamedTempFile::new()?;
-        temp_file.write_all(b"Some content")?;
-        let mut file = File::open(temp_file.path(), OpenMode::Read)?;
-        let mut buf = [];
-        let n = file.read(&mut buf)?;
-        assert_eq!(n, 0, "Reading with empty buffer should return 0");
+    /*
+        切换非阻塞模式，通过fcntl(F_GETFL/F_SETFL)修改O_NONBLOCK
+        非阻塞模式下，FIFO、管道、字符设备暂时没有数据可读或无法写入时，
+        read/write立即返回ErrorKind::WouldBlock(EAGAIN)，而不是阻塞等待，便于配合事件循环使用
+        普通文件总是"就绪"的，这个标志对它们没有效果
+        注意O_NONBLOCK属于打开文件描述，try_clone得到的File会共享这个状态
+    */
+    pub fn set_nonblocking(&mut self, nonblocking: bool) -> Result<()> {
+        self.ensure_open()?;
+
+        let flags = unsafe { libc::fcntl(self.fd, libc::F_GETFL) };
+        if flags < 0 {
+            return Err(self.record_os_error("fcntl"));
+        }
+
+        let new_flags = if nonblocking {
+            flags | libc::O_NONBLOCK
+        } else {
+            flags & !libc::O_NONBLOCK
+        };
+        if new_flags != flags && unsafe { libc::fcntl(self.fd, libc::F_SETFL, new_flags) } < 0 {
+            return Err(self.record_os_error("fcntl"));
+        }
 
         Ok(())
     }
 
-    #[test]
-    fn test_read_invalid_fd() {
-        let mut file = File { fd: -1 }; // 手动构造无效文件描述符
-        let mut buf = [0u8; 128];
-        let result = file.read(&mut buf);
-        assert!(result.is_err(), "Reading with invalid fd should fail");
-        if let Err(e) = result {
-            assert_eq!(
-                e.kind(),
-                io::ErrorKind::InvalidInput,
-                "Error should be InvalidInput"
-            );
+    /*
+        是否连接到终端，封装POSIX isatty
+        isatty(fd: i32) -> i32
+        命令行工具据此决定是否输出颜色、进度条；已关闭的File以及isatty失败(ENOTTY等)时为false
+    */
+    pub fn is_terminal(&self) -> bool {
+        self.fd != INVALID_FD && unsafe { libc::isatty(self.fd) } == 1
+    }
+
+    /*
+        把文件的数据和元信息同步到存储设备，封装POSIX fsync
+        fsync(fd: i32) -> i32
+        write返回只代表数据进入了内核缓存，fsync返回后才能保证断电不丢失
+    */
+    pub fn sync_all(&self) -> Result<()> {
+        self.ensure_open()?;
+
+        let result = unsafe { libc::fsync(self.fd) };
+        if result < 0 {
+            return Err(self.os_error("fsync"));
         }
+
+        Ok(())
     }
 
-    // 测试写入
-    #[test]
-    fn test_write_content() -> io::Result<()> {
-        let temp_file = NamedTempFile::new()?;
-        let content = b"Hello, world!";
-        let mut file = File::open(temp_file.path(), OpenMode::Write)?;
-        let n = file.write(content)?;
-        assert_eq!(n, content.len(), "Should write exact number of bytes");
+    /*
+        只同步文件数据，封装POSIX fdatasync
+        fdatasync(fd: i32) -> i32
+        与fsync相比不会为mtime等无关的元信息产生额外的磁盘写入，但文件长度变化仍会被同步
+        没有fdatasync的平台(macOS)退回fsync
+    */
+    pub fn sync_data(&self) -> Result<()> {
+        self.ensure_open()?;
 
-        let mut std_file = std::fs::File::open(temp_file.path())?;
-        let mut read_content = Vec::new();
-        std_file.read_to_end(&mut read_content)?;
-        assert_eq!(read_content, content, "Written content should match");
+        #[cfg(target_os = "linux")]
+        let result = unsafe { libc::fdatasync(self.fd) };
+        #[cfg(not(target_os = "linux"))]
+        let result = unsafe { libc::fsync(self.fd) };
+        if result < 0 {
+            return Err(self.os_error("fdatasync"));
+        }
 
         Ok(())
     }
 
-    #[test]
-    fn test_write_invalid_fd() {
-        let mut file = File { fd: -1 }; // 手动构造无效文件描述符
-        let content = b"test";
+    /*
+        从offset处读取数据，封装POSIX pread
+        pread(fd: i32, buf: *mut c_void, count: size_t, offset: off_t) -> ssize_t
+        不使用也不改变当前读写位置，只需要&self，多个线程可以在同一个File上并发随机读取
+    */
+    pub fn read_at(&self, buf: &mut [u8], offset: u64) -> Result<usize> {
+        self.ensure_open()?;
+        let offset = to_off_t(offset)?;
+
+        let n = retry_eintr(self.retry_interrupted, || unsafe {
+            sys::pread(
+                self.fd,
+                buf.as_mut_ptr() as *mut libc::c_void,
+                buf.len() as libc::size_t,
+                offset,
+            )
+        });
+        if n < 0 {
+            return Err(self.os_error("pread"));
+        }
+
+        Ok(n as usize)
+    }
+
+    /*
+        在offset处写入数据，封装POSIX pwrite
+        pwrite(fd: i32, buf: *const c_void, count: size_t, offset: off_t) -> ssize_t
+        不改变当前读写位置；注意Linux下以O_APPEND打开的文件，pwrite依然会追加到末尾
+    */
+    pub fn write_at(&self, buf: &[u8], offset: u64) -> Result<usize> {
+        self.ensure_open()?;
+        let offset = to_off_t(offset)?;
+
+        let n = retry_eintr(self.retry_interrupted, || unsafe {
+            sys::pwrite(
+                self.fd,
+                buf.as_ptr() as *const libc::c_void,
+                buf.len() as libc::size_t,
+                offset,
+            )
+        });
+        if n < 0 {
+            return Err(self.os_error("pwrite"));
+        }
+
+        Ok(n as usize)
+    }
+
+    /*
+        在同一个文件内，把[src_off, src_off + len)复制到[dst_off, dst_off + len)，返回复制的字节数
+        源范围超出文件末尾时只复制到末尾为止，两个范围不能重叠
+        Linux下优先使用copy_file_range在内核中完成复制，不支持时退回pread/pwrite循环
+    */
+    pub fn copy_range(&self, src_off: u64, dst_off: u64, len: u64) -> Result<u64> {
+        self.ensure_open()?;
+        if len > 0 && src_off < dst_off.saturating_add(len) && dst_off < src_off.saturating_add(len)
+        {
+            return Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                "Invalid copy range, overlapping not allowed",
+            ));
+        }
+
+        self.copy_between(self, src_off, dst_off, len)
+    }
+
+    /*
+        从另一个File复制最多len字节到self，返回复制的字节数，src到达末尾时可能少于len
+        offsets为Some((src_off, dst_off))时在指定偏移量读写，不改变两个File的读写位置；
+        为None时从两个File各自的当前位置开始，复制完成后两个位置都向后移动
+        Linux下使用copy_file_range，数据不经过用户态，NFS/CIFS等还可以由服务端完成复制
+    */
+    pub fn copy_range_from(
+        &mut self,
+        src: &File,
+        len: u64,
+        offsets: Option<(u64, u64)>,
+    ) -> Result<u64> {
+        self.ensure_writable()?;
+        src.ensure_readable()?;
+
+        let (src_off, dst_off) = match offsets {
+            Some(offsets) => offsets,
+            None => (
+                src.lseek(0, libc::SEEK_CUR)?,
+                self.lseek(0, libc::SEEK_CUR)?,
+            ),
+        };
+
+        let copied = self.copy_between(src, src_off, dst_off, len)?;
+        if offsets.is_none() {
+            src.lseek((src_off + copied) as i64, libc::SEEK_SET)?;
+            self.lseek((dst_off + copied) as i64, libc::SEEK_SET)?;
+        }
+
+        Ok(copied)
+    }
+
+    // 从src复制到self，copy_file_range不支持(跨文件系统、特殊文件等)时退回pread/pwrite
+    fn copy_between(&self, src: &File, src_off: u64, dst_off: u64, len: u64) -> Result<u64> {
+        #[cfg(target_os = "linux")]
+        match self.copy_file_range(src, src_off, dst_off, len) {
+            Ok(copied) => return Ok(copied),
+            Err(e)
+                if matches!(
+                    e.raw_os_error(),
+                    Some(libc::ENOSYS | libc::EXDEV | libc::EOPNOTSUPP | libc::EINVAL)
+                ) => {}
+            Err(e) => return Err(e),
+        }
+
+        self.copy_range_by_pread(src, src_off, dst_off, len)
+    }
+
+    /*
+        copy_file_range(fd_in: i32, off_in: *mut off64_t, fd_out: i32, off_out: *mut off64_t,
+                        len: size_t, flags: u32) -> ssize_t
+        传入偏移量指针时不会改变文件的读写位置，返回0表示源已到末尾
+    */
+    #[cfg(target_os = "linux")]
+    fn copy_file_range(&self, src: &File, src_off: u64, dst_off: u64, len: u64) -> Result<u64> {
+        let mut off_in = to_off_t(src_off)? as libc::loff_t;
+        let mut off_out = to_off_t(dst_off)? as libc::loff_t;
+        let mut copied = 0u64;
+
+        while copied < len {
+            let result = retry_eintr(self.retry_interrupted, || unsafe {
+                libc::copy_file_range(
+                    src.fd,
+                    &mut off_in,
+                    self.fd,
+                    &mut off_out,
+                    (len - copied).min(isize::MAX as u64) as libc::size_t,
+                    0,
+                )
+            });
+            if result < 0 {
+                return Err(self.os_error("copy_file_range"));
+            }
+            if result == 0 {
+                break;
+            }
+            copied += result as u64;
+        }
+
+        Ok(copied)
+    }
+
+    // 用户态的退路，pread/pwrite指定偏移量读写，同样不改变文件的读写位置
+    fn copy_range_by_pread(&self, src: &File, src_off: u64, dst_off: u64, len: u64) -> Result<u64> {
+        const CHUNK_SIZE: usize = 64 * 1024;
+
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        let mut copied = 0u64;
+        while copied < len {
+            let to_read = std::cmp::min(CHUNK_SIZE as u64, len - copied) as usize;
+            let n = src.read_at(&mut buf[..to_read], src_off + copied)?;
+            if n == 0 {
+                break;
+            }
+
+            let mut written = 0usize;
+            while written < n {
+                let result = self.write_at(&buf[written..n], dst_off + copied + written as u64)?;
+                if result == 0 {
+                    return Err(Error::new(
+                        io::ErrorKind::WriteZero,
+                        "Failed to write whole buffer",
+                    ));
+                }
+                written += result;
+            }
+            copied += n as u64;
+        }
+
+        Ok(copied)
+    }
+
+    /*
+        读取剩余全部内容追加到buf，返回读取的字节数
+        Read::read_to_end按倍数扩容，读取大文件时峰值内存可能达到文件大小的2倍
+        调用方已知文件大小时可以传入cap_hint，先一次性扩容到恰好能容纳这么多字节，
+        读满后才用一个小的探测缓冲区确认是否还有数据，确有数据时再按倍数扩容
+        与std相同，只清零新增的容量：短读之后剩余的部分已经清零过，记录在initialized中，下一次不再重复清零
+    */
+    pub fn read_to_end_with_capacity(
+        &mut self,
+        buf: &mut Vec<u8>,
+        cap_hint: usize,
+    ) -> Result<usize> {
+        const PROBE_SIZE: usize = 32;
+
+        let start = buf.len();
+        buf.reserve_exact(cap_hint);
+        let mut initialized = 0; // buf.len()之后已经清零的字节数
+
+        loop {
+            if buf.len() == buf.capacity() {
+                let mut probe = [0u8; PROBE_SIZE];
+                match self.read(&mut probe) {
+                    Ok(0) => return Ok(buf.len() - start),
+                    Ok(n) => {
+                        // 扩容后新分配的容量还没有初始化
+                        buf.extend_from_slice(&probe[..n]);
+                        initialized = 0;
+                    }
+                    Err(e) if self.retry_interrupted && e.kind() == io::ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e),
+                }
+                continue;
+            }
+
+            // 直接读入剩余容量，只清零还没有初始化的部分
+            let len = buf.len();
+            let spare = buf.spare_capacity_mut();
+            spare[initialized..].fill(MaybeUninit::new(0));
+            let spare_len = spare.len();
+            // 上面已经把整个spare初始化
+            let dst =
+                unsafe { slice::from_raw_parts_mut(spare.as_mut_ptr().cast::<u8>(), spare_len) };
+
+            match self.read(dst) {
+                Ok(0) => return Ok(len - start),
+                Ok(n) => {
+                    // read返回的n不超过dst的长度，前n个字节已经写入
+                    unsafe { buf.set_len(len + n) };
+                    initialized = spare_len - n;
+                }
+                Err(e) if self.retry_interrupted && e.kind() == io::ErrorKind::Interrupted => {
+                    initialized = spare_len
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+// 校验路径并转换为c-style字符串，路径不能为空，也不能包含非UTF-8字符
+fn to_c_path(path: &Path) -> Result<CString> {
+    if path.as_os_str().is_empty() {
+        return Err(Error::new(
+            io::ErrorKind::InvalidInput,
+            "Invalid path, empty not allowed",
+        ));
+    }
+
+    Ok(CString::new(path.to_str().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "Invalid path")
+    })?)?)
+}
+
+// 执行一次系统调用，retry为true时，返回值小于0且errno为EINTR则重新调用
+fn retry_eintr<T: PartialOrd + Default>(retry: bool, mut f: impl FnMut() -> T) -> T {
+    loop {
+        let result = f();
+        if retry
+            && result < T::default()
+            && io::Error::last_os_error().kind() == io::ErrorKind::Interrupted
+        {
+            continue;
+        }
+
+        return result;
+    }
+}
+
+// 文件偏移量或长度 -> off_t，超出off_t范围时返回InvalidInput
+fn to_off_t(offset: u64) -> Result<sys::off_t> {
+    sys::off_t::try_from(offset)
+        .map_err(|_| Error::new(io::ErrorKind::InvalidInput, "Invalid offset, too large"))
+}
+
+// 打开模式 -> open flags
+fn open_flags(mode: OpenMode) -> i32 {
+    let flags = match mode {
+        OpenMode::Read => O_RDONLY,
+        OpenMode::Write => O_WRONLY | O_CREAT | O_TRUNC,
+        OpenMode::ReadWrite => O_RDWR | O_CREAT,
+        OpenMode::Append => O_WRONLY | O_CREAT | libc::O_APPEND,
+    };
+
+    // 默认带上O_CLOEXEC，避免fd泄漏到exec出的子进程中
+    flags | O_CLOEXEC | sys::O_LARGEFILE
+}
+
+/*
+    POSIX open函数是需要手动释放资源的， 所以也要有对等的rust实现
+    Rust通过RAII（资源获取即初始化）进行自动的资源释放，通过实现Drop trat即可
+*/
+impl Drop for File {
+    fn drop(&mut self) {
+        if self.fd != INVALID_FD {
+            // close被信号打断时不能重试：Linux下无论close返回什么，fd都已经被释放，
+            // 重试可能关闭掉其他线程刚刚打开、恰好复用了这个编号的fd
+            unsafe {
+                close(self.fd);
+            }
+
+            self.fd = INVALID_FD; // 避免重复关闭
+        }
+    }
+}
+
+impl Read for File {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Ok(self.read(buf)?)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+        Ok(self.read_vectored(bufs)?)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        Ok(self.read_exact(buf)?)
+    }
+}
+
+impl Seek for File {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        Ok(self.seek(pos)?)
+    }
+}
+
+impl Write for File {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(self.write(buf)?)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        Ok(self.write_vectored(bufs)?)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        Ok(self.write_all(buf)?)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{File, OpenMode};
+    use crate::temp::{TempDir, TempFile};
+    use std::io::{self, Read, Seek, SeekFrom, Write};
+    use std::os::fd::FromRawFd;
+
+    // 创建一对管道，返回(读端, 写端)
+    fn pipe() -> io::Result<(File, File)> {
+        let (reader, writer) = crate::pipe()?;
+        Ok((reader.into_file(), writer.into_file()))
+    }
+
+    #[test]
+    fn test_open_read() -> io::Result<()> {
+        // 创建一个临时文件并写入内容
+        let mut temp_file = TempFile::new()?;
+        temp_file.file_mut().write_all(b"Hello, world!")?;
+
+        let file = File::open(temp_file.path(), OpenMode::Read)?;
+        assert!(file.fd >= 0, "File descriptor should be valid");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_write() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let file = File::open(temp_file.path(), OpenMode::Write)?;
+        assert!(file.fd >= 0, "File descriptor should be valid");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_read_write() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let file = File::open(temp_file.path(), OpenMode::ReadWrite)?;
+        assert!(file.fd >= 0, "File descriptor should be valid");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_nonexistent_file() {
+        let result = File::open("nonexistent.txt", OpenMode::Read);
+        assert!(result.is_err(), "Opening nonexistent file should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::NotFound,
+                "Error should be NotFound"
+            );
+        }
+    }
+
+    #[test]
+    fn test_open_invalid_path() {
+        let result = File::open("", OpenMode::Read);
+        assert!(result.is_err(), "Opening empty path should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::InvalidInput,
+                "Error should be InvalidInput"
+            );
+        }
+    }
+
+    #[test]
+    fn test_open_append() -> io::Result<()> {
+        let mut temp_file = TempFile::new()?;
+        temp_file.file_mut().write_all(b"existing\n")?;
+
+        // 多次以追加模式打开写入，内容依次累加，原有内容不会被截断
+        for line in [b"first\n", b"again\n"] {
+            let mut file = File::open(temp_file.path(), OpenMode::Append)?;
+            file.write_all(line)?;
+        }
+
+        let content = std::fs::read(temp_file.path())?;
+        assert_eq!(content, b"existing\nfirst\nagain\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_append_ignores_seek() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let mut file = File::open(temp_file.path(), OpenMode::Append)?;
+        file.write_all(b"12345")?;
+
+        // O_APPEND下每次写入前都会移动到末尾
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(b"678")?;
+
+        let content = std::fs::read(temp_file.path())?;
+        assert_eq!(content, b"12345678");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_new() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("first-write.txt");
+
+        let mut file = File::create_new(&path)?;
+        file.write_all(b"only once")?;
+
+        let result = File::create_new(&path);
+        assert!(result.is_err(), "Creating existing file should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::AlreadyExists,
+                "Error should be AlreadyExists"
+            );
+        }
+        assert_eq!(std::fs::read(&path)?, b"only once", "Content kept intact");
+
+        Ok(())
+    }
+
+    // 测试随机读写
+    #[test]
+    fn test_seek() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let mut file = File::open(temp_file.path(), OpenMode::ReadWrite)?;
+        file.write_all(b"0123456789")?;
+        assert_eq!(file.stream_position()?, 10);
+
+        let mut buf = [0u8; 3];
+        assert_eq!(file.seek(SeekFrom::Start(2))?, 2);
+        file.read_exact(&mut buf)?;
+        assert_eq!(&buf, b"234");
+
+        assert_eq!(file.seek(SeekFrom::Current(2))?, 7);
+        file.read_exact(&mut buf)?;
+        assert_eq!(&buf, b"789");
+
+        assert_eq!(file.seek(SeekFrom::End(-4))?, 6);
+        file.write_all(b"xy")?;
+        assert_eq!(file.stream_position()?, 8);
+
+        file.rewind()?;
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)?;
+        assert_eq!(content, b"012345xy89");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_seek_invalid() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let mut file = File::open(temp_file.path(), OpenMode::Read)?;
+
+        let result = file.seek(SeekFrom::Current(-1));
+        assert!(result.is_err(), "Seeking before start should fail");
+        if let Err(e) = result {
+            assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        }
+
+        let result = file.seek(SeekFrom::Start(u64::MAX));
+        assert!(result.is_err(), "Offset beyond i64 should fail");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_seek_trait() -> io::Result<()> {
+        let mut temp_file = TempFile::new()?;
+        temp_file.file_mut().write_all(b"Hello, world!")?;
+
+        // 通过Seek trait使用，例如std::io::copy之外的泛型代码
+        fn last_byte<S: Read + Seek>(source: &mut S) -> io::Result<u8> {
+            source.seek(SeekFrom::End(-1))?;
+            let mut buf = [0u8; 1];
+            source.read_exact(&mut buf)?;
+            Ok(buf[0])
+        }
+
+        let mut file = File::open(temp_file.path(), OpenMode::Read)?;
+        assert_eq!(last_byte(&mut file)?, b'!');
+
+        Ok(())
+    }
+
+    // 测试读取
+    #[test]
+    fn test_read_content() -> io::Result<()> {
+        let mut temp_file = TempFile::new()?;
+        let content = b"Hello, world!";
+        temp_file.file_mut().write_all(content)?;
+
+        let mut file = File::open(temp_file.path(), OpenMode::Read)?;
+        let mut buf = [0u8; 128];
+        let n = file.read(&mut buf)?;
+        assert_eq!(n, content.len(), "Should read exact number of bytes");
+        assert_eq!(&buf[..n], content, "Read content should match");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_empty_file() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let mut file = File::open(temp_file.path(), OpenMode::Read)?;
+        let mut buf = [0u8; 128];
+        let n = file.read(&mut buf)?;
+        assert_eq!(n, 0, "Reading empty file should return 0 bytes");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_empty_buffer() -> io::Result<()> {
+        let mut temp_file = TempFile::new()?;
+        temp_file.file_mut().write_all(b"Some content")?;
+        let mut file = File::open(temp_file.path(), OpenMode::Read)?;
+        let mut buf = [];
+        let n = file.read(&mut buf)?;
+        assert_eq!(n, 0, "Reading with empty buffer should return 0");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_invalid_fd() {
+        let mut file = File::from_fd(-1); // 手动构造无效文件描述符
+        let mut buf = [0u8; 128];
+        let result = file.read(&mut buf);
+        assert!(result.is_err(), "Reading with invalid fd should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::InvalidInput,
+                "Error should be InvalidInput"
+            );
+        }
+    }
+
+    // 测试写入
+    #[test]
+    fn test_write_content() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let content = b"Hello, world!";
+        let mut file = File::open(temp_file.path(), OpenMode::Write)?;
+        let n = file.write(content)?;
+        assert_eq!(n, content.len(), "Should write exact number of bytes");
+
+        let mut std_file = std::fs::File::open(temp_file.path())?;
+        let mut read_content = Vec::new();
+        std_file.read_to_end(&mut read_content)?;
+        assert_eq!(read_content, content, "Written content should match");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_invalid_fd() {
+        let mut file = File::from_fd(-1); // 手动构造无效文件描述符
+        let content = b"test";
         let result = file.write(content);
         assert!(result.is_err(), "Writing with invalid fd should fail");
         if let Err(e) = result {
@@ -305,70 +1257,791 @@ mod tests {
         }
     }
 
-    // 测试 Drop（自动关闭）
+    // 测试 Drop（自动关闭）
+    #[test]
+    fn test_drop_closes_fd() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let file = File::open(temp_file.path(), OpenMode::Read)?;
+        let fd = file.fd;
+        assert!(fd >= 0, "File descriptor should be valid");
+
+        // 手动 drop 文件
+        drop(file);
+
+        // 尝试使用原始 fd 调用 read（应失败）
+        let mut buf = [0u8; 128];
+        let result =
+            unsafe { libc::read(fd, buf.as_mut_ptr() as *mut _, buf.len() as libc::size_t) };
+        assert_eq!(result, -1, "Reading closed fd should fail");
+        let err = io::Error::last_os_error();
+
+        eprintln!("Error kind: {:?}", err.kind());
+        eprintln!("Actual error: {:?}", err);
+        eprintln!("Raw OS error code: {:?}", err.raw_os_error());
+        assert_eq!(
+            err.raw_os_error(),
+            Some(libc::EBADF),
+            "Raw OS error should be EBADF (9), got {:?}",
+            err.raw_os_error()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_trait() -> io::Result<()> {
+        let mut temp_file = TempFile::new()?;
+        let content = b"Hello, world!";
+        temp_file.file_mut().write_all(content)?;
+
+        let mut file = File::open(temp_file.path(), OpenMode::Read)?;
+        let mut read_content = Vec::new();
+        file.read_to_end(&mut read_content)?;
+        assert_eq!(
+            read_content, content,
+            "Read trait should read correct content"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_trait() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let content = b"Hello, world!";
+        let mut file = File::open(temp_file.path(), OpenMode::Write)?;
+        file.write_all(content)?;
+        file.flush()?;
+
+        let mut std_file = std::fs::File::open(temp_file.path())?;
+        let mut read_content = Vec::new();
+        std_file.read_to_end(&mut read_content)?;
+        assert_eq!(
+            read_content, content,
+            "Write trait should write correct content"
+        );
+
+        Ok(())
+    }
+
+    // 测试O_PATH句柄与openat
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_open_path_as_dirfd() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        std::fs::write(dir.path().join("inner.txt"), b"Hello, openat!")?;
+
+        let dir_handle = File::open_path(dir.path())?;
+        assert!(dir_handle.fd >= 0, "File descriptor should be valid");
+
+        let mut file = dir_handle.open_at("inner.txt", OpenMode::Read)?;
+        let mut buf = [0u8; 128];
+        let n = file.read(&mut buf)?;
+        assert_eq!(&buf[..n], b"Hello, openat!", "Read content should match");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_terminal() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let file = File::open(temp_file.path(), OpenMode::Read)?;
+        assert!(!file.is_terminal(), "Regular file is not a terminal");
+        assert!(!pipe()?.0.is_terminal(), "Pipe is not a terminal");
+        assert!(!File::from_fd(-1).is_terminal());
+
+        // 伪终端的主设备也是终端，环境中没有/dev/ptmx时跳过
+        let fd = unsafe { libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY) };
+        if fd >= 0 {
+            let pty = unsafe { File::from_raw_fd(fd) };
+            assert!(pty.is_terminal(), "Pseudo terminal should be a terminal");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_and_open_read() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("file.txt");
+
+        let mut file = File::create(&path)?;
+        file.write_all(b"Hello, world!")?;
+        drop(file);
+
+        // 已存在时截断
+        let mut file = File::create(&path)?;
+        file.write_all(b"Hi")?;
+        drop(file);
+
+        let mut file = File::open_read(&path)?;
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+        assert_eq!(content, "Hi", "create should truncate");
+        assert!(file.write(b"x").is_err(), "open_read should be read-only");
+
+        let result = File::open_read(dir.path().join("missing"));
+        assert!(result.is_err(), "open_read should not create");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_null_device() -> io::Result<()> {
+        let mut sink = File::null_sink()?;
+        sink.write_all(&[0u8; 100_000])?;
+        assert!(sink.read(&mut [0u8; 8]).is_err(), "Sink is write-only");
+
+        let mut source = File::null_source()?;
+        let mut content = Vec::new();
+        assert_eq!(
+            source.read_to_end(&mut content)?,
+            0,
+            "Source is always empty"
+        );
+        assert_eq!(source.read_or_eof(&mut [0u8; 8])?, None);
+        assert!(source.write(b"x").is_err(), "Source is read-only");
+
+        Ok(())
+    }
+
+    // 测试File记录的路径以及错误信息中的路径
+    #[test]
+    fn test_path_in_errors() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("file.txt");
+        std::fs::write(&path, b"Hello, world!")?;
+
+        let file = File::open(&path, OpenMode::Read)?;
+        assert_eq!(file.path(), Some(path.as_path()));
+        assert_eq!(file.try_clone()?.path(), Some(path.as_path()));
+
+        let dir_handle = File::open(dir.path(), OpenMode::Read)?;
+        let inner = dir_handle.open_at("file.txt", OpenMode::Read)?;
+        assert_eq!(inner.path(), Some(path.as_path()), "Path should be joined");
+
+        // 只读打开的文件不能截断(EINVAL)，错误信息带有操作名和路径
+        let mut file = File::open(&path, OpenMode::Read)?;
+        let err = file.set_len(0).unwrap_err();
+        assert_eq!(file.last_errno(), Some(libc::EINVAL));
+        let msg = err.to_string();
+        assert!(
+            msg.starts_with("ftruncate "),
+            "Message should name the syscall: {}",
+            msg
+        );
+        assert!(
+            msg.contains(path.to_str().unwrap()),
+            "Message should contain the path"
+        );
+
+        let result = dir_handle.open_at("missing", OpenMode::Read);
+        assert!(result.is_err(), "Opening missing file should fail");
+        if let Err(e) = result {
+            assert_eq!(e.kind(), io::ErrorKind::NotFound, "Kind should be kept");
+            assert!(e.to_string().contains("missing"));
+        }
+
+        assert_eq!(File::from_fd(-1).path(), None);
+
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_open_path_read_write_rejected() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let mut file = File::open_path(temp_file.path())?;
+
+        let mut buf = [0u8; 16];
+        let err = file.read(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let err = file.write(b"test").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        Ok(())
+    }
+
+    // 测试EOF检测
+    #[test]
+    fn test_at_eof() -> io::Result<()> {
+        let mut temp_file = TempFile::new()?;
+        temp_file.file_mut().write_all(b"Hello, world!")?;
+
+        let mut file = File::open(temp_file.path(), OpenMode::Read)?;
+        assert!(!file.at_eof()?, "Freshly opened file should not be at EOF");
+
+        let mut read_content = Vec::new();
+        file.read_to_end(&mut read_content)?;
+        assert!(file.at_eof()?, "File should be at EOF after reading to end");
+
+        // 字符设备可以lseek，但st_size为0，不能据此判断
+        let mut null = File::open("/dev/null", OpenMode::Read)?;
+        let result = null.at_eof();
+        assert!(result.is_err(), "Device has no known EOF");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::InvalidInput,
+                "Error should be InvalidInput"
+            );
+        }
+
+        Ok(())
+    }
+
+    // 测试errno记录
     #[test]
-    fn test_drop_closes_fd() -> io::Result<()> {
-        let temp_file = NamedTempFile::new()?;
-        let file = File::open(temp_file.path(), OpenMode::Read)?;
-        let fd = file.fd;
-        assert!(fd >= 0, "File descriptor should be valid");
+    fn test_last_errno() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let mut file = File::open(temp_file.path(), OpenMode::Read)?;
+        assert_eq!(file.last_errno(), None, "Fresh file should have no errno");
 
-        // 手动 drop 文件
-        drop(file);
+        // 只读句柄上写入会失败，errno为EBADF
+        let result = file.write(b"test");
+        assert!(result.is_err(), "Writing read-only file should fail");
+        assert_eq!(file.last_errno(), Some(libc::EBADF));
 
-        // 尝试使用原始 fd 调用 read（应失败）
-        let mut buf = [0u8; 128];
-        let result =
-            unsafe { libc::read(fd, buf.as_mut_ptr() as *mut _, buf.len() as libc::size_t) };
-        assert_eq!(result, -1, "Reading closed fd should fail");
-        let err = io::Error::last_os_error();
+        // 成功的操作不会清除上一次的errno
+        let mut buf = [0u8; 16];
+        file.read(&mut buf)?;
+        assert_eq!(file.last_errno(), Some(libc::EBADF));
 
-        eprintln!("Error kind: {:?}", err.kind());
-        eprintln!("Actual error: {:?}", err);
-        eprintln!("Raw OS error code: {:?}", err.raw_os_error());
-        assert_eq!(
-            err.raw_os_error(),
-            Some(libc::EBADF),
-            "Raw OS error should be EBADF (9), got {:?}",
-            err.raw_os_error()
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_to_end_with_capacity() -> io::Result<()> {
+        let mut temp_file = TempFile::new()?;
+        let content: Vec<u8> = (0..=255u8).cycle().take(100_000).collect();
+        temp_file.file_mut().write_all(&content)?;
+
+        let mut file = File::open(temp_file.path(), OpenMode::Read)?;
+        let mut buf = Vec::new();
+        let n = file.read_to_end_with_capacity(&mut buf, content.len())?;
+        assert_eq!(n, content.len(), "Should read all bytes");
+        assert_eq!(buf, content, "Read content should match");
+        assert!(
+            buf.capacity() < content.len() + content.len() / 8,
+            "Capacity {} should not grossly exceed file size",
+            buf.capacity()
         );
 
         Ok(())
     }
 
     #[test]
-    fn test_read_trait() -> io::Result<()> {
-        let mut temp_file = NamedTempFile::new()?;
-        let content = b"Hello, world!";
-        temp_file.write_all(content)?;
+    fn test_read_to_end_with_small_hint() -> io::Result<()> {
+        let mut temp_file = TempFile::new()?;
+        let content: Vec<u8> = (0..=255u8).cycle().take(10_000).collect();
+        temp_file.file_mut().write_all(&content)?;
 
+        // 预估偏小时超出部分按倍数扩容，内容依旧完整
         let mut file = File::open(temp_file.path(), OpenMode::Read)?;
-        let mut read_content = Vec::new();
-        file.read_to_end(&mut read_content)?;
-        assert_eq!(
-            read_content, content,
-            "Read trait should read correct content"
+        let mut buf = b"prefix".to_vec();
+        let n = file.read_to_end_with_capacity(&mut buf, 100)?;
+        assert_eq!(n, content.len(), "Should read all bytes");
+        assert_eq!(&buf[..6], b"prefix");
+        assert_eq!(&buf[6..], &content[..], "Read content should match");
+
+        // 管道每次只能读到一部分，剩余容量在多次短读之间复用
+        let (mut reader, mut writer) = pipe()?;
+        let sender = std::thread::spawn(move || -> io::Result<()> {
+            for chunk in content.chunks(1000) {
+                writer.write_all(chunk)?;
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+            Ok(())
+        });
+        let mut buf = Vec::new();
+        let n = reader.read_to_end_with_capacity(&mut buf, 20_000)?;
+        sender.join().unwrap()?;
+        assert_eq!(n, 10_000);
+        assert_eq!(buf, (0..=255u8).cycle().take(10_000).collect::<Vec<_>>());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncate_to_position() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let mut file = File::open(temp_file.path(), OpenMode::ReadWrite)?;
+        file.write_all(b"a long original content")?;
+
+        // 回到开头写入更短的内容，再截掉残留的尾巴
+        file.rewind()?;
+        file.write_all(b"short")?;
+        file.truncate_to_position()?;
+
+        let content = std::fs::read(temp_file.path())?;
+        assert_eq!(content, b"short", "File should only contain the rewrite");
+
+        Ok(())
+    }
+
+    // 测试管道上的短读短写
+    #[test]
+    fn test_write_all_pipe() -> io::Result<()> {
+        let (mut reader, mut writer) = pipe()?;
+        // 远大于管道容量(通常64KB)，写端必须等待读端取走数据
+        let content: Vec<u8> = (0..1024 * 1024).map(|i| (i % 251) as u8).collect();
+
+        let handle = std::thread::spawn(move || -> io::Result<Vec<u8>> {
+            let mut received = Vec::new();
+            let mut buf = [0u8; 1000];
+            while let Some(n) = reader.read_or_eof(&mut buf)? {
+                received.extend_from_slice(&buf[..n]);
+            }
+            Ok(received)
+        });
+
+        writer.write_all(&content)?;
+        drop(writer);
+
+        let received = handle.join().unwrap()?;
+        assert_eq!(received.len(), content.len(), "All bytes should arrive");
+        assert!(received == content, "Content should be intact");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_exact_pipe() -> io::Result<()> {
+        let (mut reader, mut writer) = pipe()?;
+
+        // 分多次小块写入，读端每次read只能拿到一部分
+        let handle = std::thread::spawn(move || -> io::Result<()> {
+            for chunk in b"Hello, world!".chunks(3) {
+                writer.write_all(chunk)?;
+                std::thread::sleep(std::time::Duration::from_millis(5));
+            }
+            Ok(())
+        });
+
+        let mut buf = [0u8; 10];
+        reader.read_exact(&mut buf)?;
+        assert_eq!(&buf, b"Hello, wor");
+
+        // 只剩3个字节，写端关闭后read_exact应当返回UnexpectedEof
+        handle.join().unwrap()?;
+        let result = reader.read_exact(&mut buf);
+        assert!(result.is_err(), "Short input should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::UnexpectedEof,
+                "Error should be UnexpectedEof"
+            );
+        }
+
+        Ok(())
+    }
+
+    // 测试非阻塞模式
+    #[test]
+    fn test_set_nonblocking() -> io::Result<()> {
+        let (mut reader, mut writer) = pipe()?;
+        reader.set_nonblocking(true)?;
+        writer.set_nonblocking(true)?;
+
+        // 管道为空时读取立即返回WouldBlock
+        let mut buf = [0u8; 16];
+        let result = reader.read(&mut buf);
+        assert!(result.is_err(), "Empty pipe should not block");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::WouldBlock,
+                "Error should be WouldBlock"
+            );
+        }
+        assert_eq!(reader.last_errno(), Some(libc::EAGAIN));
+
+        // 写满管道后写入立即返回WouldBlock
+        let chunk = [0u8; 4096];
+        let mut total = 0;
+        let error = loop {
+            match writer.write(&chunk) {
+                Ok(n) => total += n,
+                Err(e) => break e,
+            }
+        };
+        assert!(total > 0, "Some bytes should fit into the pipe");
+        assert_eq!(error.kind(), io::ErrorKind::WouldBlock);
+
+        // 恢复阻塞模式后可以正常读到数据
+        reader.set_nonblocking(false)?;
+        assert_eq!(reader.read(&mut buf)?, 16);
+
+        Ok(())
+    }
+
+    // 测试EINTR的处理：向阻塞在管道读取上的线程发送信号
+    // 信号处理函数不带SA_RESTART，read会以EINTR返回
+    fn read_with_signals(
+        retry: bool,
+        read: fn(&mut File) -> crate::Result<usize>,
+    ) -> io::Result<crate::Result<usize>> {
+        extern "C" fn noop(_: libc::c_int) {}
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = noop as *const () as libc::sighandler_t;
+            libc::sigaction(libc::SIGUSR1, &action, std::ptr::null_mut());
+        }
+
+        let (mut reader, mut writer) = pipe()?;
+        reader.set_retry_interrupted(retry);
+        let (tx, rx) = std::sync::mpsc::channel();
+        let handle = std::thread::spawn(move || {
+            tx.send(unsafe { libc::pthread_self() } as usize).unwrap();
+            // 连同reader一起返回，避免读端先被关闭导致后面的写入失败
+            (read(&mut reader), reader)
+        });
+
+        let thread = rx.recv().unwrap() as libc::pthread_t;
+        for _ in 0..10 {
+            if handle.is_finished() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            unsafe { libc::pthread_kill(thread, libc::SIGUSR1) };
+        }
+
+        // 重试时读取依旧阻塞，写入数据并关闭写端后才返回
+        writer.write_all(b"data")?;
+        drop(writer);
+        Ok(handle.join().unwrap().0)
+    }
+
+    #[test]
+    fn test_retry_interrupted() -> io::Result<()> {
+        let reads: [fn(&mut File) -> crate::Result<usize>; 3] = [
+            |file| file.read(&mut [0u8; 16]),
+            |file| file.read_exact(&mut [0u8; 4]).map(|_| 4),
+            |file| file.read_to_end_with_capacity(&mut Vec::new(), 16),
+        ];
+
+        for read in reads {
+            let result = read_with_signals(true, read)?;
+            assert_eq!(result?, 4, "Read should survive signals");
+
+            // 关闭重试后，循环读取的方法也要把Interrupted交给调用方
+            let result = read_with_signals(false, read)?;
+            assert!(result.is_err(), "Read should be interrupted");
+            if let Err(e) = result {
+                assert_eq!(
+                    e.kind(),
+                    io::ErrorKind::Interrupted,
+                    "Error should be Interrupted"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    // 测试指定偏移量读写
+    #[test]
+    fn test_read_write_at() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let mut file = File::open(temp_file.path(), OpenMode::ReadWrite)?;
+        file.write_all(b"Hello, world!")?;
+
+        assert_eq!(file.write_at(b"WORLD", 7)?, 5);
+        let mut buf = [0u8; 5];
+        assert_eq!(file.read_at(&mut buf, 7)?, 5);
+        assert_eq!(&buf, b"WORLD");
+        assert_eq!(file.read_at(&mut buf, 100)?, 0, "Read past EOF returns 0");
+
+        // 不改变当前读写位置
+        assert_eq!(file.stream_position()?, 13, "Position should not move");
+
+        let result = file.read_at(&mut buf, u64::MAX);
+        assert!(result.is_err(), "Offset beyond off_t should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::InvalidInput,
+                "Error should be InvalidInput"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_at_concurrent() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let content: Vec<u8> = (0..64 * 1024).map(|i| (i % 251) as u8).collect();
+        std::fs::write(temp_file.path(), &content)?;
+
+        let file = File::open(temp_file.path(), OpenMode::Read)?;
+        std::thread::scope(|s| {
+            for t in 0..4 {
+                let (file, content) = (&file, &content);
+                s.spawn(move || {
+                    let mut buf = [0u8; 1024];
+                    for offset in (t * 1024..content.len()).step_by(4 * 1024) {
+                        let n = file.read_at(&mut buf, offset as u64).unwrap();
+                        assert_eq!(&buf[..n], &content[offset..offset + n]);
+                    }
+                });
+            }
+        });
+
+        Ok(())
+    }
+
+    // 测试创建文件时指定权限
+    #[test]
+    fn test_open_with_mode() -> io::Result<()> {
+        let dir = TempDir::new()?;
+
+        for perm in [0o600, 0o755] {
+            let path = dir.path().join(format!("file_{:o}", perm));
+            let file = File::open_with_mode(&path, OpenMode::Write, perm)?;
+            let mode = file.metadata()?.permissions().mode();
+            assert_eq!(mode, perm, "File should be created with {:o}", perm);
+        }
+
+        // 默认权限0o644
+        let path = dir.path().join("default");
+        let file = File::open(&path, OpenMode::Write)?;
+        assert_eq!(file.metadata()?.permissions().mode(), 0o644);
+        drop(file);
+
+        // 文件已存在时perm不生效
+        let file = File::open_with_mode(&path, OpenMode::Write, 0o600)?;
+        assert_eq!(file.metadata()?.permissions().mode(), 0o644);
+
+        Ok(())
+    }
+
+    // 测试复制文件描述符
+    #[test]
+    fn test_try_clone() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let mut file = File::open(temp_file.path(), OpenMode::ReadWrite)?;
+        let mut clone = file.try_clone()?;
+
+        // 读写位置是共享的
+        file.write_all(b"Hello, ")?;
+        clone.write_all(b"world!")?;
+        assert_eq!(file.stream_position()?, 13);
+
+        // 关闭原来的File不影响复制出的File
+        drop(file);
+        clone.rewind()?;
+        let mut content = String::new();
+        clone.read_to_string(&mut content)?;
+        assert_eq!(content, "Hello, world!");
+
+        let flags = unsafe { libc::fcntl(clone.fd, libc::F_GETFD) };
+        assert!(
+            flags & libc::FD_CLOEXEC != 0,
+            "Clone should be close-on-exec"
         );
 
+        let result = File::from_fd(-1).try_clone();
+        assert!(result.is_err(), "Cloning closed file should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::InvalidInput,
+                "Error should be InvalidInput"
+            );
+        }
+
         Ok(())
     }
 
+    // 测试同步到存储设备
     #[test]
-    fn test_write_trait() -> io::Result<()> {
-        let temp_file = NamedTempFile::new()?;
-        let content = b"Hello, world!";
+    fn test_sync() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
         let mut file = File::open(temp_file.path(), OpenMode::Write)?;
-        file.write_all(content)?;
-        file.flush()?;
+        file.write_all(b"Hello, world!")?;
+        file.sync_data()?;
+        file.write_all(b" Again")?;
+        file.sync_all()?;
 
-        let mut std_file = std::fs::File::open(temp_file.path())?;
-        let mut read_content = Vec::new();
-        std_file.read_to_end(&mut read_content)?;
+        assert_eq!(std::fs::read(temp_file.path())?, b"Hello, world! Again");
+
+        // 已关闭的文件
+        let closed = File::from_fd(-1);
+        for result in [closed.sync_all(), closed.sync_data()] {
+            assert!(result.is_err(), "Sync on closed file should fail");
+            if let Err(e) = result {
+                assert_eq!(
+                    e.kind(),
+                    io::ErrorKind::InvalidInput,
+                    "Error should be InvalidInput"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    // 测试设置文件长度
+    #[test]
+    fn test_set_len() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let mut file = File::open(temp_file.path(), OpenMode::ReadWrite)?;
+        file.write_all(b"Hello, world!")?;
+
+        file.set_len(5)?;
+        assert_eq!(std::fs::read(temp_file.path())?, b"Hello");
+
+        // 变长的部分以0填充，读写位置不变
+        file.set_len(8)?;
+        assert_eq!(std::fs::read(temp_file.path())?, b"Hello\0\0\0");
+        assert_eq!(file.stream_position()?, 13, "Position should not move");
+
+        let result = file.set_len(u64::MAX);
+        assert!(result.is_err(), "Length beyond off_t should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::InvalidInput,
+                "Error should be InvalidInput"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_len_read_only() -> io::Result<()> {
+        let mut temp_file = TempFile::new()?;
+        temp_file.file_mut().write_all(b"Hello, world!")?;
+
+        let mut file = File::open(temp_file.path(), OpenMode::Read)?;
+        let result = file.set_len(0);
+        assert!(result.is_err(), "Read only file should not be truncated");
+        assert!(file.last_errno().is_some(), "errno should be recorded");
+        assert_eq!(std::fs::read(temp_file.path())?, b"Hello, world!");
+
+        Ok(())
+    }
+
+    // 测试文件内复制
+    #[test]
+    fn test_copy_range() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let block: Vec<u8> = (0..4096u32).map(|i| (i % 251) as u8).collect();
+        let mut file = File::open(temp_file.path(), OpenMode::ReadWrite)?;
+        file.write_all(&block)?;
+
+        let n = file.copy_range(0, 8192, 4096)?;
+        assert_eq!(n, 4096, "Should copy the whole block");
+
+        let content = std::fs::read(temp_file.path())?;
+        assert_eq!(content.len(), 8192 + 4096);
+        assert_eq!(&content[..4096], &block[..], "Source should be untouched");
+        assert!(content[4096..8192].iter().all(|&b| b == 0));
+        assert_eq!(&content[8192..], &block[..], "Destination should match");
+
+        // 用户态退路的结果一致
+        let n = file.copy_range_by_pread(&file, 0, 16384, 4096)?;
+        assert_eq!(n, 4096, "Should copy the whole block");
+        let content = std::fs::read(temp_file.path())?;
+        assert_eq!(&content[16384..], &block[..], "Destination should match");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_range_from() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let src_path = dir.path().join("src.bin");
+        let content: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&src_path, &content)?;
+
+        let mut src = File::open_read(&src_path)?;
+        let mut dst = File::open(dir.path().join("dst.bin"), OpenMode::ReadWrite)?;
+
+        // 指定偏移量时不改变读写位置
+        let n = dst.copy_range_from(&src, 1000, Some((500, 0)))?;
+        assert_eq!(n, 1000);
+        assert_eq!((src.stream_position()?, dst.stream_position()?), (0, 0));
+        let mut buf = vec![0u8; 1000];
+        dst.read_exact(&mut buf)?;
+        assert_eq!(buf, &content[500..1500]);
+
+        // 从当前位置开始，复制后两个位置都向后移动；源不足len时只复制到末尾
+        src.seek(SeekFrom::Start(99_000))?;
+        let n = dst.copy_range_from(&src, 5000, None)?;
+        assert_eq!(n, 1000, "Should stop at EOF of src");
+        assert_eq!(src.stream_position()?, 100_000);
+        assert_eq!(dst.stream_position()?, 2000);
+        assert_eq!(dst.metadata()?.len(), 2000);
+
+        // 用户态退路的结果一致
+        let n = dst.copy_range_by_pread(&src, 0, 2000, 100)?;
+        assert_eq!(n, 100);
+        let mut buf = vec![0u8; 100];
+        dst.read_at(&mut buf, 2000)?;
+        assert_eq!(buf, &content[..100]);
+
+        let result = src.copy_range_from(&dst, 10, None);
+        assert!(result.is_err(), "Read-only destination should fail");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_range_overlapping() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let file = File::open(temp_file.path(), OpenMode::ReadWrite)?;
+        let result = file.copy_range(0, 100, 200);
+        assert!(result.is_err(), "Overlapping ranges should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::InvalidInput,
+                "Error should be InvalidInput"
+            );
+        }
+
+        Ok(())
+    }
+
+    // 测试大文件偏移量，稀疏文件只占用一个块，不会真的写入3GB数据
+    #[test]
+    fn test_seek_beyond_2gb() -> io::Result<()> {
+        const OFFSET: u64 = 3 << 30;
+
+        let temp_file = TempFile::new()?;
+        let mut file = File::open(temp_file.path(), OpenMode::ReadWrite)?;
+        assert_eq!(file.seek(SeekFrom::Start(OFFSET))?, OFFSET);
+        file.write_all(b"x")?;
+
+        assert_eq!(file.fstat()?.st_size as u64, OFFSET + 1);
+        assert_eq!(file.seek(SeekFrom::End(0))?, OFFSET + 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_or_eof() -> io::Result<()> {
+        let mut temp_file = TempFile::new()?;
+        temp_file.file_mut().write_all(b"Hello")?;
+        let mut file = File::open(temp_file.path(), OpenMode::Read)?;
+
+        let mut empty = [];
         assert_eq!(
-            read_content, content,
-            "Write trait should write correct content"
+            file.read_or_eof(&mut empty)?,
+            Some(0),
+            "Empty buffer is not EOF"
         );
 
+        let mut buf = [0u8; 3];
+        assert_eq!(file.read_or_eof(&mut buf)?, Some(3));
+        let mut buf = [0u8; 128];
+        assert_eq!(file.read_or_eof(&mut buf)?, Some(2), "Partial read");
+        assert_eq!(file.read_or_eof(&mut buf)?, None, "Should report EOF");
+
         Ok(())
     }
 }
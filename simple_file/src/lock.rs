@@ -0,0 +1,441 @@
+/*
+    进程间的建议锁(advisory lock)，只对同样使用锁的进程有约束力，不会阻止普通的读写
+
+    flock(fd: i32, operation: i32) -> i32
+    operation: LOCK_SH共享锁，LOCK_EX独占锁，LOCK_UN解锁，与LOCK_NB组合时不等待
+    flock锁住整个文件，属于打开文件描述：try_clone得到的File共享同一把锁，
+    而同一进程内对同一文件再次open得到的File之间也会互相排斥
+
+    fcntl(fd: i32, cmd: i32, lock: *mut flock) -> i32
+    F_SETLK/F_SETLKW对文件中的一段字节范围加锁，可以实现数据库式的按记录加锁
+    与flock不同，这种锁属于进程：同一进程内的锁不会互相排斥，而且关闭该文件的任意一个fd都会释放进程在它上面的全部锁
+    共享锁要求fd可读，独占锁要求fd可写
+*/
+
+use std::io;
+
+use crate::{File, Result, retry_eintr, to_off_t};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LockKind {
+    Shared,    // 读锁，可以被多个持有者同时持有
+    Exclusive, // 写锁，同一时间只能有一个持有者
+}
+
+/// 持有期间文件保持加锁，Drop时解锁
+pub struct FileLock<'a> {
+    file: &'a File,
+}
+
+/*
+    持有期间[offset, offset + len)保持加锁，Drop时解锁
+    fcntl锁属于进程而不属于RangeLock：同一进程内范围重叠的两个RangeLock，先释放的一个会连同另一个在重叠部分上的锁一起释放
+*/
+pub struct RangeLock<'a> {
+    file: &'a File,
+    offset: u64,
+    len: u64,
+}
+
+/// 与请求冲突的、其他进程持有的锁
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LockInfo {
+    pub kind: LockKind,
+    pub offset: u64,
+    pub len: u64, // 0表示一直到文件末尾(包括以后增长的部分)
+    pub pid: i32,
+}
+
+impl File {
+    /// 加共享锁，已有其他独占锁时阻塞等待
+    pub fn lock_shared(&self) -> Result<()> {
+        self.flock(libc::LOCK_SH)
+    }
+
+    /// 加独占锁，已有其他任何锁时阻塞等待
+    pub fn lock_exclusive(&self) -> Result<()> {
+        self.flock(libc::LOCK_EX)
+    }
+
+    /// 尝试加共享锁，不等待，锁被占用时返回Ok(false)
+    pub fn try_lock_shared(&self) -> Result<bool> {
+        self.try_flock(libc::LOCK_SH)
+    }
+
+    /// 尝试加独占锁，不等待，锁被占用时返回Ok(false)
+    pub fn try_lock_exclusive(&self) -> Result<bool> {
+        self.try_flock(libc::LOCK_EX)
+    }
+
+    /// 释放锁，文件关闭时锁也会自动释放
+    pub fn unlock(&self) -> Result<()> {
+        self.flock(libc::LOCK_UN)
+    }
+
+    /// 阻塞地加锁，返回的FileLock离开作用域时自动解锁
+    pub fn lock(&self, kind: LockKind) -> Result<FileLock<'_>> {
+        match kind {
+            LockKind::Shared => self.lock_shared()?,
+            LockKind::Exclusive => self.lock_exclusive()?,
+        }
+
+        Ok(FileLock { file: self })
+    }
+
+    /// 不等待地加锁，锁被占用时返回Ok(None)
+    pub fn try_lock(&self, kind: LockKind) -> Result<Option<FileLock<'_>>> {
+        let locked = match kind {
+            LockKind::Shared => self.try_lock_shared()?,
+            LockKind::Exclusive => self.try_lock_exclusive()?,
+        };
+
+        Ok(locked.then_some(FileLock { file: self }))
+    }
+
+    /*
+        对[offset, offset + len)加锁，len为0表示从offset一直到文件末尾(包括以后增长的部分)
+        与其他进程的锁冲突时阻塞等待(F_SETLKW)，内核检测到死锁时返回EDEADLK
+    */
+    pub fn lock_range(&self, offset: u64, len: u64, kind: LockKind) -> Result<RangeLock<'_>> {
+        let mut lock = range_flock(offset, len, kind_to_raw(kind))?;
+        self.fcntl_lock(libc::F_SETLKW, &mut lock)?;
+
+        Ok(RangeLock {
+            file: self,
+            offset,
+            len,
+        })
+    }
+
+    /// 与lock_range相同但不等待(F_SETLK)，与其他进程的锁冲突时返回Ok(None)
+    pub fn try_lock_range(
+        &self,
+        offset: u64,
+        len: u64,
+        kind: LockKind,
+    ) -> Result<Option<RangeLock<'_>>> {
+        let mut lock = range_flock(offset, len, kind_to_raw(kind))?;
+        match self.fcntl_lock(libc::F_SETLK, &mut lock) {
+            Ok(()) => Ok(Some(RangeLock {
+                file: self,
+                offset,
+                len,
+            })),
+            // POSIX允许冲突时返回EAGAIN或EACCES
+            Err(e) if matches!(e.raw_os_error(), Some(libc::EAGAIN) | Some(libc::EACCES)) => {
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// 释放本进程在[offset, offset + len)上的锁，包括本进程中其他RangeLock在这段范围上的锁
+    pub fn unlock_range(&self, offset: u64, len: u64) -> Result<()> {
+        let mut lock = range_flock(offset, len, libc::F_UNLCK)?;
+        self.fcntl_lock(libc::F_SETLK, &mut lock)
+    }
+
+    /*
+        查询是否有其他进程持有与请求冲突的锁(F_GETLK)，没有时返回None
+        本进程自己持有的锁不会被报告；结果只是查询时刻的快照，随后可能已经变化
+    */
+    pub fn get_lock(&self, offset: u64, len: u64, kind: LockKind) -> Result<Option<LockInfo>> {
+        let mut lock = range_flock(offset, len, kind_to_raw(kind))?;
+        self.fcntl_lock(libc::F_GETLK, &mut lock)?;
+
+        if lock.l_type == libc::F_UNLCK as libc::c_short {
+            return Ok(None);
+        }
+
+        let kind = if lock.l_type == libc::F_WRLCK as libc::c_short {
+            LockKind::Exclusive
+        } else {
+            LockKind::Shared
+        };
+        Ok(Some(LockInfo {
+            kind,
+            offset: lock.l_start as u64,
+            len: lock.l_len as u64,
+            pid: lock.l_pid,
+        }))
+    }
+
+    fn fcntl_lock(&self, cmd: i32, lock: &mut libc::flock) -> Result<()> {
+        self.ensure_open()?;
+
+        let result = retry_eintr(self.retry_interrupted, || unsafe {
+            libc::fcntl(self.fd, cmd, lock as *mut libc::flock)
+        });
+        if result < 0 {
+            return Err(self.os_error("fcntl"));
+        }
+
+        Ok(())
+    }
+
+    fn flock(&self, operation: i32) -> Result<()> {
+        self.ensure_open()?;
+
+        let result = retry_eintr(self.retry_interrupted, || unsafe {
+            libc::flock(self.fd, operation)
+        });
+        if result < 0 {
+            return Err(self.os_error("flock"));
+        }
+
+        Ok(())
+    }
+
+    fn try_flock(&self, operation: i32) -> Result<bool> {
+        match self.flock(operation | libc::LOCK_NB) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl FileLock<'_> {
+    /// 立即解锁，可以拿到解锁失败的错误，Drop时的错误只能被忽略
+    pub fn unlock(self) -> Result<()> {
+        let result = self.file.unlock();
+        std::mem::forget(self);
+        result
+    }
+}
+
+impl Drop for FileLock<'_> {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+impl RangeLock<'_> {
+    /// 立即解锁，可以拿到解锁失败的错误
+    pub fn unlock(self) -> Result<()> {
+        let result = self.file.unlock_range(self.offset, self.len);
+        std::mem::forget(self);
+        result
+    }
+}
+
+impl Drop for RangeLock<'_> {
+    fn drop(&mut self) {
+        let _ = self.file.unlock_range(self.offset, self.len);
+    }
+}
+
+fn kind_to_raw(kind: LockKind) -> i32 {
+    match kind {
+        LockKind::Shared => libc::F_RDLCK,
+        LockKind::Exclusive => libc::F_WRLCK,
+    }
+}
+
+// 组装从文件开头计算的字节范围
+fn range_flock(offset: u64, len: u64, l_type: i32) -> Result<libc::flock> {
+    let mut lock: libc::flock = unsafe { std::mem::zeroed() };
+    lock.l_type = l_type as libc::c_short;
+    lock.l_whence = libc::SEEK_SET as libc::c_short;
+    lock.l_start = to_off_t(offset)? as libc::off_t;
+    lock.l_len = to_off_t(len)? as libc::off_t;
+
+    Ok(lock)
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(target_os = "linux")]
+    use super::LockInfo;
+    use super::LockKind;
+    use crate::temp::TempFile;
+    use crate::{File, OpenMode};
+    use std::io;
+    use std::os::fd::AsRawFd;
+
+    // fcntl锁属于进程，需要在子进程中检查冲突；子进程中只调用async-signal-safe的函数
+    fn lockable_in_child(file: &File, offset: i64, len: i64) -> bool {
+        let mut lock: libc::flock = unsafe { std::mem::zeroed() };
+        lock.l_type = libc::F_WRLCK as libc::c_short;
+        lock.l_whence = libc::SEEK_SET as libc::c_short;
+        lock.l_start = offset;
+        lock.l_len = len;
+        let fd = file.as_raw_fd();
+
+        unsafe {
+            let pid = libc::fork();
+            if pid == 0 {
+                let result = libc::fcntl(fd, libc::F_SETLK, &mut lock as *mut libc::flock);
+                libc::_exit(if result == 0 { 0 } else { 1 });
+            }
+
+            let mut status = 0;
+            libc::waitpid(pid, &mut status, 0);
+            libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0
+        }
+    }
+
+    #[test]
+    fn test_flock() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let first = File::open(temp_file.path(), OpenMode::Read)?;
+        let second = File::open(temp_file.path(), OpenMode::Read)?;
+
+        // 共享锁可以同时持有
+        first.lock_shared()?;
+        assert!(second.try_lock_shared()?, "Shared locks should coexist");
+        assert!(!second.try_lock_exclusive()?, "Exclusive should be refused");
+        first.unlock()?;
+        second.unlock()?;
+
+        // 独占锁排斥其他任何锁
+        first.lock_exclusive()?;
+        assert!(!second.try_lock_shared()?, "Shared should be refused");
+        first.unlock()?;
+        assert!(second.try_lock_exclusive()?, "Lock should be free again");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_flock_guard() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let first = File::open(temp_file.path(), OpenMode::Read)?;
+        let second = File::open(temp_file.path(), OpenMode::Read)?;
+
+        let guard = first.lock(LockKind::Exclusive)?;
+        assert!(second.try_lock(LockKind::Shared)?.is_none());
+        drop(guard);
+
+        let guard = second.try_lock(LockKind::Shared)?;
+        assert!(guard.is_some(), "Lock should be released on drop");
+        guard.unwrap().unlock()?;
+        assert!(first.try_lock_exclusive()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_flock_blocking() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let first = File::open(temp_file.path(), OpenMode::Read)?;
+        let second = File::open(temp_file.path(), OpenMode::Read)?;
+
+        let guard = first.lock(LockKind::Exclusive)?;
+        let handle = std::thread::spawn(move || -> io::Result<std::time::Instant> {
+            second.lock_exclusive()?;
+            Ok(std::time::Instant::now())
+        });
+
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        let released = std::time::Instant::now();
+        drop(guard);
+
+        let acquired = handle.join().unwrap()?;
+        assert!(acquired >= released, "Second lock should wait for release");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lock_range() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let file = File::open(temp_file.path(), OpenMode::ReadWrite)?;
+
+        let guard = file.lock_range(0, 100, LockKind::Exclusive)?;
+        assert!(
+            !lockable_in_child(&file, 50, 10),
+            "Locked range should conflict"
+        );
+        assert!(
+            lockable_in_child(&file, 100, 10),
+            "Other ranges should be free"
+        );
+
+        // 同一进程内不冲突，但锁属于进程，释放inner也释放了guard在[0, 10)上的锁
+        let inner = file.try_lock_range(0, 10, LockKind::Exclusive)?;
+        assert!(inner.is_some(), "Same process should not conflict");
+        drop(inner);
+        assert!(
+            lockable_in_child(&file, 0, 10),
+            "Overlapping part should be released with inner"
+        );
+        assert!(
+            !lockable_in_child(&file, 50, 10),
+            "Rest of guard should stay locked"
+        );
+
+        drop(guard);
+        assert!(
+            lockable_in_child(&file, 50, 10),
+            "Lock should be released on drop"
+        );
+
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_get_lock() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let file = File::open(temp_file.path(), OpenMode::ReadWrite)?;
+
+        // 子进程持有[10, 30)的共享锁，直到父进程关闭通知管道
+        let (mut ready, mut done) = ([0i32; 2], [0i32; 2]);
+        unsafe {
+            assert_eq!(libc::pipe2(ready.as_mut_ptr(), libc::O_CLOEXEC), 0);
+            assert_eq!(libc::pipe2(done.as_mut_ptr(), libc::O_CLOEXEC), 0);
+        }
+        let mut lock: libc::flock = unsafe { std::mem::zeroed() };
+        lock.l_type = libc::F_RDLCK as libc::c_short;
+        lock.l_whence = libc::SEEK_SET as libc::c_short;
+        lock.l_start = 10;
+        lock.l_len = 20;
+        let fd = file.as_raw_fd();
+
+        let pid = unsafe { libc::fork() };
+        if pid == 0 {
+            unsafe {
+                // 关闭子进程中的写端，父进程关闭它的写端后read才能返回
+                libc::close(done[1]);
+                libc::fcntl(fd, libc::F_SETLK, &mut lock as *mut libc::flock);
+                libc::write(ready[1], b"x".as_ptr() as *const _, 1);
+                let mut byte = 0u8;
+                libc::read(done[0], &mut byte as *mut u8 as *mut _, 1);
+                libc::_exit(0);
+            }
+        }
+
+        unsafe {
+            libc::close(ready[1]);
+            libc::close(done[0]);
+            let mut byte = 0u8;
+            libc::read(ready[0], &mut byte as *mut u8 as *mut _, 1);
+        }
+
+        let info = file.get_lock(0, 0, LockKind::Exclusive)?;
+        let expected = LockInfo {
+            kind: LockKind::Shared,
+            offset: 10,
+            len: 20,
+            pid,
+        };
+        assert_eq!(info, Some(expected), "Child lock should be reported");
+        assert_eq!(
+            file.get_lock(0, 0, LockKind::Shared)?,
+            None,
+            "Shared locks coexist"
+        );
+        assert!(file.try_lock_range(15, 1, LockKind::Exclusive)?.is_none());
+        assert!(file.try_lock_range(15, 1, LockKind::Shared)?.is_some());
+
+        unsafe {
+            libc::close(done[1]);
+            libc::close(ready[0]);
+            libc::waitpid(pid, std::ptr::null_mut(), 0);
+        }
+        assert_eq!(file.get_lock(0, 0, LockKind::Exclusive)?, None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,210 @@
+/*
+    锁文件(pidfile)，保证同一时间只有一个进程实例在运行
+
+    step1: 打开(不存在则创建)锁文件，以LOCK_EX | LOCK_NB加flock独占锁，其他实例持有时立即失败
+    step2: 确认锁住的仍然是path当前指向的文件，加锁前持有者可能刚好删除了它，锁住的是一个已经没有名字的旧文件
+    step3: 写入自己的PID，供运维工具和其他实例查看
+    Drop时先删除锁文件再关闭fd释放锁
+
+    flock在进程退出时由内核自动释放，被kill -9的进程不会让锁一直被占用
+    但它留下的锁文件中还是旧的PID，acquire时检查这个进程是否还活着，不存在时视为过期的锁，通过stale_pid报告
+*/
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{Error, File, OpenOptions, Result};
+
+pub struct LockFile {
+    _file: File, // 只用于持有flock，关闭时释放锁
+    path: PathBuf,
+    stale_pid: Option<u32>,
+}
+
+impl LockFile {
+    /// 获取锁，不等待，其他进程持有时返回WouldBlock，错误信息中带有持有者的PID
+    pub fn acquire<P: AsRef<Path>>(path: P) -> Result<LockFile> {
+        let path = path.as_ref();
+
+        loop {
+            let mut file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .open(path)?;
+
+            if !file.try_lock_exclusive()? {
+                let holder = read_pid(&file).map_or("unknown".to_string(), |pid| pid.to_string());
+                return Err(Error::new(
+                    io::ErrorKind::WouldBlock,
+                    format!("Lock file {} is held by process {}", path.display(), holder),
+                ));
+            }
+
+            if !same_file(&file, path)? {
+                continue;
+            }
+
+            // 文件中残留的PID属于已经不存在的进程，说明上一个持有者没有正常释放
+            let pid = std::process::id();
+            let stale_pid = read_pid(&file).filter(|&old| old != pid && !process_alive(old));
+
+            file.set_len(0)?;
+            write_pid(&file, pid)?;
+            file.sync_data()?;
+
+            return Ok(LockFile {
+                _file: file,
+                path: path.to_path_buf(),
+                stale_pid,
+            });
+        }
+    }
+
+    /*
+        当前持有path的锁的进程，没有被锁住时返回None
+        不对文件加锁，加共享锁会让同时调用acquire的进程误以为锁被占用，只读取PID并检查进程是否存活
+        结果只供参考：持有者正在写入PID时可能读到空内容而返回None，持有者异常退出后PID被复用时会返回无关的进程
+    */
+    pub fn holder<P: AsRef<Path>>(path: P) -> Result<Option<u32>> {
+        let file = match File::open_read(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        Ok(read_pid(&file).filter(|&pid| process_alive(pid)))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 获取锁时发现的过期锁的PID，即上一个没有正常释放锁、已经退出的进程
+    pub fn stale_pid(&self) -> Option<u32> {
+        self.stale_pid
+    }
+
+    /// 释放锁并删除锁文件，与Drop相同，但会返回删除时的错误
+    pub fn release(mut self) -> Result<()> {
+        let result = crate::remove_file(&self.path);
+        self.path = PathBuf::new();
+        result
+    }
+}
+
+impl Drop for LockFile {
+    fn drop(&mut self) {
+        // 必须在持有锁时删除，否则可能删掉其他实例刚刚创建并锁住的文件
+        if !self.path.as_os_str().is_empty() {
+            let _ = crate::remove_file(&self.path);
+        }
+    }
+}
+
+// 锁文件的内容是十进制PID加换行，内容为空或无法解析时返回None
+fn read_pid(file: &File) -> Option<u32> {
+    let mut buf = [0u8; 32];
+    let n = file.read_at(&mut buf, 0).ok()?;
+    std::str::from_utf8(&buf[..n]).ok()?.trim().parse().ok()
+}
+
+// write_at可能只写入一部分，循环写完，否则文件中会留下被截断的PID
+fn write_pid(file: &File, pid: u32) -> Result<()> {
+    let content = format!("{}\n", pid);
+    let (mut buf, mut offset) = (content.as_bytes(), 0);
+    while !buf.is_empty() {
+        let n = file.write_at(buf, offset)?;
+        if n == 0 {
+            return Err(Error::new(
+                io::ErrorKind::WriteZero,
+                "Failed to write whole PID",
+            ));
+        }
+        buf = &buf[n..];
+        offset += n as u64;
+    }
+
+    Ok(())
+}
+
+// kill(pid, 0)不发送信号，只检查进程是否存在；EPERM表示进程存在但属于其他用户
+fn process_alive(pid: u32) -> bool {
+    let result = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    result == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+// 比较fd与path当前指向的文件的(dev, ino)
+fn same_file(file: &File, path: &Path) -> Result<bool> {
+    let locked = file.metadata()?;
+    match crate::metadata(path) {
+        Ok(current) => Ok(current.dev() == locked.dev() && current.ino() == locked.ino()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LockFile;
+    use crate::temp::TempDir;
+    use std::io;
+
+    #[test]
+    fn test_lock_file() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("app.pid");
+
+        let lock = LockFile::acquire(&path)?;
+        assert_eq!(lock.stale_pid(), None);
+        assert_eq!(
+            crate::read_to_string(&path)?,
+            format!("{}\n", std::process::id())
+        );
+        assert_eq!(LockFile::holder(&path)?, Some(std::process::id()));
+
+        // flock属于打开文件描述，同一进程再次获取也会失败
+        let result = LockFile::acquire(&path);
+        assert!(result.is_err(), "Second acquire should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::WouldBlock,
+                "Error should be WouldBlock"
+            );
+            assert!(e.to_string().contains(&std::process::id().to_string()));
+        }
+
+        drop(lock);
+        assert!(!crate::exists(&path)?, "Lock file should be removed");
+        assert_eq!(LockFile::holder(&path)?, None);
+
+        let lock = LockFile::acquire(&path)?;
+        lock.release()?;
+        assert!(!crate::exists(&path)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lock_file_stale() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("app.pid");
+
+        // 已经退出的子进程留下的锁文件
+        let mut child = std::process::Command::new("true").spawn()?;
+        let dead_pid = child.id();
+        child.wait()?;
+        crate::write(&path, format!("{}\n", dead_pid).as_bytes())?;
+        assert_eq!(LockFile::holder(&path)?, None, "Holder has exited");
+
+        let lock = LockFile::acquire(&path)?;
+        assert_eq!(lock.stale_pid(), Some(dead_pid));
+        assert_eq!(
+            crate::read_to_string(&path)?,
+            format!("{}\n", std::process::id())
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+/*
+    memfd_create创建一个只存在于内存中的匿名文件，可以像普通文件一样读写、mmap，并通过fd传给其他进程
+    memfd_create(name: *const c_char, flags: u32) -> i32
+    name只用于/proc/self/fd中的显示，可以重复；MFD_ALLOW_SEALING允许之后添加封印
+
+    封印(seal)限制之后对文件的修改，通过fcntl添加和查询，一旦添加就不能移除
+    fcntl(fd, F_ADD_SEALS, seals: i32) -> i32
+    fcntl(fd, F_GET_SEALS) -> i32
+    接收方确认文件带有SHRINK | GROW | WRITE后，就不必担心发送方在自己读取时修改内容
+*/
+
+use std::ffi::CString;
+use std::io;
+use std::ops::BitOr;
+
+use crate::{File, INVALID_FD, Result};
+
+/// 封印的组合，例如Seals::SHRINK | Seals::GROW | Seals::WRITE
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Seals {
+    bits: i32,
+}
+
+impl Seals {
+    pub const SEAL: Seals = Seals::from_bits(libc::F_SEAL_SEAL); // 不能再添加新的封印
+    pub const SHRINK: Seals = Seals::from_bits(libc::F_SEAL_SHRINK); // 不能缩小
+    pub const GROW: Seals = Seals::from_bits(libc::F_SEAL_GROW); // 不能增大
+    pub const WRITE: Seals = Seals::from_bits(libc::F_SEAL_WRITE); // 不能修改内容，已有可写mmap时添加失败
+    pub const FUTURE_WRITE: Seals = Seals::from_bits(libc::F_SEAL_FUTURE_WRITE); // 已有的可写mmap不受影响
+
+    const fn from_bits(bits: i32) -> Seals {
+        Seals { bits }
+    }
+
+    pub fn empty() -> Seals {
+        Seals::default()
+    }
+
+    pub fn bits(&self) -> i32 {
+        self.bits
+    }
+
+    pub fn contains(&self, other: Seals) -> bool {
+        self.bits & other.bits == other.bits
+    }
+}
+
+impl BitOr for Seals {
+    type Output = Seals;
+
+    fn bitor(self, rhs: Seals) -> Seals {
+        Seals::from_bits(self.bits | rhs.bits)
+    }
+}
+
+impl File {
+    /// 创建一个可读写、允许封印的内存文件，带有MFD_CLOEXEC
+    pub fn memfd(name: &str) -> Result<File> {
+        let c_name = CString::new(name)?;
+
+        let fd = unsafe {
+            libc::memfd_create(c_name.as_ptr(), libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING)
+        };
+        if fd == INVALID_FD {
+            return Err(crate::error::with_context(
+                io::Error::last_os_error(),
+                "memfd_create",
+                None,
+            ));
+        }
+
+        Ok(File::from_fd(fd))
+    }
+
+    /// 添加封印，不是memfd或创建时没有MFD_ALLOW_SEALING时返回EINVAL/EPERM
+    pub fn add_seals(&mut self, seals: Seals) -> Result<()> {
+        self.ensure_open()?;
+
+        let result = unsafe { libc::fcntl(self.fd, libc::F_ADD_SEALS, seals.bits) };
+        if result < 0 {
+            return Err(self.record_os_error("fcntl"));
+        }
+
+        Ok(())
+    }
+
+    pub fn get_seals(&self) -> Result<Seals> {
+        self.ensure_open()?;
+
+        let result = unsafe { libc::fcntl(self.fd, libc::F_GET_SEALS) };
+        if result < 0 {
+            return Err(self.os_error("fcntl"));
+        }
+
+        Ok(Seals::from_bits(result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Seals;
+    use crate::temp::TempFile;
+    use crate::{File, OpenMode};
+    use std::io;
+
+    #[test]
+    fn test_memfd() -> io::Result<()> {
+        let mut file = File::memfd("test")?;
+        file.write_all(b"Hello, world!")?;
+        assert_eq!(file.metadata()?.len(), 13);
+
+        let mut buf = [0u8; 13];
+        assert_eq!(file.read_at(&mut buf, 0)?, 13);
+        assert_eq!(&buf, b"Hello, world!");
+
+        assert!(
+            file.resolved_path()?
+                .to_str()
+                .unwrap()
+                .starts_with("/memfd:test")
+        );
+
+        let result = File::memfd("bad\0name");
+        assert!(result.is_err(), "Name with NUL should fail");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_seals() -> io::Result<()> {
+        let mut file = File::memfd("sealed")?;
+        file.write_all(b"Hello, world!")?;
+        assert_eq!(file.get_seals()?, Seals::empty());
+
+        let seals = Seals::SHRINK | Seals::GROW | Seals::WRITE;
+        file.add_seals(seals)?;
+        assert!(file.get_seals()?.contains(seals), "Seals should be added");
+        assert!(!file.get_seals()?.contains(Seals::SEAL));
+
+        // 封印后既不能写入也不能改变长度，读取不受影响
+        let result = file.write_at(b"x", 0);
+        assert!(result.is_err(), "Write should be sealed");
+        if let Err(e) = result {
+            assert_eq!(e.raw_os_error(), Some(libc::EPERM), "Error should be EPERM");
+        }
+        assert!(file.set_len(0).is_err(), "Shrink should be sealed");
+        assert!(file.set_len(100).is_err(), "Grow should be sealed");
+
+        let mut buf = [0u8; 5];
+        assert_eq!(file.read_at(&mut buf, 0)?, 5);
+        assert_eq!(&buf, b"Hello");
+
+        // 添加SEAL后不能再添加其他封印
+        file.add_seals(Seals::SEAL)?;
+        assert!(file.add_seals(Seals::FUTURE_WRITE).is_err());
+        assert_eq!(file.last_errno(), Some(libc::EPERM));
+
+        // 普通文件不支持封印
+        let temp_file = TempFile::new()?;
+        let mut file = File::open(temp_file.path(), OpenMode::Write)?;
+        assert!(file.add_seals(Seals::WRITE).is_err());
+
+        Ok(())
+    }
+}
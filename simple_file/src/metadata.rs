@@ -0,0 +1,435 @@
+/*
+    文件元信息，由fstat/stat/lstat填充的stat结构体构建
+
+    st_mode的高位(S_IFMT)表示文件类型，低12位表示权限位(含setuid/setgid/sticky)
+*/
+
+// stat各字段的类型随平台不同(例如macOS上st_mode为u16)，这里统一转换
+#![allow(clippy::unnecessary_cast)]
+
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{File, Result, sys};
+
+#[derive(Clone, Copy)]
+pub struct Metadata {
+    stat: sys::stat,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FileType {
+    mode: u32,
+}
+
+/// 文件的权限位，即st_mode的低12位(rwx以及setuid/setgid/sticky)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Permissions {
+    mode: u32,
+}
+
+impl Metadata {
+    pub(crate) fn from_stat(stat: sys::stat) -> Metadata {
+        Metadata { stat }
+    }
+
+    /// 文件大小，单位字节
+    pub fn len(&self) -> u64 {
+        self.stat.st_size as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 完整的st_mode，包括文件类型和权限位
+    pub fn mode(&self) -> u32 {
+        self.stat.st_mode as u32
+    }
+
+    pub fn permissions(&self) -> Permissions {
+        Permissions::from_mode(self.stat.st_mode as u32)
+    }
+
+    pub fn file_type(&self) -> FileType {
+        FileType {
+            mode: self.stat.st_mode as u32,
+        }
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.file_type().is_file()
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.file_type().is_dir()
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.file_type().is_symlink()
+    }
+
+    /// 最后访问时间(atime)
+    pub fn accessed(&self) -> SystemTime {
+        system_time(self.stat.st_atime as i64, self.stat.st_atime_nsec as i64)
+    }
+
+    /// 最后修改时间(mtime)
+    pub fn modified(&self) -> SystemTime {
+        system_time(self.stat.st_mtime as i64, self.stat.st_mtime_nsec as i64)
+    }
+
+    /// 最后状态变更时间(ctime)，权限、所有者、链接数等元信息变化也会更新
+    pub fn changed(&self) -> SystemTime {
+        system_time(self.stat.st_ctime as i64, self.stat.st_ctime_nsec as i64)
+    }
+
+    /// inode编号，与dev一起唯一标识一个文件
+    pub fn ino(&self) -> u64 {
+        self.stat.st_ino as u64
+    }
+
+    /// 文件所在设备的设备号
+    pub fn dev(&self) -> u64 {
+        self.stat.st_dev as u64
+    }
+
+    /// 实际分配的512字节块数，与块大小无关；稀疏文件可能小于len，小文件因按块分配可能大于len
+    pub fn blocks(&self) -> u64 {
+        self.stat.st_blocks as u64
+    }
+
+    /// 硬链接数
+    pub fn nlink(&self) -> u64 {
+        self.stat.st_nlink as u64
+    }
+
+    pub fn uid(&self) -> u32 {
+        self.stat.st_uid
+    }
+
+    pub fn gid(&self) -> u32 {
+        self.stat.st_gid
+    }
+}
+
+// time_t + 纳秒 -> SystemTime，1970年之前的时间秒数为负
+pub(crate) fn system_time(sec: i64, nsec: i64) -> SystemTime {
+    let nanos = Duration::from_nanos(nsec as u64);
+    if sec >= 0 {
+        UNIX_EPOCH + Duration::from_secs(sec as u64) + nanos
+    } else {
+        UNIX_EPOCH - Duration::from_secs(sec.unsigned_abs()) + nanos
+    }
+}
+
+impl fmt::Debug for Metadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Metadata")
+            .field("file_type", &self.file_type())
+            .field("mode", &format_args!("{:#o}", self.mode() & 0o7777))
+            .field("len", &self.len())
+            .field("ino", &self.ino())
+            .field("dev", &self.dev())
+            .field("modified", &self.modified())
+            .finish()
+    }
+}
+
+impl FileType {
+    pub(crate) fn from_mode(mode: u32) -> FileType {
+        FileType { mode }
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.is(libc::S_IFREG)
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.is(libc::S_IFDIR)
+    }
+
+    /// 只有通过lstat(symlink_metadata)获取的元信息才可能是符号链接
+    pub fn is_symlink(&self) -> bool {
+        self.is(libc::S_IFLNK)
+    }
+
+    /// 命名管道(mkfifo创建)
+    pub fn is_fifo(&self) -> bool {
+        self.is(libc::S_IFIFO)
+    }
+
+    /// Unix域套接字在文件系统中的节点
+    pub fn is_socket(&self) -> bool {
+        self.is(libc::S_IFSOCK)
+    }
+
+    /// 块设备，例如/dev/sda
+    pub fn is_block_device(&self) -> bool {
+        self.is(libc::S_IFBLK)
+    }
+
+    /// 字符设备，例如/dev/null、终端
+    pub fn is_char_device(&self) -> bool {
+        self.is(libc::S_IFCHR)
+    }
+
+    fn is(&self, kind: libc::mode_t) -> bool {
+        self.mode & libc::S_IFMT as u32 == kind as u32
+    }
+}
+
+impl Permissions {
+    /// 文件类型的位会被去掉，例如Permissions::from_mode(0o600)
+    pub fn from_mode(mode: u32) -> Permissions {
+        Permissions {
+            mode: mode & 0o7777,
+        }
+    }
+
+    pub fn mode(&self) -> u32 {
+        self.mode
+    }
+
+    /// 所有人都没有写权限时为只读
+    pub fn readonly(&self) -> bool {
+        self.mode & 0o222 == 0
+    }
+
+    /// 设为只读时去掉所有写权限，取消只读时只恢复所有者的写权限
+    pub fn set_readonly(&mut self, readonly: bool) {
+        if readonly {
+            self.mode &= !0o222;
+        } else {
+            self.mode |= 0o200;
+        }
+    }
+}
+
+impl File {
+    /// 通过fstat获取已打开文件的元信息
+    pub fn metadata(&self) -> Result<Metadata> {
+        Ok(Metadata::from_stat(self.fstat()?))
+    }
+
+    /*
+        修改已打开文件的权限，封装POSIX fchmod
+        fchmod(fd: i32, mode: mode_t) -> i32
+        不受umask影响，只有文件所有者或root可以修改
+    */
+    pub fn set_permissions(&mut self, perm: Permissions) -> Result<()> {
+        self.ensure_open()?;
+
+        let result = unsafe { libc::fchmod(self.fd, perm.mode as libc::mode_t) };
+        if result < 0 {
+            return Err(self.record_os_error("fchmod"));
+        }
+
+        Ok(())
+    }
+
+    /*
+        修改已打开文件的所有者和所属组，封装POSIX fchown
+        fchown(fd: i32, owner: uid_t, group: gid_t) -> i32
+        为None的一项保持不变；修改所有者需要root权限，普通用户只能把组改为自己所在的组
+    */
+    pub fn set_owner(&mut self, uid: Option<u32>, gid: Option<u32>) -> Result<()> {
+        self.ensure_open()?;
+
+        let (uid, gid) = owner_ids(uid, gid);
+        let result = unsafe { libc::fchown(self.fd, uid, gid) };
+        if result < 0 {
+            return Err(self.record_os_error("fchown"));
+        }
+
+        Ok(())
+    }
+}
+
+// None -> -1，chown系列函数把-1视为"保持不变"
+pub(crate) fn owner_ids(uid: Option<u32>, gid: Option<u32>) -> (libc::uid_t, libc::gid_t) {
+    (
+        uid.map_or(libc::uid_t::MAX, |uid| uid as libc::uid_t),
+        gid.map_or(libc::gid_t::MAX, |gid| gid as libc::gid_t),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Permissions;
+    use crate::temp::{TempDir, TempFile};
+    use crate::{File, OpenMode, chown, lchown, metadata, set_permissions, symlink_metadata};
+    use std::io;
+    use std::os::unix::fs::MetadataExt;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_file_metadata() -> io::Result<()> {
+        let mut temp_file = TempFile::new()?;
+        temp_file.file_mut().write_all(b"Hello, world!")?;
+
+        let file = File::open(temp_file.path(), OpenMode::Read)?;
+        let meta = file.metadata()?;
+        let expected = std::fs::metadata(temp_file.path())?;
+
+        assert_eq!(meta.len(), 13);
+        assert!(meta.is_file() && !meta.is_dir() && !meta.is_symlink());
+        assert_eq!(meta.mode(), expected.mode(), "Mode should match std");
+        assert_eq!(meta.ino(), expected.ino(), "Inode should match std");
+        assert_eq!(meta.dev(), expected.dev(), "Device should match std");
+        assert_eq!(
+            meta.modified(),
+            expected.modified()?,
+            "mtime should match std"
+        );
+        assert_eq!(
+            meta.accessed(),
+            expected.accessed()?,
+            "atime should match std"
+        );
+
+        // 刚写入的文件，修改时间应当就在附近
+        let age = SystemTime::now()
+            .duration_since(meta.changed())
+            .unwrap_or_default();
+        assert!(age < Duration::from_secs(60), "ctime should be recent");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_path_metadata() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let target = dir.path().join("target.txt");
+        let link = dir.path().join("link");
+        std::fs::write(&target, b"content")?;
+        std::os::unix::fs::symlink(&target, &link)?;
+
+        let meta = metadata(dir.path())?;
+        assert!(meta.is_dir(), "Directory should be a dir");
+
+        // metadata跟随符号链接，symlink_metadata不跟随
+        let meta = metadata(&link)?;
+        assert!(meta.is_file());
+        assert_eq!(meta.len(), 7);
+        let meta = symlink_metadata(&link)?;
+        assert!(meta.is_symlink());
+
+        let result = metadata(dir.path().join("missing"));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::NotFound);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_types() -> io::Result<()> {
+        let dir = TempDir::new()?;
+
+        let file_type = metadata("/dev/null")?.file_type();
+        assert!(
+            file_type.is_char_device(),
+            "/dev/null should be a char device"
+        );
+        assert!(!file_type.is_file() && !file_type.is_block_device());
+
+        let fifo = dir.path().join("fifo");
+        let path = std::ffi::CString::new(fifo.to_str().unwrap())?;
+        assert_eq!(unsafe { libc::mkfifo(path.as_ptr(), 0o644) }, 0);
+        let file_type = metadata(&fifo)?.file_type();
+        assert!(file_type.is_fifo(), "mkfifo should create a FIFO");
+        assert!(!file_type.is_socket() && !file_type.is_char_device());
+
+        let socket = dir.path().join("socket");
+        let _listener = std::os::unix::net::UnixListener::bind(&socket)?;
+        let file_type = metadata(&socket)?.file_type();
+        assert!(file_type.is_socket(), "Bound socket should be a socket");
+        assert!(!file_type.is_fifo() && !file_type.is_file());
+
+        // 已打开的文件通过fstat获取类型
+        let file = File::open(dir.path(), OpenMode::Read)?;
+        assert!(file.metadata()?.file_type().is_dir());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_permissions() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let mut file = File::open(temp_file.path(), OpenMode::ReadWrite)?;
+
+        file.set_permissions(Permissions::from_mode(0o600))?;
+        assert_eq!(file.metadata()?.permissions().mode(), 0o600);
+
+        set_permissions(temp_file.path(), Permissions::from_mode(0o755))?;
+        let perm = metadata(temp_file.path())?.permissions();
+        assert_eq!(perm.mode(), 0o755, "Mode should be 0o755");
+        assert!(!perm.readonly());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_permissions_readonly() {
+        let mut perm = Permissions::from_mode(0o100664);
+        assert_eq!(perm.mode(), 0o664, "File type bits should be dropped");
+
+        perm.set_readonly(true);
+        assert_eq!(perm.mode(), 0o444);
+        assert!(perm.readonly());
+
+        perm.set_readonly(false);
+        assert_eq!(perm.mode(), 0o644);
+        assert!(!perm.readonly());
+    }
+
+    #[test]
+    fn test_set_owner() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let mut file = File::open(temp_file.path(), OpenMode::Write)?;
+        let meta = file.metadata()?;
+        let (uid, gid) = (meta.uid(), meta.gid());
+
+        // 保持不变以及设置为当前值总是允许的
+        file.set_owner(None, None)?;
+        file.set_owner(Some(uid), Some(gid))?;
+        chown(temp_file.path(), Some(uid), None)?;
+
+        if unsafe { libc::geteuid() } != 0 {
+            let result = file.set_owner(Some(uid + 1), None);
+            assert!(result.is_err(), "Non-root should not give files away");
+            assert_eq!(file.last_errno(), Some(libc::EPERM));
+            return Ok(());
+        }
+
+        file.set_owner(Some(12345), None)?;
+        let meta = file.metadata()?;
+        assert_eq!((meta.uid(), meta.gid()), (12345, gid), "gid should be kept");
+
+        chown(temp_file.path(), None, Some(54321))?;
+        let meta = metadata(temp_file.path())?;
+        assert_eq!((meta.uid(), meta.gid()), (12345, 54321));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lchown() -> io::Result<()> {
+        // 修改为其他用户需要root权限
+        if unsafe { libc::geteuid() } != 0 {
+            return Ok(());
+        }
+
+        let dir = TempDir::new()?;
+        let target = dir.path().join("target.txt");
+        let link = dir.path().join("link");
+        std::fs::write(&target, b"content")?;
+        std::os::unix::fs::symlink(&target, &link)?;
+
+        // lchown修改链接本身，不影响指向的文件
+        lchown(&link, Some(12345), Some(12345))?;
+        assert_eq!(symlink_metadata(&link)?.uid(), 12345);
+        assert_eq!(metadata(&target)?.uid(), 0);
+
+        Ok(())
+    }
+}
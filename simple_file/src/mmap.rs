@@ -0,0 +1,210 @@
+/*
+    内存映射，封装POSIX mmap/munmap/msync
+
+    mmap(addr: *mut c_void, len: size_t, prot: i32, flags: i32, fd: i32, offset: off_t) -> *mut c_void
+    失败返回MAP_FAILED
+
+    映射建立后即使关闭fd映射依然有效，所以Mmap/MmapMut不借用File，由Drop负责munmap
+
+    映射的内容可能被其他进程(或本进程通过write)随时修改，文件被截断后访问超出部分会触发SIGBUS，
+    这些都不在Rust借用检查的控制之下，&[u8]要求内容在借用期间不变，所以建立映射是unsafe的，与memmap2相同
+*/
+
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::slice;
+
+use crate::{Error, File, Result, error};
+
+/// 只读映射，解引用为&[u8]
+pub struct Mmap {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+/// 可写的共享映射，解引用为&mut [u8]，写入会回写到文件
+pub struct MmapMut {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+// 映射的内存区域不属于任何线程，与&[u8]/&mut [u8]的线程安全语义一致
+unsafe impl Send for Mmap {}
+unsafe impl Sync for Mmap {}
+unsafe impl Send for MmapMut {}
+unsafe impl Sync for MmapMut {}
+
+impl File {
+    /// 将文件从头开始的len个字节只读映射到内存
+    ///
+    /// # Safety
+    ///
+    /// 映射存在期间，调用者必须保证文件不会被截断到len以下，也不会被本进程或其他进程修改，
+    /// 否则解引用得到的&[u8]内容会在借用期间改变(未定义行为)，访问被截断的部分会触发SIGBUS
+    pub unsafe fn mmap(&self, len: usize) -> Result<Mmap> {
+        let ptr = self.map(len, libc::PROT_READ)?;
+
+        Ok(Mmap { ptr, len })
+    }
+
+    /// 将文件从头开始的len个字节以读写、共享方式映射到内存
+    ///
+    /// 映射范围不能超出文件大小，超出部分的访问会触发SIGBUS，
+    /// 映射前需要先把文件扩展到足够的长度(ftruncate或写入)
+    ///
+    /// # Safety
+    ///
+    /// 与mmap相同，映射存在期间文件不能被截断，也不能通过映射以外的方式(write、其他映射、其他进程)修改
+    pub unsafe fn mmap_mut(&self, len: usize) -> Result<MmapMut> {
+        let ptr = self.map(len, libc::PROT_READ | libc::PROT_WRITE)?;
+
+        Ok(MmapMut { ptr, len })
+    }
+
+    fn map(&self, len: usize, prot: i32) -> Result<*mut libc::c_void> {
+        self.ensure_open()?;
+        if len == 0 {
+            return Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                "Invalid mmap length, zero not allowed",
+            ));
+        }
+
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len as libc::size_t,
+                prot,
+                libc::MAP_SHARED,
+                self.fd,
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(self.os_error("mmap"));
+        }
+
+        Ok(ptr)
+    }
+}
+
+impl MmapMut {
+    /*
+        msync(addr: *mut c_void, len: size_t, flags: i32) -> i32
+        MS_SYNC会等待回写完成后再返回
+    */
+    pub fn flush(&self) -> Result<()> {
+        let result = unsafe { libc::msync(self.ptr, self.len as libc::size_t, libc::MS_SYNC) };
+        if result < 0 {
+            return Err(error::with_context(
+                io::Error::last_os_error(),
+                "msync",
+                None,
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl Deref for Mmap {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+}
+
+impl Deref for MmapMut {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+}
+
+impl DerefMut for MmapMut {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.ptr as *mut u8, self.len) }
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr, self.len as libc::size_t);
+        }
+    }
+}
+
+impl Drop for MmapMut {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr, self.len as libc::size_t);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::temp::TempFile;
+    use crate::{File, OpenMode};
+    use std::io;
+
+    #[test]
+    fn test_mmap_read() -> io::Result<()> {
+        let mut temp_file = TempFile::new()?;
+        temp_file.file_mut().write_all(b"Hello, world!")?;
+
+        let file = File::open(temp_file.path(), OpenMode::Read)?;
+        let map = unsafe { file.mmap(13)? };
+        assert_eq!(&map[..], b"Hello, world!", "Mapped content should match");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mmap_mut_write_back() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let mut file = File::open(temp_file.path(), OpenMode::ReadWrite)?;
+        // 先把文件扩展到映射长度，避免SIGBUS
+        file.write_all(&[0u8; 64])?;
+
+        let mut map = unsafe { file.mmap_mut(64)? };
+        map[..12].copy_from_slice(b"Hello, mmap!");
+        map.flush()?;
+        drop(map);
+
+        // 按偏移量读取，不依赖读写位置
+        let mut buf = [0u8; 64];
+        assert_eq!(file.read_at(&mut buf, 0)?, 64);
+        assert_eq!(
+            &buf[..12],
+            b"Hello, mmap!",
+            "Mapped write should be visible"
+        );
+        assert!(
+            buf[12..].iter().all(|&b| b == 0),
+            "Rest should be untouched"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mmap_zero_len() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let file = File::open(temp_file.path(), OpenMode::Read)?;
+        let result = unsafe { file.mmap(0) };
+        assert!(result.is_err(), "Zero length mmap should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::InvalidInput,
+                "Error should be InvalidInput"
+            );
+        }
+
+        Ok(())
+    }
+}
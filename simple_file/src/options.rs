@@ -0,0 +1,855 @@
+/*
+    OpenMode只有固定的几种组合，无法表达"读+追加"、"创建但不截断"、自定义权限等
+    OpenOptions与std::fs::OpenOptions用法一致，把各个选项组装为open的flags和mode
+
+    File::options().read(true).append(true).create(true).open("app.log")?;
+*/
+
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use libc::{O_APPEND, O_CLOEXEC, O_CREAT, O_EXCL, O_RDONLY, O_RDWR, O_TRUNC, O_WRONLY};
+
+use crate::{
+    DEFAULT_FILE_PERMSSIONS, Error, File, INVALID_FD, Result, error, retry_eintr, sys, to_c_path,
+};
+
+// openat2解析路径期间目录树被并发修改时返回EAGAIN，重试的次数上限
+#[cfg(target_os = "linux")]
+const OPENAT2_MAX_RETRIES: usize = 8;
+
+#[derive(Clone, Debug)]
+pub struct OpenOptions {
+    read: bool,
+    write: bool,
+    append: bool,
+    truncate: bool,
+    create: bool,
+    create_new: bool,
+    mode: u32, // 创建文件时的权限，实际权限还会受umask影响
+    retry_interrupted: bool,
+    inheritable: bool, // 为false时带上O_CLOEXEC
+    sync: bool,
+    data_sync: bool,
+    nonblocking: bool,
+    #[cfg(target_os = "linux")]
+    direct: bool,
+    nofollow: bool,
+    #[cfg(target_os = "linux")]
+    noatime: bool,
+    #[cfg(target_os = "linux")]
+    path_only: bool,
+}
+
+impl OpenOptions {
+    /// 所有选项默认关闭，权限默认0o644
+    pub fn new() -> OpenOptions {
+        OpenOptions {
+            read: false,
+            write: false,
+            append: false,
+            truncate: false,
+            create: false,
+            create_new: false,
+            mode: DEFAULT_FILE_PERMSSIONS as u32,
+            retry_interrupted: true,
+            inheritable: false,
+            sync: false,
+            data_sync: false,
+            nonblocking: false,
+            #[cfg(target_os = "linux")]
+            direct: false,
+            nofollow: false,
+            #[cfg(target_os = "linux")]
+            noatime: false,
+            #[cfg(target_os = "linux")]
+            path_only: false,
+        }
+    }
+
+    pub fn read(&mut self, read: bool) -> &mut OpenOptions {
+        self.read = read;
+        self
+    }
+
+    pub fn write(&mut self, write: bool) -> &mut OpenOptions {
+        self.write = write;
+        self
+    }
+
+    /// 追加写入，隐含write
+    pub fn append(&mut self, append: bool) -> &mut OpenOptions {
+        self.append = append;
+        self
+    }
+
+    /// 打开时把文件截断为0，需要write
+    pub fn truncate(&mut self, truncate: bool) -> &mut OpenOptions {
+        self.truncate = truncate;
+        self
+    }
+
+    /// 文件不存在时创建，需要write或append
+    pub fn create(&mut self, create: bool) -> &mut OpenOptions {
+        self.create = create;
+        self
+    }
+
+    /// 原子地创建新文件，文件已存在时失败(AlreadyExists)，设置后create和truncate被忽略
+    pub fn create_new(&mut self, create_new: bool) -> &mut OpenOptions {
+        self.create_new = create_new;
+        self
+    }
+
+    /// 创建文件时使用的权限位，例如0o600
+    pub fn mode(&mut self, mode: u32) -> &mut OpenOptions {
+        self.mode = mode;
+        self
+    }
+
+    /*
+        默认以O_CLOEXEC打开，exec出的子进程不会继承这个fd
+        确实需要把fd交给子进程时(例如作为子进程的输入输出)设置为true
+    */
+    pub fn inheritable(&mut self, inheritable: bool) -> &mut OpenOptions {
+        self.inheritable = inheritable;
+        self
+    }
+
+    /*
+        O_SYNC: 每次write返回前，数据和元信息都已写入存储设备，相当于每次写入后调用sync_all
+        适合WAL等要求逐条写入持久化的场景，代价是每次写入都要等待磁盘
+    */
+    pub fn sync(&mut self, sync: bool) -> &mut OpenOptions {
+        self.sync = sync;
+        self
+    }
+
+    /// O_DSYNC: 与sync相同，但只保证数据(以及读取数据所需的元信息，如文件长度)落盘，相当于每次写入后调用sync_data
+    pub fn data_sync(&mut self, data_sync: bool) -> &mut OpenOptions {
+        self.data_sync = data_sync;
+        self
+    }
+
+    /*
+        O_NONBLOCK: 打开和之后的读写都不阻塞，主要用于FIFO(命名管道)
+        FIFO默认在另一端也被打开之前阻塞在open上，生产者和消费者在此汇合
+        非阻塞打开读端总是立即成功；非阻塞打开写端时，如果还没有读端则返回ENXIO，可以稍后重试
+        打开后可以通过File::set_nonblocking(false)恢复阻塞读写
+    */
+    pub fn nonblocking(&mut self, nonblocking: bool) -> &mut OpenOptions {
+        self.nonblocking = nonblocking;
+        self
+    }
+
+    /*
+        O_DIRECT (Linux): 绕过页缓存，直接在用户缓冲区与设备之间传输数据
+        要求缓冲区地址、读写长度和文件偏移量都按块大小对齐，否则read/write返回EINVAL
+        缓冲区可以用File::aligned_buffer分配；部分文件系统(例如tmpfs)不支持，open时返回EINVAL
+    */
+    #[cfg(target_os = "linux")]
+    pub fn direct(&mut self, direct: bool) -> &mut OpenOptions {
+        self.direct = direct;
+        self
+    }
+
+    /// O_NOFOLLOW: 路径的最后一个分量是符号链接时打开失败(ELOOP)，防止被替换为符号链接的攻击
+    pub fn nofollow(&mut self, nofollow: bool) -> &mut OpenOptions {
+        self.nofollow = nofollow;
+        self
+    }
+
+    /*
+        O_NOATIME (Linux): 读取时不更新访问时间，适合备份、索引等需要扫描大量文件的工具
+        只有文件所有者(或拥有CAP_FOWNER)可以使用，否则open返回EPERM
+    */
+    #[cfg(target_os = "linux")]
+    pub fn noatime(&mut self, noatime: bool) -> &mut OpenOptions {
+        self.noatime = noatime;
+        self
+    }
+
+    /*
+        O_PATH (Linux): 与File::open_path相同，得到的File只能用于fstat、openat等路径相关操作
+        设置后read/write/append/truncate/create等选项都被忽略，与nofollow一起使用时可以打开符号链接本身
+    */
+    #[cfg(target_os = "linux")]
+    pub fn path_only(&mut self, path_only: bool) -> &mut OpenOptions {
+        self.path_only = path_only;
+        self
+    }
+
+    /// open被信号打断(EINTR)时是否自动重试，默认为true，打开得到的File也沿用这个设置
+    pub fn retry_interrupted(&mut self, retry: bool) -> &mut OpenOptions {
+        self.retry_interrupted = retry;
+        self
+    }
+
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> Result<File> {
+        let c_style_str_path = to_c_path(path.as_ref())?;
+        let flags = self.flags()?;
+
+        let fd = retry_eintr(self.retry_interrupted, || unsafe {
+            libc::open(c_style_str_path.as_ptr(), flags, self.mode as libc::c_uint)
+        });
+
+        let path = path.as_ref();
+        if fd == INVALID_FD {
+            return Err(error::with_context(
+                io::Error::last_os_error(),
+                "open",
+                Some(path),
+            ));
+        }
+
+        Ok(self.wrap(fd, Some(path.to_path_buf())))
+    }
+
+    /// 与open相同，但相对路径以dir为基准，封装openat
+    pub fn open_at<P: AsRef<Path>>(&self, dir: &File, path: P) -> Result<File> {
+        dir.ensure_open()?;
+        let c_style_str_path = to_c_path(path.as_ref())?;
+        let flags = self.flags()?;
+
+        let fd = retry_eintr(self.retry_interrupted, || unsafe {
+            libc::openat(
+                dir.fd,
+                c_style_str_path.as_ptr(),
+                flags,
+                self.mode as libc::c_uint,
+            )
+        });
+
+        let path = dir.join_path(path.as_ref());
+        if fd == INVALID_FD {
+            return Err(error::with_fd_context(
+                io::Error::last_os_error(),
+                "openat",
+                path.as_deref(),
+                Some(dir.fd),
+            ));
+        }
+
+        Ok(self.wrap(fd, path))
+    }
+
+    /*
+        打开dir下的path，解析过程不能离开dir，也不能经过任何符号链接，由Dir::open_beneath调用
+        Linux 5.6+使用openat2，由内核在解析路径时检查，不存在检查与打开之间的竞争
+        openat2(dirfd: i32, path: *const c_char, how: *const open_how, size: size_t) -> i32
+        open_how.resolve为RESOLVE_BENEATH时，绝对路径或".."越过dir返回EXDEV，RESOLVE_NO_SYMLINKS时遇到符号链接返回ELOOP
+        内核不支持openat2(ENOSYS)、被seccomp禁止(EPERM，例如部分容器)或其他平台上，逐级openat(O_NOFOLLOW)手动解析，返回同样的错误
+        解析期间有并发的rename或mount时openat2返回EAGAIN，最多重试OPENAT2_MAX_RETRIES次
+    */
+    pub(crate) fn open_beneath(&self, dir: &File, path: &Path) -> Result<File> {
+        dir.ensure_open()?;
+
+        #[cfg(target_os = "linux")]
+        {
+            let c_style_str_path = to_c_path(path)?;
+            let flags = self.flags()?;
+            let mut how: libc::open_how = unsafe { std::mem::zeroed() };
+            how.flags = flags as u64;
+            // 不创建文件时mode必须为0，否则openat2返回EINVAL
+            if flags & O_CREAT != 0 {
+                how.mode = self.mode as u64;
+            }
+            how.resolve = libc::RESOLVE_BENEATH | libc::RESOLVE_NO_SYMLINKS;
+
+            let mut retries = 0;
+            loop {
+                let fd = retry_eintr(self.retry_interrupted, || unsafe {
+                    libc::syscall(
+                        libc::SYS_openat2,
+                        dir.fd,
+                        c_style_str_path.as_ptr(),
+                        &how as *const libc::open_how,
+                        std::mem::size_of::<libc::open_how>(),
+                    ) as i32
+                });
+                if fd != INVALID_FD {
+                    return Ok(self.wrap(fd, dir.join_path(path)));
+                }
+
+                let err = io::Error::last_os_error();
+                match err.raw_os_error() {
+                    Some(libc::ENOSYS | libc::EPERM) => break,
+                    Some(libc::EAGAIN) if retries < OPENAT2_MAX_RETRIES => retries += 1,
+                    _ => {
+                        return Err(error::with_fd_context(
+                            err,
+                            "openat2",
+                            dir.join_path(path).as_deref(),
+                            Some(dir.fd),
+                        ));
+                    }
+                }
+            }
+        }
+
+        self.open_beneath_by_components(dir, path)
+    }
+
+    // 逐级openat，目录用O_DIRECTORY|O_NOFOLLOW打开，".."只能回到已经打开的上一级
+    fn open_beneath_by_components(&self, dir: &File, path: &Path) -> Result<File> {
+        let full_path = dir.join_path(path);
+        let fail = |errno: i32, op: &'static str| {
+            error::with_fd_context(
+                io::Error::from_raw_os_error(errno),
+                op,
+                full_path.as_deref(),
+                Some(dir.fd),
+            )
+        };
+
+        let mut components: Vec<Component> = path.components().collect();
+        // 最后一级是普通文件名时按options打开，否则(例如"a/..")打开解析到的目录本身
+        let last = match components.last() {
+            Some(Component::Normal(name)) => {
+                let name = *name;
+                components.pop();
+                Some(name)
+            }
+            _ => None,
+        };
+
+        let mut opened: Vec<File> = Vec::new();
+        for component in components {
+            let name = match component {
+                Component::CurDir => continue,
+                Component::ParentDir => {
+                    if opened.pop().is_none() {
+                        return Err(fail(libc::EXDEV, "openat"));
+                    }
+                    continue;
+                }
+                Component::RootDir | Component::Prefix(_) => {
+                    return Err(fail(libc::EXDEV, "openat"));
+                }
+                Component::Normal(name) => name,
+            };
+
+            let current = opened.last().unwrap_or(dir);
+            let c_style_str_name = to_c_path(Path::new(name))?;
+            let fd = retry_eintr(self.retry_interrupted, || unsafe {
+                libc::openat(
+                    current.fd,
+                    c_style_str_name.as_ptr(),
+                    libc::O_RDONLY | libc::O_DIRECTORY | libc::O_NOFOLLOW | O_CLOEXEC,
+                )
+            });
+            if fd == INVALID_FD {
+                let errno = io::Error::last_os_error().raw_os_error().unwrap_or(0);
+                // Linux下对符号链接使用O_DIRECTORY|O_NOFOLLOW返回ENOTDIR，统一为ELOOP
+                let is_symlink = errno == libc::ENOTDIR && {
+                    let mut stat: sys::stat = unsafe { std::mem::zeroed() };
+                    let result = unsafe {
+                        sys::fstatat(
+                            current.fd,
+                            c_style_str_name.as_ptr(),
+                            &mut stat,
+                            libc::AT_SYMLINK_NOFOLLOW,
+                        )
+                    };
+                    result == 0 && stat.st_mode & libc::S_IFMT == libc::S_IFLNK
+                };
+                return Err(fail(if is_symlink { libc::ELOOP } else { errno }, "openat"));
+            }
+            opened.push(File::from_fd(fd));
+        }
+
+        let current = opened.last().unwrap_or(dir);
+        let c_style_str_name = to_c_path(Path::new(last.unwrap_or(".".as_ref())))?;
+        let flags = self.flags()? | libc::O_NOFOLLOW;
+        let fd = retry_eintr(self.retry_interrupted, || unsafe {
+            libc::openat(
+                current.fd,
+                c_style_str_name.as_ptr(),
+                flags,
+                self.mode as libc::c_uint,
+            )
+        });
+        if fd == INVALID_FD {
+            return Err(error::with_fd_context(
+                io::Error::last_os_error(),
+                "openat",
+                full_path.as_deref(),
+                Some(dir.fd),
+            ));
+        }
+
+        Ok(self.wrap(fd, full_path))
+    }
+
+    // 把打开得到的fd包装为File，并带上与File相关的选项
+    fn wrap(&self, fd: i32, path: Option<PathBuf>) -> File {
+        let mut file = File::from_fd(fd);
+        file.path = path;
+        file.retry_interrupted = self.retry_interrupted;
+        #[cfg(target_os = "linux")]
+        {
+            file.path_only = self.path_only;
+        }
+        file
+    }
+
+    // 选项 -> open flags，与std一致，矛盾的组合返回InvalidInput
+    fn flags(&self) -> Result<i32> {
+        // O_PATH会忽略读写和创建相关的flags
+        #[cfg(target_os = "linux")]
+        if self.path_only {
+            return Ok(libc::O_PATH | self.behavior_flags());
+        }
+
+        Ok(
+            self.access_flags()?
+                | self.creation_flags()?
+                | self.behavior_flags()
+                | sys::O_LARGEFILE,
+        )
+    }
+
+    // 不影响打开方式，只改变fd行为的flags
+    fn behavior_flags(&self) -> i32 {
+        let mut flags = 0;
+        if !self.inheritable {
+            flags |= O_CLOEXEC;
+        }
+        if self.sync {
+            flags |= libc::O_SYNC;
+        }
+        if self.data_sync {
+            flags |= libc::O_DSYNC;
+        }
+        if self.nonblocking {
+            flags |= libc::O_NONBLOCK;
+        }
+        #[cfg(target_os = "linux")]
+        if self.direct {
+            flags |= libc::O_DIRECT;
+        }
+        if self.nofollow {
+            flags |= libc::O_NOFOLLOW;
+        }
+        #[cfg(target_os = "linux")]
+        if self.noatime {
+            flags |= libc::O_NOATIME;
+        }
+
+        flags
+    }
+
+    fn access_flags(&self) -> Result<i32> {
+        match (self.read, self.write, self.append) {
+            (true, false, false) => Ok(O_RDONLY),
+            (false, true, false) => Ok(O_WRONLY),
+            (true, true, false) => Ok(O_RDWR),
+            (false, _, true) => Ok(O_WRONLY | O_APPEND),
+            (true, _, true) => Ok(O_RDWR | O_APPEND),
+            (false, false, false) => Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                "Invalid options, read, write or append required",
+            )),
+        }
+    }
+
+    fn creation_flags(&self) -> Result<i32> {
+        if !self.write && !self.append && (self.truncate || self.create || self.create_new) {
+            return Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                "Invalid options, creating or truncating requires write access",
+            ));
+        }
+        if self.append && self.truncate && !self.create_new {
+            return Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                "Invalid options, append and truncate are exclusive",
+            ));
+        }
+
+        Ok(match (self.create, self.truncate, self.create_new) {
+            (_, _, true) => O_CREAT | O_EXCL,
+            (true, true, false) => O_CREAT | O_TRUNC,
+            (true, false, false) => O_CREAT,
+            (false, true, false) => O_TRUNC,
+            (false, false, false) => 0,
+        })
+    }
+}
+
+impl Default for OpenOptions {
+    fn default() -> OpenOptions {
+        OpenOptions::new()
+    }
+}
+
+impl File {
+    /// 等价于OpenOptions::new()
+    pub fn options() -> OpenOptions {
+        OpenOptions::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OpenOptions;
+    use crate::temp::TempDir;
+    use crate::{File, OpenMode, mkfifo};
+    use std::io::{self, Read, SeekFrom};
+    use std::os::fd::AsRawFd;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+
+    #[test]
+    fn test_read_append() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("log.txt");
+        std::fs::write(&path, b"first\n")?;
+
+        let mut file = File::options().read(true).append(true).open(&path)?;
+        file.write_all(b"second\n")?;
+
+        file.seek(SeekFrom::Start(0))?;
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)?;
+        assert_eq!(
+            content, b"first\nsecond\n",
+            "Append handle should be readable"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_without_truncate() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("data.txt");
+
+        let mut file = OpenOptions::new().write(true).create(true).open(&path)?;
+        file.write_all(b"Hello, world!")?;
+        drop(file);
+
+        // 再次打开不会截断，从头覆盖写入
+        let mut file = OpenOptions::new().write(true).create(true).open(&path)?;
+        file.write_all(b"HELLO")?;
+        drop(file);
+
+        assert_eq!(std::fs::read(&path)?, b"HELLO, world!");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_new() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("lock");
+
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        let result = OpenOptions::new().write(true).create_new(true).open(&path);
+        assert!(result.is_err(), "Second create_new should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::AlreadyExists,
+                "Error should be AlreadyExists"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mode() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("secret");
+
+        OpenOptions::new()
+            .write(true)
+            .create(true)
+            .mode(0o600)
+            .open(&path)?;
+        let mode = std::fs::metadata(&path)?.permissions().mode();
+        assert_eq!(mode & 0o777, 0o600, "File should be created with 0o600");
+
+        Ok(())
+    }
+
+    // 子进程中/dev/fd/N存在说明fd被继承了
+    fn inherited_by_child(file: &File) -> io::Result<bool> {
+        let status = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("test -e /dev/fd/{}", file.as_raw_fd()))
+            .status()?;
+        Ok(status.success())
+    }
+
+    #[test]
+    fn test_cloexec() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("data.txt");
+
+        let file = OpenOptions::new().write(true).create(true).open(&path)?;
+        assert!(!inherited_by_child(&file)?, "fd should be closed on exec");
+
+        let file = File::open(&path, OpenMode::Read)?;
+        assert!(!inherited_by_child(&file)?, "fd should be closed on exec");
+
+        let file = OpenOptions::new()
+            .read(true)
+            .inheritable(true)
+            .open(&path)?;
+        assert!(inherited_by_child(&file)?, "fd should survive exec");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sync_flags() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("wal.log");
+
+        let cases = [
+            (
+                OpenOptions::new()
+                    .append(true)
+                    .create(true)
+                    .sync(true)
+                    .clone(),
+                libc::O_SYNC,
+            ),
+            (
+                OpenOptions::new().append(true).data_sync(true).clone(),
+                libc::O_DSYNC,
+            ),
+        ];
+        for (options, flag) in cases {
+            let mut file = options.open(&path)?;
+            let flags = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_GETFL) };
+            assert_eq!(
+                flags & flag,
+                flag,
+                "{:?} should set flag {:#o}",
+                options,
+                flag
+            );
+            file.write_all(b"record\n")?;
+        }
+
+        assert_eq!(std::fs::read(&path)?, b"record\nrecord\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fifo_nonblocking() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("fifo");
+        mkfifo(&path, 0o600)?;
+        assert!(crate::metadata(&path)?.file_type().is_fifo());
+
+        let result = mkfifo(&path, 0o600);
+        assert!(result.is_err(), "FIFO already exists");
+        if let Err(e) = result {
+            assert_eq!(e.kind(), io::ErrorKind::AlreadyExists);
+        }
+
+        // 没有读端时非阻塞打开写端返回ENXIO
+        let result = OpenOptions::new().write(true).nonblocking(true).open(&path);
+        assert!(result.is_err(), "Writer without reader should fail");
+        if let Err(e) = result {
+            assert_eq!(e.raw_os_error(), Some(libc::ENXIO), "Error should be ENXIO");
+        }
+
+        // 读端总是立即打开，之后写端也能打开
+        let mut reader = OpenOptions::new()
+            .read(true)
+            .nonblocking(true)
+            .open(&path)?;
+        let mut writer = OpenOptions::new()
+            .write(true)
+            .nonblocking(true)
+            .open(&path)?;
+
+        let mut buf = [0u8; 16];
+        let result = reader.read(&mut buf);
+        assert!(result.is_err(), "Empty FIFO should not block");
+        if let Err(e) = result {
+            assert_eq!(e.kind(), io::ErrorKind::WouldBlock);
+        }
+
+        writer.write_all(b"Hello, fifo!")?;
+        let n = reader.read(&mut buf)?;
+        assert_eq!(&buf[..n], b"Hello, fifo!");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_nofollow() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let target = dir.path().join("target.txt");
+        let link = dir.path().join("link");
+        std::fs::write(&target, b"content")?;
+        std::os::unix::fs::symlink(&target, &link)?;
+
+        OpenOptions::new().read(true).nofollow(true).open(&target)?;
+
+        let result = OpenOptions::new().read(true).nofollow(true).open(&link);
+        assert!(result.is_err(), "Opening symlink with nofollow should fail");
+        if let Err(e) = result {
+            assert_eq!(e.raw_os_error(), Some(libc::ELOOP), "Error should be ELOOP");
+        }
+
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_path_only_symlink() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink("missing-target", &link)?;
+
+        // O_PATH | O_NOFOLLOW打开符号链接本身，即使它指向的文件不存在
+        let mut file = OpenOptions::new()
+            .path_only(true)
+            .nofollow(true)
+            .open(&link)?;
+        assert!(
+            file.metadata()?.is_symlink(),
+            "Handle should refer to the link"
+        );
+
+        let mut buf = [0u8; 8];
+        let err = file.read(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_noatime() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("data.txt");
+        std::fs::write(&path, b"content")?;
+
+        // 自己的文件可以使用O_NOATIME
+        OpenOptions::new().read(true).noatime(true).open(&path)?;
+
+        // 不属于自己的文件返回EPERM，root拥有CAP_FOWNER，跳过
+        if unsafe { libc::geteuid() } == 0 {
+            return Ok(());
+        }
+        let result = OpenOptions::new()
+            .read(true)
+            .noatime(true)
+            .open("/etc/passwd");
+        assert!(result.is_err(), "O_NOATIME on foreign file should fail");
+        if let Err(e) = result {
+            assert_eq!(e.raw_os_error(), Some(libc::EPERM), "Error should be EPERM");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncate() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("data.txt");
+        std::fs::write(&path, b"old content")?;
+
+        OpenOptions::new().write(true).truncate(true).open(&path)?;
+        assert_eq!(std::fs::read(&path)?, b"");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_combinations() {
+        let cases = [
+            OpenOptions::new().clone(),
+            OpenOptions::new().read(true).create(true).clone(),
+            OpenOptions::new().read(true).truncate(true).clone(),
+            OpenOptions::new().append(true).truncate(true).clone(),
+        ];
+
+        for options in cases {
+            let result = options.open("unused.txt");
+            assert!(result.is_err(), "{:?} should fail", options);
+            if let Err(e) = result {
+                assert_eq!(
+                    e.kind(),
+                    io::ErrorKind::InvalidInput,
+                    "Error should be InvalidInput"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_open_beneath() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path();
+        std::fs::create_dir_all(root.join("a/b"))?;
+        std::fs::write(root.join("a/file.txt"), b"content")?;
+        std::fs::write(temp_dir.path().join("secret"), b"secret")?;
+        std::os::unix::fs::symlink("file.txt", root.join("a/link"))?;
+        std::os::unix::fs::symlink("b", root.join("a/dirlink"))?;
+
+        let sandbox = File::open(root.join("a"), OpenMode::Read)?;
+        let mut read = OpenOptions::new();
+        read.read(true);
+
+        // openat2与手动解析的结果必须一致
+        for by_components in [false, true] {
+            let open = |path: &Path| {
+                if by_components {
+                    read.open_beneath_by_components(&sandbox, path)
+                } else {
+                    read.open_beneath(&sandbox, path)
+                }
+            };
+            let mut content = String::new();
+            open(Path::new("file.txt"))?.read_to_string(&mut content)?;
+            assert_eq!(content, "content");
+            open(Path::new("b/../file.txt"))?;
+            open(Path::new("./b/.."))?;
+
+            for (path, errno) in [
+                ("../secret", libc::EXDEV),
+                ("b/../../secret", libc::EXDEV),
+                ("/etc/passwd", libc::EXDEV),
+                ("link", libc::ELOOP),
+                ("dirlink/../file.txt", libc::ELOOP),
+                ("missing/file.txt", libc::ENOENT),
+            ] {
+                let result = open(Path::new(path));
+                assert!(result.is_err(), "{} should be rejected", path);
+                if let Err(e) = result {
+                    assert_eq!(e.raw_os_error(), Some(errno), "Wrong errno for {}", path);
+                }
+            }
+        }
+
+        // 通过Dir使用，可以在目录下创建文件
+        let dir = crate::Dir::open(root.join("a"))?;
+        let file = dir.open_beneath(
+            "b/new.txt",
+            OpenOptions::new().write(true).create_new(true).mode(0o600),
+        )?;
+        assert_eq!(file.path(), Some(root.join("a/b/new.txt").as_path()));
+        assert_eq!(
+            std::fs::metadata(root.join("a/b/new.txt"))?
+                .permissions()
+                .mode()
+                & 0o777,
+            0o600
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,222 @@
+/*
+    匿名管道，常用于父子进程之间、线程之间传递字节流
+
+    pipe2(fds: *mut [i32; 2], flags: i32) -> i32 (Linux)
+    fds[0]为读端，fds[1]为写端；带上O_CLOEXEC使两端不会被exec出的子进程意外继承
+    没有pipe2的平台(macOS)先pipe再通过fcntl设置FD_CLOEXEC，两步之间并发fork的子进程仍可能继承
+
+    写端全部关闭后读端read返回0(EOF)；读端全部关闭后写入返回BrokenPipe(EPIPE)
+*/
+
+use std::io::{self, IoSlice, IoSliceMut, Read, Write};
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd, RawFd};
+
+use crate::{File, Result, error};
+
+/// 管道的读端
+#[derive(Debug)]
+pub struct PipeReader {
+    file: File,
+}
+
+/// 管道的写端
+#[derive(Debug)]
+pub struct PipeWriter {
+    file: File,
+}
+
+/// 创建一对管道，返回(读端, 写端)
+pub fn pipe() -> Result<(PipeReader, PipeWriter)> {
+    let mut fds = [0i32; 2];
+
+    #[cfg(target_os = "linux")]
+    let (result, op) = (
+        unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) },
+        "pipe2",
+    );
+    #[cfg(not(target_os = "linux"))]
+    let (result, op) = (unsafe { libc::pipe(fds.as_mut_ptr()) }, "pipe");
+    if result < 0 {
+        return Err(error::with_context(io::Error::last_os_error(), op, None));
+    }
+
+    // 先包装为File，设置FD_CLOEXEC失败时两端也会被关闭
+    let (reader, writer) = (File::from_fd(fds[0]), File::from_fd(fds[1]));
+    #[cfg(not(target_os = "linux"))]
+    for file in [&reader, &writer] {
+        if unsafe { libc::fcntl(file.fd, libc::F_SETFD, libc::FD_CLOEXEC) } < 0 {
+            return Err(file.os_error("fcntl"));
+        }
+    }
+
+    Ok((PipeReader { file: reader }, PipeWriter { file: writer }))
+}
+
+impl PipeReader {
+    pub fn try_clone(&self) -> Result<PipeReader> {
+        Ok(PipeReader {
+            file: self.file.try_clone()?,
+        })
+    }
+
+    /// 非阻塞模式下管道为空时read返回WouldBlock而不是等待
+    pub fn set_nonblocking(&mut self, nonblocking: bool) -> Result<()> {
+        self.file.set_nonblocking(nonblocking)
+    }
+
+    /// 转换为File，例如用于splice_from
+    pub fn into_file(self) -> File {
+        self.file
+    }
+}
+
+impl PipeWriter {
+    pub fn try_clone(&self) -> Result<PipeWriter> {
+        Ok(PipeWriter {
+            file: self.file.try_clone()?,
+        })
+    }
+
+    /// 非阻塞模式下管道已满时write返回WouldBlock而不是等待
+    pub fn set_nonblocking(&mut self, nonblocking: bool) -> Result<()> {
+        self.file.set_nonblocking(nonblocking)
+    }
+
+    /// 转换为File，例如用于splice_to
+    pub fn into_file(self) -> File {
+        self.file
+    }
+}
+
+impl Read for PipeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Ok(self.file.read(buf)?)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+        Ok(self.file.read_vectored(bufs)?)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        Ok(self.file.read_exact(buf)?)
+    }
+}
+
+impl Write for PipeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(self.file.write(buf)?)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        Ok(self.file.write_vectored(bufs)?)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        Ok(self.file.write_all(buf)?)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl AsRawFd for PipeReader {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+impl AsRawFd for PipeWriter {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+impl AsFd for PipeReader {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+}
+
+impl AsFd for PipeWriter {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+}
+
+impl From<PipeReader> for OwnedFd {
+    fn from(reader: PipeReader) -> OwnedFd {
+        OwnedFd::from(reader.file)
+    }
+}
+
+impl From<PipeWriter> for OwnedFd {
+    fn from(writer: PipeWriter) -> OwnedFd {
+        OwnedFd::from(writer.file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::pipe;
+    use std::io::{self, Read, Write};
+    use std::os::fd::AsRawFd;
+
+    #[test]
+    fn test_pipe() -> io::Result<()> {
+        let (mut reader, mut writer) = pipe()?;
+
+        for fd in [reader.as_raw_fd(), writer.as_raw_fd()] {
+            let flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+            assert!(
+                flags & libc::FD_CLOEXEC != 0,
+                "Pipe should be close-on-exec"
+            );
+        }
+
+        // 写入的数据在另一个线程中读出
+        let handle = std::thread::spawn(move || -> io::Result<String> {
+            let mut content = String::new();
+            reader.read_to_string(&mut content)?;
+            Ok(content)
+        });
+        writer.write_all(b"Hello, ")?;
+        writer.try_clone()?.write_all(b"pipe!")?;
+        drop(writer); // 写端全部关闭后读端得到EOF
+
+        assert_eq!(handle.join().unwrap()?, "Hello, pipe!");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pipe_nonblocking_and_broken() -> io::Result<()> {
+        let (mut reader, mut writer) = pipe()?;
+
+        reader.set_nonblocking(true)?;
+        let mut buf = [0u8; 8];
+        let result = reader.read(&mut buf);
+        assert!(result.is_err(), "Empty pipe should not block");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::WouldBlock,
+                "Error should be WouldBlock"
+            );
+        }
+
+        // Rust程序默认忽略SIGPIPE，读端关闭后写入返回EPIPE
+        drop(reader);
+        let result = writer.write(b"x");
+        assert!(result.is_err(), "Writing to closed pipe should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::BrokenPipe,
+                "Error should be BrokenPipe"
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+/*
+    文件空间管理
+
+    fallocate(fd: i32, mode: i32, offset: off_t, len: off_t) -> i32 (Linux)
+    为文件的[offset, offset + len)预先分配磁盘块，磁盘空间不足时立即以ENOSPC失败，
+    而不是在写到一半时才发现；分配的部分读出来是0，文件长度不足时会被扩展
+
+    稀疏文件中从未写入过的区域称为空洞(hole)，不占磁盘空间，读出来是0
+    lseek的SEEK_DATA/SEEK_HOLE可以跳过空洞，只处理有数据的区域
+
+    这些都是Linux特有的接口
+*/
+
+use std::io;
+
+use crate::{File, Result, sys, to_off_t};
+
+impl File {
+    /*
+        预分配[offset, offset + len)的磁盘空间
+        文件系统不支持fallocate(EOPNOTSUPP)时退回posix_fallocate，
+        后者由glibc逐块写入0来模拟，速度较慢但结果相同
+    */
+    pub fn allocate(&mut self, offset: u64, len: u64) -> Result<()> {
+        self.ensure_writable()?;
+        let (offset, len) = (to_off_t(offset)?, to_off_t(len)?);
+
+        let result = unsafe { sys::fallocate(self.fd, 0, offset, len) };
+        if result == 0 {
+            return Ok(());
+        }
+        let err = self.record_os_error("fallocate");
+        if err.raw_os_error() != Some(libc::EOPNOTSUPP) {
+            return Err(err);
+        }
+
+        // posix_fallocate不设置errno，而是直接返回错误码
+        let result = unsafe { sys::posix_fallocate(self.fd, offset, len) };
+        if result != 0 {
+            self.last_errno = Some(result);
+            let err = io::Error::from_raw_os_error(result);
+            return Err(self.context_error(err, "posix_fallocate"));
+        }
+
+        Ok(())
+    }
+
+    /*
+        在[offset, offset + len)上打洞，释放对应的磁盘块，文件长度不变，之后读出来是0
+        fallocate(fd, FALLOC_FL_PUNCH_HOLE | FALLOC_FL_KEEP_SIZE, offset, len)
+        没有覆盖完整块的首尾部分会被填0而不是释放；不支持的文件系统返回EOPNOTSUPP
+    */
+    pub fn punch_hole(&mut self, offset: u64, len: u64) -> Result<()> {
+        self.ensure_writable()?;
+        let (offset, len) = (to_off_t(offset)?, to_off_t(len)?);
+
+        let mode = libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE;
+        let result = unsafe { sys::fallocate(self.fd, mode, offset, len) };
+        if result < 0 {
+            return Err(self.record_os_error("fallocate"));
+        }
+
+        Ok(())
+    }
+
+    /*
+        从offset开始的下一个数据区域的起点，封装lseek(SEEK_DATA)，offset之后全是空洞时为None
+        与lseek一样会把读写位置移动到返回的偏移量
+    */
+    pub fn next_data(&mut self, offset: u64) -> Result<Option<u64>> {
+        self.seek_sparse(offset, libc::SEEK_DATA)
+    }
+
+    /*
+        从offset开始的下一个空洞的起点，封装lseek(SEEK_HOLE)，offset超出文件末尾时为None
+        文件末尾被视为一个空洞，所以没有真正的空洞时返回文件长度
+    */
+    pub fn next_hole(&mut self, offset: u64) -> Result<Option<u64>> {
+        self.seek_sparse(offset, libc::SEEK_HOLE)
+    }
+
+    // 找不到时lseek返回ENXIO
+    fn seek_sparse(&mut self, offset: u64, whence: i32) -> Result<Option<u64>> {
+        let offset = to_off_t(offset)?;
+
+        match self.lseek(offset, whence) {
+            Ok(position) => Ok(Some(position)),
+            Err(e) if e.raw_os_error() == Some(libc::ENXIO) => Ok(None),
+            Err(e) => {
+                self.last_errno = e.raw_os_error();
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::temp::TempFile;
+    use crate::{File, OpenMode};
+    use std::io::{self, Read};
+    use std::os::unix::fs::MetadataExt;
+
+    #[test]
+    fn test_allocate() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let mut file = File::open(temp_file.path(), OpenMode::ReadWrite)?;
+
+        file.allocate(0, 1024 * 1024)?;
+        let meta = file.metadata()?;
+        assert_eq!(meta.len(), 1024 * 1024, "File should be extended");
+        let blocks = std::fs::metadata(temp_file.path())?.blocks();
+        assert!(blocks * 512 >= 1024 * 1024, "Blocks should be reserved");
+
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)?;
+        assert!(
+            content.iter().all(|&b| b == 0),
+            "Allocated range reads as 0"
+        );
+
+        // 预分配不会截断已有的更长的文件
+        file.allocate(0, 16)?;
+        assert_eq!(file.metadata()?.len(), 1024 * 1024);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_allocate_errors() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+
+        let mut file = File::open(temp_file.path(), OpenMode::Read)?;
+        let result = file.allocate(0, 4096);
+        assert!(result.is_err(), "Read only file should not be allocated");
+        assert_eq!(file.last_errno(), Some(libc::EBADF));
+
+        let mut file = File::open(temp_file.path(), OpenMode::Write)?;
+        let result = file.allocate(u64::MAX, 4096);
+        assert!(result.is_err(), "Offset beyond off_t should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::InvalidInput,
+                "Error should be InvalidInput"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sparse_traversal() -> io::Result<()> {
+        const MB: u64 = 1024 * 1024;
+        let temp_file = TempFile::new()?;
+        let mut file = File::open(temp_file.path(), OpenMode::ReadWrite)?;
+
+        // [0, 4K)有数据，[4K, 1M)是空洞，[1M, 1M + 4K)有数据
+        file.write_at(&[1u8; 4096], 0)?;
+        file.write_at(&[2u8; 4096], MB)?;
+
+        assert_eq!(file.next_data(0)?, Some(0));
+        assert_eq!(file.next_hole(0)?, Some(4096));
+        assert_eq!(file.next_data(4096)?, Some(MB));
+        assert_eq!(file.next_hole(MB)?, Some(MB + 4096), "EOF is a hole");
+        assert_eq!(file.next_data(MB + 4096)?, None);
+        assert_eq!(file.next_hole(MB + 4096 + 1)?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_punch_hole() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let mut file = File::open(temp_file.path(), OpenMode::ReadWrite)?;
+        file.write_all(&[7u8; 3 * 4096])?;
+
+        file.punch_hole(4096, 4096)?;
+        assert_eq!(file.metadata()?.len(), 3 * 4096, "Length should be kept");
+
+        let mut block = [0xffu8; 4096];
+        file.read_at(&mut block, 4096)?;
+        assert!(block.iter().all(|&b| b == 0), "Punched range reads as 0");
+        file.read_at(&mut block, 2 * 4096)?;
+        assert!(block.iter().all(|&b| b == 7), "Other data should be kept");
+
+        assert_eq!(file.next_hole(0)?, Some(4096));
+        assert_eq!(file.next_data(4096)?, Some(2 * 4096));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,131 @@
+/*
+    splice在文件与管道之间移动数据，数据只在内核的页缓存与管道缓冲区之间传递，不经过用户态
+    splice(fd_in: i32, off_in: *mut loff_t, fd_out: i32, off_out: *mut loff_t, len: size_t, flags: u32) -> ssize_t
+    两端中至少有一个是管道；偏移量为NULL时使用并推进文件的当前读写位置
+
+    tee在两个管道之间复制数据而不消耗输入管道中的数据，常与splice配合把一份输入分发到多个输出
+    tee(fd_in: i32, fd_out: i32, len: size_t, flags: u32) -> ssize_t
+
+    返回0表示输入端已到末尾(文件EOF或管道写端全部关闭)；单次调用最多移动一个管道缓冲区的数据(默认64KB)
+*/
+
+use crate::{File, Result, retry_eintr};
+
+impl File {
+    /// 从self的当前位置移动最多len字节到管道pipe中，返回移动的字节数
+    pub fn splice_to(&mut self, pipe: &File, len: usize) -> Result<usize> {
+        self.ensure_readable()?;
+        pipe.ensure_writable()?;
+
+        self.splice(self.fd, pipe.fd, len)
+    }
+
+    /// 从管道pipe中移动最多len字节写入到self的当前位置，返回移动的字节数
+    pub fn splice_from(&mut self, pipe: &File, len: usize) -> Result<usize> {
+        self.ensure_writable()?;
+        pipe.ensure_readable()?;
+
+        self.splice(pipe.fd, self.fd, len)
+    }
+
+    /// self为管道，把其中最多len字节复制到管道out中，self中的数据不被消耗，返回复制的字节数
+    pub fn tee_to(&mut self, out: &File, len: usize) -> Result<usize> {
+        self.ensure_readable()?;
+        out.ensure_writable()?;
+
+        let result = retry_eintr(self.retry_interrupted, || unsafe {
+            libc::tee(self.fd, out.fd, len, 0)
+        });
+        if result < 0 {
+            return Err(self.record_os_error("tee"));
+        }
+
+        Ok(result as usize)
+    }
+
+    fn splice(&mut self, fd_in: i32, fd_out: i32, len: usize) -> Result<usize> {
+        let result = retry_eintr(self.retry_interrupted, || unsafe {
+            libc::splice(
+                fd_in,
+                std::ptr::null_mut(),
+                fd_out,
+                std::ptr::null_mut(),
+                len,
+                libc::SPLICE_F_MOVE,
+            )
+        });
+        if result < 0 {
+            return Err(self.record_os_error("splice"));
+        }
+
+        Ok(result as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::temp::TempFile;
+    use crate::{File, OpenMode};
+    use std::io;
+
+    // 创建一对管道，返回(读端, 写端)
+    fn pipe() -> io::Result<(File, File)> {
+        let (reader, writer) = crate::pipe()?;
+        Ok((reader.into_file(), writer.into_file()))
+    }
+
+    #[test]
+    fn test_splice() -> io::Result<()> {
+        let src = TempFile::new()?;
+        std::fs::write(src.path(), b"Hello, splice!")?;
+        let dst = TempFile::new()?;
+
+        let (mut reader, writer) = pipe()?;
+        let mut src_file = File::open(src.path(), OpenMode::Read)?;
+        let mut dst_file = File::open(dst.path(), OpenMode::Write)?;
+
+        // 文件 -> 管道，推进文件的读写位置
+        assert_eq!(src_file.splice_to(&writer, 5)?, 5);
+        assert_eq!(src_file.stream_position()?, 5);
+        assert_eq!(src_file.splice_to(&writer, 1024)?, 9, "Should stop at EOF");
+        assert_eq!(src_file.splice_to(&writer, 1024)?, 0);
+
+        // 管道 -> 文件
+        assert_eq!(dst_file.splice_from(&reader, 1024)?, 14);
+        assert_eq!(std::fs::read(dst.path())?, b"Hello, splice!");
+
+        // 两端都不是管道时返回EINVAL
+        let result = src_file.splice_to(&dst_file, 5);
+        assert!(result.is_err(), "Splice between files should fail");
+        assert_eq!(src_file.last_errno(), Some(libc::EINVAL));
+
+        drop(writer);
+        assert_eq!(
+            dst_file.splice_from(&reader, 1024)?,
+            0,
+            "Closed pipe is EOF"
+        );
+        let mut buf = [0u8; 1];
+        assert_eq!(reader.read(&mut buf)?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tee() -> io::Result<()> {
+        let (mut in_reader, mut in_writer) = pipe()?;
+        let (mut out_reader, out_writer) = pipe()?;
+        in_writer.write_all(b"Hello, tee!")?;
+
+        assert_eq!(in_reader.tee_to(&out_writer, 1024)?, 11);
+
+        // 输出管道得到一份副本，输入管道中的数据仍然在
+        let mut buf = [0u8; 11];
+        out_reader.read_exact(&mut buf)?;
+        assert_eq!(&buf, b"Hello, tee!");
+        in_reader.read_exact(&mut buf)?;
+        assert_eq!(&buf, b"Hello, tee!");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,238 @@
+/*
+    statx是Linux 4.11加入的扩展版stat，除了stat的全部字段外还能拿到创建时间(btime)、挂载点id、文件属性标志
+
+    statx(dirfd: i32, path: *const c_char, flags: i32, mask: u32, buf: *mut statx) -> i32
+    path为空字符串并带上AT_EMPTY_PATH时获取dirfd本身的信息，等价于fstat
+    mask表示需要哪些字段，返回后stx_mask表示实际填充了哪些，文件系统不支持的字段(例如btime)不会被置位
+
+    内核或libc太旧时返回ENOSYS，seccomp禁止了statx时返回ENOSYS或EPERM(取决于过滤规则，例如旧版Docker的默认配置为EPERM)
+    这两种情况都退回fstat/stat，扩展字段都为None；statx本身不会因为文件权限返回EPERM(没有权限时为EACCES)
+*/
+
+// statx各字段的类型与stat不同，并且stat的字段类型随架构变化，这里统一转换
+#![allow(clippy::unnecessary_cast)]
+
+use std::ffi::CStr;
+use std::io;
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::metadata::system_time;
+use crate::{File, Metadata, Result, error, sys, to_c_path};
+
+const STATX_MASK: u32 =
+    libc::STATX_BASIC_STATS | libc::STATX_BTIME | libc::STATX_MNT_ID | libc::STATX_DIOALIGN;
+
+/// statx获取的扩展元信息，文件系统或内核不支持的字段为None
+#[derive(Clone, Copy, Debug)]
+pub struct ExtendedMetadata {
+    metadata: Metadata,
+    created: Option<SystemTime>,
+    mount_id: Option<u64>,
+    dio_mem_align: Option<u32>,
+    attributes: u64,
+    attributes_mask: u64,
+}
+
+impl ExtendedMetadata {
+    // 把statx转换成stat，使基础字段可以沿用Metadata的接口
+    fn from_statx(stx: &libc::statx) -> ExtendedMetadata {
+        let mut stat: sys::stat = unsafe { std::mem::zeroed() };
+        stat.st_dev = libc::makedev(stx.stx_dev_major, stx.stx_dev_minor) as _;
+        stat.st_ino = stx.stx_ino as _;
+        stat.st_nlink = stx.stx_nlink as _;
+        stat.st_mode = stx.stx_mode as _;
+        stat.st_uid = stx.stx_uid;
+        stat.st_gid = stx.stx_gid;
+        stat.st_rdev = libc::makedev(stx.stx_rdev_major, stx.stx_rdev_minor) as _;
+        stat.st_size = stx.stx_size as _;
+        stat.st_blksize = stx.stx_blksize as _;
+        stat.st_blocks = stx.stx_blocks as _;
+        stat.st_atime = stx.stx_atime.tv_sec as _;
+        stat.st_atime_nsec = stx.stx_atime.tv_nsec as _;
+        stat.st_mtime = stx.stx_mtime.tv_sec as _;
+        stat.st_mtime_nsec = stx.stx_mtime.tv_nsec as _;
+        stat.st_ctime = stx.stx_ctime.tv_sec as _;
+        stat.st_ctime_nsec = stx.stx_ctime.tv_nsec as _;
+
+        let created = (stx.stx_mask & libc::STATX_BTIME != 0)
+            .then(|| system_time(stx.stx_btime.tv_sec, stx.stx_btime.tv_nsec as i64));
+        let mount_id = (stx.stx_mask & libc::STATX_MNT_ID != 0).then_some(stx.stx_mnt_id);
+        // 文件不支持O_DIRECT时对齐值为0
+        let dio_mem_align = (stx.stx_mask & libc::STATX_DIOALIGN != 0
+            && stx.stx_dio_mem_align != 0)
+            .then_some(stx.stx_dio_mem_align);
+
+        ExtendedMetadata {
+            metadata: Metadata::from_stat(stat),
+            created,
+            mount_id,
+            dio_mem_align,
+            attributes: stx.stx_attributes,
+            attributes_mask: stx.stx_attributes_mask,
+        }
+    }
+
+    // statx不可用时只有基础字段
+    fn from_metadata(metadata: Metadata) -> ExtendedMetadata {
+        ExtendedMetadata {
+            metadata,
+            created: None,
+            mount_id: None,
+            dio_mem_align: None,
+            attributes: 0,
+            attributes_mask: 0,
+        }
+    }
+
+    /// 与File::metadata()相同的基础元信息
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// 创建时间(btime)，ext4/xfs/btrfs等支持，tmpfs等不支持时为None
+    pub fn created(&self) -> Option<SystemTime> {
+        self.created
+    }
+
+    /// 挂载点id，与/proc/self/mountinfo的第一列对应，需要Linux 5.8
+    pub fn mount_id(&self) -> Option<u64> {
+        self.mount_id
+    }
+
+    /// O_DIRECT要求的用户缓冲区地址对齐，需要Linux 6.1，文件不支持O_DIRECT时为None
+    pub fn dio_mem_align(&self) -> Option<u32> {
+        self.dio_mem_align
+    }
+
+    /// STATX_ATTR_*标志位，只有attributes_mask中置位的标志才有意义
+    pub fn attributes(&self) -> u64 {
+        self.attributes
+    }
+
+    /// 文件系统支持的属性标志
+    pub fn attributes_mask(&self) -> u64 {
+        self.attributes_mask
+    }
+
+    /// chattr +i，不能修改、删除、重命名
+    pub fn is_immutable(&self) -> bool {
+        self.attributes & libc::STATX_ATTR_IMMUTABLE as u64 != 0
+    }
+
+    /// chattr +a，只能追加写入
+    pub fn is_append_only(&self) -> bool {
+        self.attributes & libc::STATX_ATTR_APPEND as u64 != 0
+    }
+}
+
+// 返回None表示statx不可用，调用方需要退回stat
+fn statx(dirfd: i32, path: &CStr, flags: i32) -> io::Result<Option<ExtendedMetadata>> {
+    let mut stx: libc::statx = unsafe { std::mem::zeroed() };
+    let result = unsafe { libc::statx(dirfd, path.as_ptr(), flags, STATX_MASK, &mut stx) };
+    if result < 0 {
+        let err = io::Error::last_os_error();
+        if matches!(err.raw_os_error(), Some(libc::ENOSYS | libc::EPERM)) {
+            return Ok(None);
+        }
+        return Err(err);
+    }
+
+    Ok(Some(ExtendedMetadata::from_statx(&stx)))
+}
+
+impl File {
+    /// 通过statx获取已打开文件的扩展元信息
+    pub fn extended_metadata(&self) -> Result<ExtendedMetadata> {
+        self.ensure_open()?;
+
+        let result = statx(self.fd, c"", libc::AT_EMPTY_PATH);
+        match result.map_err(|e| self.context_error(e, "statx"))? {
+            Some(meta) => Ok(meta),
+            None => Ok(ExtendedMetadata::from_metadata(self.metadata()?)),
+        }
+    }
+}
+
+/// 获取路径的扩展元信息，符号链接会被跟随
+pub fn extended_metadata<P: AsRef<Path>>(path: P) -> Result<ExtendedMetadata> {
+    let c_style_str_path = to_c_path(path.as_ref())?;
+
+    let result = statx(libc::AT_FDCWD, &c_style_str_path, 0);
+    match result.map_err(|e| error::with_context(e, "statx", Some(path.as_ref())))? {
+        Some(meta) => Ok(meta),
+        None => Ok(ExtendedMetadata::from_metadata(crate::metadata(path)?)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ExtendedMetadata, extended_metadata};
+    use crate::temp::TempFile;
+    use crate::{File, OpenMode, metadata};
+    use std::io;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_extended_metadata() -> io::Result<()> {
+        let mut temp_file = TempFile::new()?;
+        temp_file.file_mut().write_all(b"Hello, world!")?;
+
+        let file = File::open(temp_file.path(), OpenMode::Read)?;
+        let meta = file.extended_metadata()?;
+        let expected = file.metadata()?;
+
+        // 基础字段应与fstat一致
+        assert_eq!(meta.metadata().len(), 13);
+        assert_eq!(meta.metadata().mode(), expected.mode());
+        assert_eq!(
+            meta.metadata().ino(),
+            expected.ino(),
+            "Inode should match fstat"
+        );
+        assert_eq!(
+            meta.metadata().dev(),
+            expected.dev(),
+            "Device should match fstat"
+        );
+        assert_eq!(meta.metadata().modified(), expected.modified());
+        assert_eq!(meta.metadata().changed(), expected.changed());
+
+        // tmpfs等文件系统没有btime
+        if let Some(created) = meta.created() {
+            let age = SystemTime::now()
+                .duration_since(created)
+                .unwrap_or_default();
+            assert!(age < Duration::from_secs(60), "btime should be recent");
+        }
+        assert!(!meta.is_immutable() && !meta.is_append_only());
+
+        let by_path = extended_metadata(temp_file.path())?;
+        assert_eq!(by_path.metadata().ino(), expected.ino());
+        assert_eq!(by_path.created(), meta.created());
+        assert_eq!(by_path.mount_id(), meta.mount_id());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extended_metadata_fallback() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let meta = ExtendedMetadata::from_metadata(metadata(temp_file.path())?);
+        assert_eq!(meta.created(), None);
+        assert_eq!(meta.mount_id(), None);
+        assert_eq!(meta.attributes(), 0);
+
+        let result = extended_metadata(temp_file.path().join("missing"));
+        assert!(result.is_err(), "Missing path should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::NotADirectory,
+                "Error should be NotADirectory"
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+/*
+    标准输入输出(fd 0/1/2)的包装，与普通文件使用同一套File接口
+    这些fd属于整个进程，不由这里打开，所以Drop时不能关闭，内部的File放在ManuallyDrop中
+
+    不带缓冲，每次write都是一次系统调用，大量小块输出时应当配合BufWriter使用
+    通过Deref可以调用File的&self方法，例如is_terminal、metadata；
+    不提供DerefMut，避免通过*stdout = file把fd 1替换掉并关闭
+*/
+
+use std::io::{self, IoSlice, IoSliceMut, Read, Write};
+use std::mem::ManuallyDrop;
+use std::ops::Deref;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, RawFd};
+
+use crate::File;
+
+/// 标准输入，fd 0
+#[derive(Debug)]
+pub struct Stdin {
+    file: ManuallyDrop<File>,
+}
+
+/// 标准输出，fd 1
+#[derive(Debug)]
+pub struct Stdout {
+    file: ManuallyDrop<File>,
+}
+
+/// 标准错误，fd 2
+#[derive(Debug)]
+pub struct Stderr {
+    file: ManuallyDrop<File>,
+}
+
+pub fn stdin() -> Stdin {
+    Stdin {
+        file: ManuallyDrop::new(File::from_fd(libc::STDIN_FILENO)),
+    }
+}
+
+pub fn stdout() -> Stdout {
+    Stdout {
+        file: ManuallyDrop::new(File::from_fd(libc::STDOUT_FILENO)),
+    }
+}
+
+pub fn stderr() -> Stderr {
+    Stderr {
+        file: ManuallyDrop::new(File::from_fd(libc::STDERR_FILENO)),
+    }
+}
+
+impl Read for Stdin {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Ok(self.file.read(buf)?)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+        Ok(self.file.read_vectored(bufs)?)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        Ok(self.file.read_exact(buf)?)
+    }
+}
+
+impl Write for Stdout {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(self.file.write(buf)?)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        Ok(self.file.write_vectored(bufs)?)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        Ok(self.file.write_all(buf)?)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Write for Stderr {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(self.file.write(buf)?)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        Ok(self.file.write_vectored(bufs)?)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        Ok(self.file.write_all(buf)?)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Deref for Stdin {
+    type Target = File;
+
+    fn deref(&self) -> &File {
+        &self.file
+    }
+}
+
+impl Deref for Stdout {
+    type Target = File;
+
+    fn deref(&self) -> &File {
+        &self.file
+    }
+}
+
+impl Deref for Stderr {
+    type Target = File;
+
+    fn deref(&self) -> &File {
+        &self.file
+    }
+}
+
+impl AsRawFd for Stdin {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+impl AsRawFd for Stdout {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+impl AsRawFd for Stderr {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+impl AsFd for Stdin {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+}
+
+impl AsFd for Stdout {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+}
+
+impl AsFd for Stderr {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{stderr, stdin, stdout};
+    use std::io::{self, Write};
+    use std::os::fd::AsRawFd;
+
+    #[test]
+    fn test_stdio_not_closed() -> io::Result<()> {
+        assert_eq!(stdin().as_raw_fd(), 0);
+        assert_eq!(stdout().as_raw_fd(), 1);
+        assert_eq!(stderr().as_raw_fd(), 2);
+
+        // 离开作用域之后fd仍然有效
+        {
+            let _err = stderr();
+        }
+        assert!(
+            unsafe { libc::fcntl(2, libc::F_GETFD) } >= 0,
+            "fd 2 should stay open"
+        );
+
+        let mut err = stderr();
+        err.write_all(b"")?;
+        let _ = err.is_terminal();
+        let _ = stdout().metadata()?;
+
+        Ok(())
+    }
+}
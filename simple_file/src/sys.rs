@@ -0,0 +1,43 @@
+/*
+    大文件支持
+
+    32位平台上off_t默认只有32位，超过2GB的偏移量和文件大小会溢出
+    Linux下统一使用*64版本的函数和off64_t，打开时带上O_LARGEFILE，64位平台上二者等价，没有额外开销
+    其余平台(例如macOS)的off_t本身就是64位，直接使用原版函数
+*/
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) use libc::{
+    fstat, fstatat, fstatvfs, ftruncate, lseek, lstat, off_t, pread, preadv, pwrite, pwritev,
+    readdir, stat, statvfs,
+};
+#[cfg(target_os = "linux")]
+pub(crate) use libc::{
+    fstat64 as fstat, fstatat64 as fstatat, fstatvfs64 as fstatvfs, ftruncate64 as ftruncate,
+    lseek64 as lseek, lstat64 as lstat, off64_t as off_t, pread64 as pread, preadv64 as preadv,
+    pwrite64 as pwrite, pwritev64 as pwritev, readdir64 as readdir, stat64 as stat,
+    statvfs64 as statvfs,
+};
+// 只有Linux提供的接口
+#[cfg(target_os = "linux")]
+pub(crate) use libc::{
+    fallocate64 as fallocate, posix_fadvise64 as posix_fadvise,
+    posix_fallocate64 as posix_fallocate,
+};
+
+#[cfg(target_os = "linux")]
+pub(crate) const O_LARGEFILE: i32 = libc::O_LARGEFILE;
+#[cfg(not(target_os = "linux"))]
+pub(crate) const O_LARGEFILE: i32 = 0;
+
+// 把errno清零，readdir这类以NULL同时表示"结束"和"出错"的函数，需要调用前清零、调用后检查
+pub(crate) fn clear_errno() {
+    #[cfg(target_os = "linux")]
+    unsafe {
+        *libc::__errno_location() = 0;
+    }
+    #[cfg(not(target_os = "linux"))]
+    unsafe {
+        *libc::__error() = 0;
+    }
+}
@@ -0,0 +1,502 @@
+/*
+    临时文件和临时目录，常见的"先写临时文件，成功后再放到目标位置"流程
+
+    mkstemp(template: *mut c_char) -> i32
+    template以XXXXXX结尾，mkstemp把它替换为唯一的文件名，以O_RDWR | O_CREAT | O_EXCL打开，权限0600
+    Linux下使用mkostemp额外带上O_CLOEXEC，其余平台创建后再通过fcntl设置FD_CLOEXEC
+    mkstemp不能指定权限，需要其他权限时(new_in_with_mode)自己生成随机名字，以O_CREAT | O_EXCL打开
+
+    mkdtemp(template: *mut c_char) -> *mut c_char
+    与mkstemp相同的方式生成唯一的目录名，创建权限为0700的目录，返回template本身，失败返回NULL
+
+    Linux下还可以用O_TMPFILE创建一个没有名字的文件(File::tmpfile)，写完后再用linkat给它一个名字
+    崩溃时没有残留的临时文件，也不需要生成临时文件名
+*/
+
+use std::collections::hash_map::RandomState;
+use std::ffi::OsString;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::os::unix::ffi::OsStringExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{File, INVALID_FD, Result, error, to_c_path};
+
+pub struct TempFile {
+    file: File,
+    path: PathBuf,
+    persisted: bool, // 已经persist的文件不能在Drop时删除
+}
+
+impl TempFile {
+    /// 在系统临时目录(TMPDIR，默认/tmp)下创建临时文件
+    pub fn new() -> Result<TempFile> {
+        TempFile::new_in(std::env::temp_dir())
+    }
+
+    /// 在dir目录下创建一个唯一命名的临时文件，Drop时自动删除
+    pub fn new_in<P: AsRef<Path>>(dir: P) -> Result<TempFile> {
+        let mut template = template_in(dir.as_ref())?;
+
+        #[cfg(target_os = "linux")]
+        let fd =
+            unsafe { libc::mkostemp(template.as_mut_ptr() as *mut libc::c_char, libc::O_CLOEXEC) };
+        #[cfg(not(target_os = "linux"))]
+        let fd = unsafe { libc::mkstemp(template.as_mut_ptr() as *mut libc::c_char) };
+        if fd == INVALID_FD {
+            return Err(error::with_context(
+                io::Error::last_os_error(),
+                "mkstemp",
+                Some(dir.as_ref()),
+            ));
+        }
+        // 立即交给File管理，之后的步骤不会泄漏fd
+        let mut file = File::from_fd(fd);
+        #[cfg(not(target_os = "linux"))]
+        unsafe {
+            libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC);
+        }
+
+        let path = template_to_path(template);
+        file.path = Some(path.clone());
+
+        Ok(TempFile {
+            file,
+            path,
+            persisted: false,
+        })
+    }
+
+    /*
+        与new_in相同，但以mode创建，权限经过umask过滤，而不是mkstemp固定的0600
+        mkstemp不能指定权限，这里自己生成随机的名字，以O_CREAT | O_EXCL打开，名字已存在时换一个重试
+    */
+    pub fn new_in_with_mode<P: AsRef<Path>>(dir: P, mode: u32) -> Result<TempFile> {
+        let dir = dir.as_ref();
+
+        for _ in 0..MAX_ATTEMPTS {
+            let path = dir.join(format!(".tmp{}", random_suffix()));
+            match File::options()
+                .read(true)
+                .write(true)
+                .create_new(true)
+                .mode(mode)
+                .open(&path)
+            {
+                Ok(file) => {
+                    return Ok(TempFile {
+                        file,
+                        path,
+                        persisted: false,
+                    });
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(error::with_context(
+            io::Error::from_raw_os_error(libc::EEXIST),
+            "open",
+            Some(dir),
+        ))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+
+    pub fn file_mut(&mut self) -> &mut File {
+        &mut self.file
+    }
+
+    /// 保留临时文件，不再自动删除，返回内部的File和文件路径
+    pub fn keep(mut self) -> (File, PathBuf) {
+        self.persisted = true;
+        let file = std::mem::replace(&mut self.file, File::from_fd(INVALID_FD));
+        (file, std::mem::take(&mut self.path))
+    }
+
+    /*
+        把临时文件rename到path，返回内部的File，之后不再自动删除
+        rename在同一文件系统内是原子的，所以临时文件应当和目标位于同一个文件系统
+        rename失败时临时文件随TempFile一起被删除
+    */
+    pub fn persist<Q: AsRef<Path>>(mut self, path: Q) -> Result<File> {
+        let from = to_c_path(&self.path)?;
+        let to = to_c_path(path.as_ref())?;
+
+        let result = unsafe { libc::rename(from.as_ptr(), to.as_ptr()) };
+        if result < 0 {
+            return Err(error::with_context(
+                io::Error::last_os_error(),
+                "rename",
+                Some(&self.path),
+            ));
+        }
+
+        self.persisted = true;
+        self.file.path = Some(path.as_ref().to_path_buf());
+        Ok(std::mem::replace(&mut self.file, File::from_fd(INVALID_FD)))
+    }
+
+    /*
+        与persist相同，但保证返回时文件内容和新名字都已经写入磁盘
+        step1: fsync临时文件，rename之前内容必须已经落盘，否则崩溃后可能看到新名字对应的空文件
+        step2: rename到path
+        step3: fsync path所在的目录，rename本身修改的是目录，不fsync目录时崩溃后可能仍是旧文件
+    */
+    pub fn persist_sync<Q: AsRef<Path>>(self, path: Q) -> Result<File> {
+        self.file.sync_all()?;
+        let file = self.persist(path.as_ref())?;
+        crate::dir::sync_parent(path.as_ref())?;
+
+        Ok(file)
+    }
+}
+
+/// 临时目录，Drop时连同其中的内容一起删除
+pub struct TempDir {
+    path: PathBuf,
+    kept: bool,
+}
+
+impl TempDir {
+    /// 在系统临时目录(TMPDIR，默认/tmp)下创建临时目录
+    pub fn new() -> Result<TempDir> {
+        TempDir::new_in(std::env::temp_dir())
+    }
+
+    /// 在dir目录下创建一个唯一命名、权限为0700的临时目录
+    pub fn new_in<P: AsRef<Path>>(dir: P) -> Result<TempDir> {
+        let mut template = template_in(dir.as_ref())?;
+
+        let result = unsafe { libc::mkdtemp(template.as_mut_ptr() as *mut libc::c_char) };
+        if result.is_null() {
+            return Err(error::with_context(
+                io::Error::last_os_error(),
+                "mkdtemp",
+                Some(dir.as_ref()),
+            ));
+        }
+
+        Ok(TempDir {
+            path: template_to_path(template),
+            kept: false,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 保留临时目录，不再自动删除，返回目录路径
+    pub fn keep(mut self) -> PathBuf {
+        self.kept = true;
+        std::mem::take(&mut self.path)
+    }
+
+    /// 立即删除临时目录，与Drop不同，删除失败时返回错误
+    pub fn close(mut self) -> Result<()> {
+        self.kept = true;
+        crate::remove_dir_all(&self.path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        if !self.kept {
+            let _ = crate::remove_dir_all(&self.path);
+        }
+    }
+}
+
+// new_in_with_mode生成名字的尝试次数，与glibc的mkstemp相同
+const MAX_ATTEMPTS: usize = 62 * 62 * 62;
+
+// 6个字母或数字，与mkstemp生成的名字形式相同
+fn random_suffix() -> String {
+    const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    // RandomState每次创建时使用不同的随机种子，再混入pid、计数器和时间
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(std::process::id());
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    if let Ok(now) = SystemTime::now().duration_since(UNIX_EPOCH) {
+        hasher.write_u128(now.as_nanos());
+    }
+    let mut bits = hasher.finish();
+
+    (0..6)
+        .map(|_| {
+            let c = CHARS[(bits % CHARS.len() as u64) as usize];
+            bits /= CHARS.len() as u64;
+            c as char
+        })
+        .collect()
+}
+
+// dir/.tmpXXXXXX，带结尾空字符，交给mkstemp/mkdtemp原地替换
+fn template_in(dir: &Path) -> Result<Vec<u8>> {
+    Ok(to_c_path(&dir.join(".tmpXXXXXX"))?.into_bytes_with_nul())
+}
+
+// mkstemp/mkdtemp只替换XXXXXX部分，其余字节来自dir，不要求是UTF-8
+fn template_to_path(mut template: Vec<u8>) -> PathBuf {
+    template.pop(); // 去掉结尾的空字符
+    PathBuf::from(OsString::from_vec(template))
+}
+
+impl File {
+    /*
+        在dir所在的文件系统上创建一个匿名文件，封装open(dir, O_TMPFILE | O_RDWR, 0o600)
+        文件在目录中不可见，File关闭后自动释放；不支持O_TMPFILE的文件系统返回EOPNOTSUPP
+    */
+    #[cfg(target_os = "linux")]
+    pub fn tmpfile<P: AsRef<Path>>(dir: P) -> Result<File> {
+        let c_style_str_path = to_c_path(dir.as_ref())?;
+        let flags = libc::O_TMPFILE | libc::O_RDWR | libc::O_CLOEXEC | crate::sys::O_LARGEFILE;
+
+        let fd = crate::retry_eintr(true, || unsafe {
+            libc::open(c_style_str_path.as_ptr(), flags, 0o600 as libc::c_uint)
+        });
+        if fd == INVALID_FD {
+            return Err(error::with_context(
+                io::Error::last_os_error(),
+                "open",
+                Some(dir.as_ref()),
+            ));
+        }
+
+        Ok(File::from_fd(fd))
+    }
+
+    /*
+        给fd指向的文件创建一个新名字(硬链接)，封装POSIX linkat
+        linkat(olddirfd: i32, oldpath: *const c_char, newdirfd: i32, newpath: *const c_char, flags: i32) -> i32
+        通过/proc/self/fd/N与AT_SYMLINK_FOLLOW链接，不需要linkat(AT_EMPTY_PATH)所要求的特权
+        path已存在时返回AlreadyExists而不是覆盖
+
+        用于给File::tmpfile(O_TMPFILE)创建的匿名文件一个名字，名字出现的同时内容已经完整
+        对有名字的普通文件只是多一个硬链接，原来的名字依然存在，不是TempFile::persist那样的移动
+        以O_TMPFILE | O_EXCL打开的匿名文件不允许链接，返回ENOENT
+    */
+    #[cfg(target_os = "linux")]
+    pub fn link_to<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.ensure_open()?;
+        let from = to_c_path(Path::new(&format!("/proc/self/fd/{}", self.fd)))?;
+        let to = to_c_path(path.as_ref())?;
+
+        let result = unsafe {
+            libc::linkat(
+                libc::AT_FDCWD,
+                from.as_ptr(),
+                libc::AT_FDCWD,
+                to.as_ptr(),
+                libc::AT_SYMLINK_FOLLOW,
+            )
+        };
+        if result < 0 {
+            return Err(error::with_context(
+                io::Error::last_os_error(),
+                "linkat",
+                Some(path.as_ref()),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if self.persisted {
+            return;
+        }
+
+        if let Ok(path) = to_c_path(&self.path) {
+            unsafe {
+                libc::unlink(path.as_ptr());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TempDir, TempFile, template_to_path};
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+
+    #[test]
+    fn test_temp_file_discard_on_drop() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let mut temp = TempFile::new_in(dir.path())?;
+        temp.file_mut().write_all(b"scratch")?;
+
+        let path = temp.path().to_path_buf();
+        assert!(path.starts_with(dir.path()), "Temp file should be in dir");
+        assert!(path.exists(), "Temp file should exist");
+
+        drop(temp);
+        assert!(!path.exists(), "Temp file should be removed on drop");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_temp_file_persist() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let target = dir.path().join("committed.txt");
+
+        let mut temp = TempFile::new_in(dir.path())?;
+        temp.file_mut().write_all(b"Hello, ")?;
+        let temp_path = temp.path().to_path_buf();
+
+        // persist后返回的File依旧可以继续写入
+        let mut file = temp.persist(&target)?;
+        file.write_all(b"world!")?;
+        drop(file);
+
+        assert!(!temp_path.exists(), "Temp path should be gone");
+        assert_eq!(std::fs::read(&target)?, b"Hello, world!");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_temp_file_persist_sync() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let target = dir.path().join("durable.txt");
+        std::fs::write(&target, b"old")?;
+
+        let mut temp = TempFile::new_in(dir.path())?;
+        temp.file_mut().write_all(b"new")?;
+        temp.persist_sync(&target)?;
+        assert_eq!(std::fs::read(&target)?, b"new");
+
+        // 目标目录不存在时rename失败，临时文件被删除
+        let mut temp = TempFile::new_in(dir.path())?;
+        temp.file_mut().write_all(b"lost")?;
+        let temp_path = temp.path().to_path_buf();
+        let result = temp.persist_sync(dir.path().join("missing/durable.txt"));
+        assert!(result.is_err(), "Missing directory should fail");
+        assert!(!temp_path.exists(), "Temp file should be removed");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_temp_file_keep() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let mut temp = TempFile::new_in(dir.path())?;
+        temp.file_mut().write_all(b"kept")?;
+
+        let (file, path) = temp.keep();
+        drop(file);
+        assert_eq!(std::fs::read(&path)?, b"kept", "Kept file should remain");
+
+        // 默认在系统临时目录下创建
+        let temp = TempFile::new()?;
+        assert!(temp.path().starts_with(std::env::temp_dir()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_temp_dir() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().to_path_buf();
+        assert!(crate::is_dir(&path)?);
+        assert_eq!(crate::metadata(&path)?.permissions().mode() & 0o777, 0o700);
+
+        // Drop时连同内容一起删除
+        crate::create_dir_all(path.join("a/b"))?;
+        crate::write(path.join("a/b/file.txt"), b"content")?;
+        drop(dir);
+        assert!(!crate::exists(&path)?, "Temp dir should be removed on drop");
+
+        let parent = TempDir::new()?;
+        let kept = TempDir::new_in(parent.path())?.keep();
+        assert!(crate::is_dir(&kept)?, "Kept dir should remain");
+
+        let closed = TempDir::new_in(parent.path())?;
+        let path = closed.path().to_path_buf();
+        closed.close()?;
+        assert!(!crate::exists(&path)?);
+
+        let result = TempDir::new_in(parent.path().join("missing"));
+        assert!(result.is_err(), "Missing parent should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::NotFound,
+                "Error should be NotFound"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_temp_file_unique_names() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let first = TempFile::new_in(dir.path())?;
+        let second = TempFile::new_in(dir.path())?;
+        assert_ne!(first.path(), second.path(), "Names should be unique");
+
+        // 生成的名字按字节还原，不要求是UTF-8
+        let path = template_to_path(b"/tmp/\xff/.tmpabcdef\0".to_vec());
+        assert_eq!(path.as_os_str().as_bytes(), b"/tmp/\xff/.tmpabcdef");
+
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_tmpfile_link_to() -> io::Result<()> {
+        use crate::File;
+
+        let dir = TempDir::new()?;
+        let target = dir.path().join("published.txt");
+
+        let mut file = File::tmpfile(dir.path())?;
+        file.write_all(b"Hello, world!")?;
+        assert_eq!(
+            std::fs::read_dir(dir.path())?.count(),
+            0,
+            "Anonymous file should not be visible"
+        );
+
+        file.link_to(&target)?;
+        assert_eq!(std::fs::read(&target)?, b"Hello, world!");
+
+        // 目标已存在时不会被覆盖
+        let other = File::tmpfile(dir.path())?;
+        let result = other.link_to(&target);
+        assert!(result.is_err(), "Linking over existing file should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::AlreadyExists,
+                "Error should be AlreadyExists"
+            );
+        }
+
+        // 有名字的文件只是多一个硬链接
+        let named = File::open(&target, crate::OpenMode::Read)?;
+        let linked = dir.path().join("linked.txt");
+        named.link_to(&linked)?;
+        assert!(crate::exists(&target)?, "Original name should remain");
+        assert_eq!(crate::metadata(&linked)?.nlink(), 2);
+
+        Ok(())
+    }
+}
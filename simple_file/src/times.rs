@@ -0,0 +1,165 @@
+/*
+    修改文件的访问时间(atime)和修改时间(mtime)，解压、同步工具需要用它保留原始时间戳
+
+    futimens(fd: i32, times: *const [timespec; 2]) -> i32
+    times[0]为atime，times[1]为mtime，精确到纳秒
+    tv_nsec为UTIME_NOW时设置为当前时间，为UTIME_OMIT时保持不变
+*/
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{File, Result};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Time {
+    Omit,
+    Now,
+    At(SystemTime),
+}
+
+/// 需要设置的时间戳，未设置的项保持不变
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FileTimes {
+    accessed: Time,
+    modified: Time,
+}
+
+impl FileTimes {
+    pub fn new() -> FileTimes {
+        FileTimes {
+            accessed: Time::Omit,
+            modified: Time::Omit,
+        }
+    }
+
+    pub fn set_accessed(mut self, time: SystemTime) -> FileTimes {
+        self.accessed = Time::At(time);
+        self
+    }
+
+    pub fn set_modified(mut self, time: SystemTime) -> FileTimes {
+        self.modified = Time::At(time);
+        self
+    }
+
+    /// 由内核取当前时间，避免SystemTime::now()与实际写入之间的时间差
+    pub fn set_accessed_now(mut self) -> FileTimes {
+        self.accessed = Time::Now;
+        self
+    }
+
+    pub fn set_modified_now(mut self) -> FileTimes {
+        self.modified = Time::Now;
+        self
+    }
+}
+
+impl Default for FileTimes {
+    fn default() -> FileTimes {
+        FileTimes::new()
+    }
+}
+
+impl File {
+    pub fn set_times(&mut self, times: FileTimes) -> Result<()> {
+        self.ensure_open()?;
+
+        let timespecs = [to_timespec(times.accessed), to_timespec(times.modified)];
+        let result = unsafe { libc::futimens(self.fd, timespecs.as_ptr()) };
+        if result < 0 {
+            return Err(self.record_os_error("futimens"));
+        }
+
+        Ok(())
+    }
+
+    /// 只修改mtime，等价于set_times(FileTimes::new().set_modified(time))
+    pub fn set_modified(&mut self, time: SystemTime) -> Result<()> {
+        self.set_times(FileTimes::new().set_modified(time))
+    }
+
+    /// 最后访问时间(atime)
+    pub fn accessed(&self) -> Result<SystemTime> {
+        Ok(self.metadata()?.accessed())
+    }
+
+    /// 最后修改时间(mtime)
+    pub fn modified(&self) -> Result<SystemTime> {
+        Ok(self.metadata()?.modified())
+    }
+}
+
+// SystemTime -> timespec，1970年之前的时间秒数为负，纳秒部分总是在[0, 1e9)之间
+fn to_timespec(time: Time) -> libc::timespec {
+    let (sec, nsec) = match time {
+        Time::Omit => (0, libc::UTIME_OMIT),
+        Time::Now => (0, libc::UTIME_NOW),
+        Time::At(time) => match time.duration_since(UNIX_EPOCH) {
+            Ok(d) => (d.as_secs() as i64, d.subsec_nanos() as i64),
+            Err(e) => {
+                let d = e.duration();
+                let (sec, nsec) = (-(d.as_secs() as i64), d.subsec_nanos() as i64);
+                if nsec == 0 {
+                    (sec, 0)
+                } else {
+                    (sec - 1, 1_000_000_000 - nsec)
+                }
+            }
+        },
+    };
+
+    libc::timespec {
+        tv_sec: sec as libc::time_t,
+        tv_nsec: nsec as libc::c_long,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FileTimes;
+    use crate::temp::TempFile;
+    use crate::{File, OpenMode};
+    use std::io;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn test_set_times() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let mut file = File::open(temp_file.path(), OpenMode::Write)?;
+
+        let accessed = UNIX_EPOCH + Duration::new(1_000_000_000, 123_456_789);
+        let modified = UNIX_EPOCH + Duration::new(1_500_000_000, 987_654_321);
+        let times = FileTimes::new()
+            .set_accessed(accessed)
+            .set_modified(modified);
+        file.set_times(times)?;
+
+        assert_eq!(file.accessed()?, accessed, "atime should keep nanoseconds");
+        assert_eq!(file.modified()?, modified, "mtime should keep nanoseconds");
+
+        // 未设置的项保持不变
+        let later = modified + Duration::from_secs(60);
+        file.set_modified(later)?;
+        assert_eq!(file.accessed()?, accessed, "atime should be omitted");
+        assert_eq!(file.modified()?, later);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_times_now_and_before_epoch() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let mut file = File::open(temp_file.path(), OpenMode::Write)?;
+
+        let before_epoch = UNIX_EPOCH - Duration::new(100, 250_000_000);
+        file.set_times(FileTimes::new().set_modified(before_epoch))?;
+        assert_eq!(file.modified()?, before_epoch);
+
+        let start = SystemTime::now() - Duration::from_secs(1);
+        file.set_times(FileTimes::new().set_accessed_now().set_modified_now())?;
+        assert!(file.modified()? >= start, "mtime should be now");
+        assert!(file.accessed()? >= start, "atime should be now");
+
+        Ok(())
+    }
+}
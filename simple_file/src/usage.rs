@@ -0,0 +1,266 @@
+/*
+    统计目录树占用的空间，类似du
+
+    每个文件有两种大小：
+    apparent_size   文件长度(st_size)之和，即读出全部内容得到的字节数
+    allocated_size  实际分配的磁盘块(st_blocks * 512)之和，稀疏文件的空洞不占空间，小文件按块向上取整
+
+    基于walk先序遍历，walk保证目录先于它里面的内容输出，用一个按深度排列的栈累加：
+    遇到深度为d的条目时，栈中深度不小于d的目录都已遍历完，依次弹出并累加到父目录
+    目录本身占用的块也计入，与du相同；符号链接不跟随，只统计链接本身
+*/
+
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{Error, Metadata, Result, walk};
+
+/// 统计path下的全部空间，使用默认选项
+pub fn disk_usage<P: AsRef<Path>>(path: P) -> Result<DiskUsage> {
+    DiskUsageOptions::new().scan(path)
+}
+
+/// 统计空间的选项，默认每个硬链接都计入一次，并且会进入挂载在树中的其他文件系统
+#[derive(Clone, Debug, Default)]
+pub struct DiskUsageOptions {
+    count_links_once: bool,
+    one_file_system: bool,
+}
+
+impl DiskUsageOptions {
+    pub fn new() -> DiskUsageOptions {
+        DiskUsageOptions::default()
+    }
+
+    /// 同一个文件的多个硬链接只计入第一次遇到的那个，与du的默认行为相同
+    pub fn count_links_once(&mut self, once: bool) -> &mut DiskUsageOptions {
+        self.count_links_once = once;
+        self
+    }
+
+    /// 不进入与起点位于不同文件系统的目录(挂载点)，挂载点本身仍然计入，与du -x相同
+    pub fn one_file_system(&mut self, one: bool) -> &mut DiskUsageOptions {
+        self.one_file_system = one;
+        self
+    }
+
+    /*
+        遍历path并统计，path不存在或无法访问时返回错误
+        遍历过程中没有权限读取、已被删除或被替换的条目跳过，清理工具运行时目录树可能正在变化
+    */
+    pub fn scan<P: AsRef<Path>>(&self, path: P) -> Result<DiskUsage> {
+        let root = path.as_ref();
+        let root_dev = crate::metadata(root)?.dev();
+
+        let mut walker = walk(root);
+        let mut seen = HashSet::new();
+        let mut stack: Vec<DiskUsage> = Vec::new();
+        while let Some(entry) = walker.next() {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) if stack.is_empty() => return Err(e),
+                Err(e) if skippable(&e) => continue,
+                Err(e) => return Err(e),
+            };
+
+            // 起点是符号链接时walk会跟随，统计链接指向的目录
+            let metadata = if entry.depth() == 0 {
+                crate::metadata(entry.path())
+            } else {
+                entry.metadata()
+            };
+            let metadata = match metadata {
+                Ok(metadata) => metadata,
+                Err(e) if skippable(&e) && entry.depth() > 0 => continue,
+                Err(e) => return Err(e),
+            };
+
+            // 与du -x相同，挂载点目录本身计入，只是不进入它
+            if self.one_file_system && metadata.is_dir() && metadata.dev() != root_dev {
+                walker.skip_current_dir();
+            }
+
+            while stack.len() > entry.depth() {
+                fold(&mut stack);
+            }
+
+            let counted = !self.count_links_once
+                || metadata.is_dir()
+                || metadata.nlink() <= 1
+                || seen.insert((metadata.dev(), metadata.ino()));
+            let mut usage = DiskUsage::new(entry.into_path());
+            if counted {
+                usage.add(&metadata);
+            }
+
+            match stack.last_mut() {
+                Some(parent) if !metadata.is_dir() => parent.merge(&usage),
+                _ => stack.push(usage),
+            }
+        }
+
+        while stack.len() > 1 {
+            fold(&mut stack);
+        }
+        stack
+            .pop()
+            .ok_or_else(|| Error::new(io::ErrorKind::NotFound, "Disk usage root was filtered out"))
+    }
+}
+
+// walk自己检查出的错误(目录在遍历时被替换)同样是目录树变化造成的
+fn skippable(e: &Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::PermissionDenied | io::ErrorKind::NotFound
+    ) || e.operation() == Some("walk")
+}
+
+// 弹出栈顶已经遍历完的目录，累加到它的父目录
+fn fold(stack: &mut Vec<DiskUsage>) {
+    if let Some(child) = stack.pop()
+        && let Some(parent) = stack.last_mut()
+    {
+        parent.merge(&child);
+        parent.children.push(child);
+    }
+}
+
+/// 一棵子树的统计结果，children为其中的子目录，顺序与readdir相同
+#[derive(Clone, Debug)]
+pub struct DiskUsage {
+    path: PathBuf,
+    apparent_size: u64,
+    allocated_size: u64,
+    files: u64,
+    children: Vec<DiskUsage>,
+}
+
+impl DiskUsage {
+    fn new(path: PathBuf) -> DiskUsage {
+        DiskUsage {
+            path,
+            apparent_size: 0,
+            allocated_size: 0,
+            files: 0,
+            children: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, metadata: &Metadata) {
+        self.apparent_size += metadata.len();
+        self.allocated_size += metadata.blocks() * 512;
+        self.files += 1;
+    }
+
+    fn merge(&mut self, other: &DiskUsage) {
+        self.apparent_size += other.apparent_size;
+        self.allocated_size += other.allocated_size;
+        self.files += other.files;
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 文件长度之和，单位字节
+    pub fn apparent_size(&self) -> u64 {
+        self.apparent_size
+    }
+
+    /// 实际占用的磁盘空间，单位字节
+    pub fn allocated_size(&self) -> u64 {
+        self.allocated_size
+    }
+
+    /// 计入统计的条目数，包括目录和符号链接；只计入一次的硬链接不重复计数
+    pub fn files(&self) -> u64 {
+        self.files
+    }
+
+    /// 直接子目录的统计结果
+    pub fn children(&self) -> &[DiskUsage] {
+        &self.children
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DiskUsageOptions, disk_usage};
+    use crate::temp::TempDir;
+    use crate::{File, OpenMode, create_dir_all, hard_link, metadata, symlink, write};
+    use std::io;
+
+    #[test]
+    fn test_disk_usage() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let root = dir.path();
+        create_dir_all(root.join("a/b"))?;
+        write(root.join("top.txt"), &[1u8; 100])?;
+        write(root.join("a/one.txt"), &[1u8; 1000])?;
+        write(root.join("a/b/two.txt"), &[1u8; 10])?;
+        symlink("top.txt", root.join("link"))?;
+
+        let usage = disk_usage(root)?;
+        let dirs = ["", "a", "a/b"]
+            .iter()
+            .map(|d| metadata(root.join(d)).map(|m| m.len()))
+            .sum::<crate::Result<u64>>()?;
+        assert_eq!(usage.apparent_size(), 100 + 1000 + 10 + 7 + dirs);
+        assert_eq!(usage.files(), 7);
+        assert!(usage.allocated_size() > 0);
+
+        // 子树各自统计
+        assert_eq!(usage.children().len(), 1);
+        let a = &usage.children()[0];
+        assert_eq!(a.path(), root.join("a"));
+        assert_eq!(a.files(), 4);
+        let b = &a.children()[0];
+        assert_eq!(b.apparent_size(), 10 + metadata(root.join("a/b"))?.len());
+
+        // 单个文件
+        let file = disk_usage(root.join("top.txt"))?;
+        assert_eq!(file.apparent_size(), 100);
+        assert!(file.children().is_empty());
+
+        let result = disk_usage(root.join("missing"));
+        assert!(result.is_err(), "Missing root should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::NotFound,
+                "Error should be NotFound"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_disk_usage_links_and_sparse() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let root = dir.path();
+        write(root.join("data"), &[1u8; 4096])?;
+        hard_link(root.join("data"), root.join("data2"))?;
+
+        let base = metadata(root)?.len();
+        assert_eq!(disk_usage(root)?.apparent_size(), base + 8192);
+        let once = DiskUsageOptions::new().count_links_once(true).scan(root)?;
+        assert_eq!(once.apparent_size(), base + 4096);
+        assert_eq!(once.files(), 2);
+
+        // 稀疏文件的空洞不占磁盘空间
+        let sparse = root.join("sparse");
+        File::open(&sparse, OpenMode::Write)?.set_len(64 * 1024 * 1024)?;
+        let usage = disk_usage(&sparse)?;
+        assert_eq!(usage.apparent_size(), 64 * 1024 * 1024);
+        assert!(usage.allocated_size() < usage.apparent_size());
+
+        // 同一文件系统内结果不变
+        let one = DiskUsageOptions::new().one_file_system(true).scan(root)?;
+        assert_eq!(one.apparent_size(), disk_usage(root)?.apparent_size());
+
+        Ok(())
+    }
+}
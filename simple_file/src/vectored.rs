@@ -0,0 +1,198 @@
+/*
+    分散读/集中写，一次系统调用读写多个缓冲区，拼装"头部+数据"时无需先复制到一块连续内存
+
+    readv(fd: i32, iov: *const iovec, iovcnt: i32) -> ssize_t
+    writev(fd: i32, iov: *const iovec, iovcnt: i32) -> ssize_t
+    Unix下IoSlice/IoSliceMut与iovec内存布局相同，可以直接当作iovec数组传入
+
+    preadv/pwritev在此基础上再指定偏移量，与pread/pwrite一样不使用也不改变当前读写位置
+    preadv(fd: i32, iov: *const iovec, iovcnt: i32, offset: off_t) -> ssize_t
+*/
+
+use std::io::{IoSlice, IoSliceMut};
+
+use crate::{File, Result, retry_eintr, sys, to_off_t};
+
+// 单次调用的缓冲区个数上限(IOV_MAX)，超出部分留给下一次调用，与短读短写的处理方式相同
+const MAX_IOV: usize = 1024;
+
+impl File {
+    pub fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> Result<usize> {
+        self.ensure_readable()?;
+
+        let result = retry_eintr(self.retry_interrupted, || unsafe {
+            libc::readv(
+                self.fd,
+                bufs.as_mut_ptr() as *const libc::iovec,
+                bufs.len().min(MAX_IOV) as libc::c_int,
+            )
+        });
+        if result < 0 {
+            return Err(self.record_os_error("readv"));
+        }
+
+        Ok(result as usize)
+    }
+
+    pub fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> Result<usize> {
+        self.ensure_writable()?;
+
+        let result = retry_eintr(self.retry_interrupted, || unsafe {
+            libc::writev(
+                self.fd,
+                bufs.as_ptr() as *const libc::iovec,
+                bufs.len().min(MAX_IOV) as libc::c_int,
+            )
+        });
+        if result < 0 {
+            return Err(self.record_os_error("writev"));
+        }
+
+        Ok(result as usize)
+    }
+
+    /// 从offset处分散读取到多个缓冲区，只需要&self，可以在多个线程间共享
+    pub fn read_vectored_at(&self, bufs: &mut [IoSliceMut<'_>], offset: u64) -> Result<usize> {
+        self.ensure_readable()?;
+        let offset = to_off_t(offset)?;
+
+        let result = retry_eintr(self.retry_interrupted, || unsafe {
+            sys::preadv(
+                self.fd,
+                bufs.as_mut_ptr() as *const libc::iovec,
+                bufs.len().min(MAX_IOV) as libc::c_int,
+                offset,
+            )
+        });
+        if result < 0 {
+            return Err(self.os_error("preadv"));
+        }
+
+        Ok(result as usize)
+    }
+
+    /// 把多个缓冲区集中写入到offset处，一次系统调用完成，适合数据库式的"页头+页数据"写入
+    pub fn write_vectored_at(&self, bufs: &[IoSlice<'_>], offset: u64) -> Result<usize> {
+        self.ensure_writable()?;
+        let offset = to_off_t(offset)?;
+
+        let result = retry_eintr(self.retry_interrupted, || unsafe {
+            sys::pwritev(
+                self.fd,
+                bufs.as_ptr() as *const libc::iovec,
+                bufs.len().min(MAX_IOV) as libc::c_int,
+                offset,
+            )
+        });
+        if result < 0 {
+            return Err(self.os_error("pwritev"));
+        }
+
+        Ok(result as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::temp::TempFile;
+    use crate::{File, OpenMode};
+    use std::io::{self, IoSlice, IoSliceMut, Read, Write};
+
+    #[test]
+    fn test_write_vectored() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let mut file = File::open(temp_file.path(), OpenMode::Write)?;
+
+        let header = b"HEAD";
+        let payload = b"Hello, world!";
+        let n = file.write_vectored(&[IoSlice::new(header), IoSlice::new(payload)])?;
+        assert_eq!(n, 17, "Both buffers should be written");
+
+        assert_eq!(std::fs::read(temp_file.path())?, b"HEADHello, world!");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_vectored() -> io::Result<()> {
+        let mut temp_file = TempFile::new()?;
+        temp_file.file_mut().write_all(b"HEADHello, world!")?;
+
+        let mut file = File::open(temp_file.path(), OpenMode::Read)?;
+        let mut header = [0u8; 4];
+        let mut payload = [0u8; 32];
+        let n =
+            file.read_vectored(&mut [IoSliceMut::new(&mut header), IoSliceMut::new(&mut payload)])?;
+
+        assert_eq!(n, 17);
+        assert_eq!(&header, b"HEAD");
+        assert_eq!(&payload[..13], b"Hello, world!");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_vectored_at() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let mut file = File::open(temp_file.path(), OpenMode::ReadWrite)?;
+        file.write_all(b"................")?;
+
+        let n = file.write_vectored_at(&[IoSlice::new(b"PAGE"), IoSlice::new(b"data")], 4)?;
+        assert_eq!(n, 8);
+        assert_eq!(std::fs::read(temp_file.path())?, b"....PAGEdata....");
+
+        let (mut head, mut body) = ([0u8; 4], [0u8; 4]);
+        let n = file.read_vectored_at(
+            &mut [IoSliceMut::new(&mut head), IoSliceMut::new(&mut body)],
+            4,
+        )?;
+        assert_eq!(n, 8);
+        assert_eq!((&head, &body), (b"PAGE", b"data"));
+
+        // 不改变当前读写位置
+        assert_eq!(file.stream_position()?, 16, "Position should not move");
+
+        let result = file.write_vectored_at(&[IoSlice::new(b"x")], u64::MAX);
+        assert!(result.is_err(), "Offset beyond off_t should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::InvalidInput,
+                "Error should be InvalidInput"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_vectored_trait() -> io::Result<()> {
+        let temp_file = TempFile::new()?;
+        let mut file = File::open(temp_file.path(), OpenMode::ReadWrite)?;
+
+        // 通过trait调用，确认使用的是writev/readv而不是默认的逐个缓冲区实现
+        let n = Write::write_vectored(&mut file, &[IoSlice::new(b"ab"), IoSlice::new(b"cd")])?;
+        assert_eq!(n, 4, "Default impl would only write the first buffer");
+
+        file.rewind()?;
+        let (mut a, mut b) = ([0u8; 2], [0u8; 2]);
+        let n = Read::read_vectored(
+            &mut file,
+            &mut [IoSliceMut::new(&mut a), IoSliceMut::new(&mut b)],
+        )?;
+        assert_eq!(n, 4, "Default impl would only fill the first buffer");
+        assert_eq!((&a, &b), (b"ab", b"cd"));
+
+        let result = File::from_fd(-1).write_vectored(&[IoSlice::new(b"x")]);
+        assert!(result.is_err(), "Writing to closed file should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::InvalidInput,
+                "Error should be InvalidInput"
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,452 @@
+/*
+    递归遍历目录树，先序输出：先输出目录本身，再输出它里面的内容
+
+    每一层目录对应一个打开的Dir，同时打开的目录数等于当前深度
+    子目录相对于父目录的fd用openat(O_DIRECTORY|O_NOFOLLOW)打开，条目的元信息也用fstatat相对于父目录查询，
+    不会重新解析完整路径：遍历过程中上级目录被替换成符号链接时，不会被带到树外
+    检查条目(lstat)到打开目录之间，目录可能被换成别的目录，打开后用fstat比较(dev, ino)，不一致时返回错误
+    默认不跟随符号链接，此时不可能出现环(目录不能有硬链接)
+    跟随符号链接时，指向祖先目录的链接会形成环，通过比较(dev, ino)识别，遇到时返回ELOOP错误而不是无限递归
+*/
+
+use std::ffi::OsStr;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::{Dir, FileType, Metadata, ReadDir, Result, error};
+
+/// 从path开始递归遍历，path本身作为深度为0的第一项输出，出错的条目以Err输出后继续遍历
+pub fn walk<P: AsRef<Path>>(path: P) -> Walk {
+    Walk {
+        root: Some(path.as_ref().to_path_buf()),
+        max_depth: usize::MAX,
+        follow_symlinks: false,
+        filter: None,
+        stack: Vec::new(),
+        pending: None,
+    }
+}
+
+type Filter = Box<dyn FnMut(&WalkEntry) -> bool>;
+
+// 正在遍历的一层目录
+struct Frame {
+    dir: Arc<Dir>,
+    entries: ReadDir,
+    depth: usize,
+    id: (u64, u64), // (dev, ino)，用于识别环
+}
+
+// 已经输出、下一次调用时进入的目录
+struct Pending {
+    path: PathBuf,
+    depth: usize,
+    id: (u64, u64), // 检查条目时得到的(dev, ino)，打开后再次比较
+    follow: bool,   // 条目本身是被跟随的符号链接
+}
+
+/// 递归遍历的迭代器，由walk创建，通过链式调用设置选项
+pub struct Walk {
+    root: Option<PathBuf>,
+    max_depth: usize,
+    follow_symlinks: bool,
+    filter: Option<Filter>,
+    stack: Vec<Frame>,
+    pending: Option<Pending>,
+}
+
+impl Walk {
+    /// 最大深度，0表示只输出path本身，1表示只输出path下的直接条目
+    pub fn max_depth(mut self, depth: usize) -> Walk {
+        self.max_depth = depth;
+        self
+    }
+
+    /// 是否跟随符号链接进入目录，默认不跟随，链接本身作为一项输出
+    pub fn follow_symlinks(mut self, follow: bool) -> Walk {
+        self.follow_symlinks = follow;
+        self
+    }
+
+    /// 只输出predicate返回true的条目，返回false的目录不会被进入
+    pub fn filter<F: FnMut(&WalkEntry) -> bool + 'static>(mut self, predicate: F) -> Walk {
+        self.filter = Some(Box::new(predicate));
+        self
+    }
+
+    /// 不进入上一次输出的目录，它本身已经输出，里面的内容被跳过；上一项不是目录时没有效果
+    pub fn skip_current_dir(&mut self) {
+        self.pending = None;
+    }
+
+    /*
+        对一个条目应用过滤条件，是目录且未超过最大深度时记录下来，等下一次调用时进入
+        metadata为None时相对于父目录fstatat查询，follow表示条目是被跟随的符号链接
+    */
+    fn visit(
+        &mut self,
+        entry: WalkEntry,
+        metadata: Option<Metadata>,
+        follow: bool,
+    ) -> Result<Option<WalkEntry>> {
+        if let Some(filter) = self.filter.as_mut()
+            && !filter(&entry)
+        {
+            return Ok(None);
+        }
+
+        if entry.file_type.is_dir() && entry.depth < self.max_depth {
+            let metadata = match metadata {
+                Some(metadata) => metadata,
+                None => entry.metadata()?,
+            };
+            let id = (metadata.dev(), metadata.ino());
+
+            if self.stack.iter().any(|frame| frame.id == id) {
+                return Err(error::with_context(
+                    io::Error::from_raw_os_error(libc::ELOOP),
+                    "walk",
+                    Some(&entry.path),
+                ));
+            }
+            self.pending = Some(Pending {
+                path: entry.path.clone(),
+                depth: entry.depth,
+                id,
+                follow,
+            });
+        }
+
+        Ok(Some(entry))
+    }
+
+    /*
+        打开pending中的目录并压栈
+        起点按路径打开；子目录相对于父目录的fd打开，不是被跟随的符号链接时带O_NOFOLLOW，
+        期间被换成符号链接的目录打开失败，被换成其他目录的用(dev, ino)识别
+    */
+    fn enter(&mut self, pending: Pending) -> Result<()> {
+        let dir = match (self.stack.last(), pending.path.file_name()) {
+            (Some(parent), Some(name)) if pending.follow => parent.dir.open_dir_at(name)?,
+            (Some(parent), Some(name)) => parent.dir.open_dir_at_nofollow(Path::new(name))?,
+            _ => Dir::open(&pending.path)?,
+        };
+
+        let metadata = dir.metadata()?;
+        if (metadata.dev(), metadata.ino()) != pending.id {
+            return Err(error::with_context(
+                io::Error::other("Directory was replaced during walk"),
+                "walk",
+                Some(&pending.path),
+            ));
+        }
+
+        let entries = dir.read_dir()?;
+        self.stack.push(Frame {
+            dir: Arc::new(dir),
+            entries,
+            depth: pending.depth,
+            id: pending.id,
+        });
+
+        Ok(())
+    }
+}
+
+impl Iterator for Walk {
+    type Item = Result<WalkEntry>;
+
+    fn next(&mut self) -> Option<Result<WalkEntry>> {
+        if let Some(root) = self.root.take() {
+            // 起点本身是符号链接时总是跟随
+            let metadata = match crate::metadata(&root) {
+                Ok(metadata) => metadata,
+                Err(e) => return Some(Err(e)),
+            };
+            let entry = WalkEntry {
+                path: root,
+                depth: 0,
+                file_type: metadata.file_type(),
+                parent: None,
+            };
+            match self.visit(entry, Some(metadata), true) {
+                Ok(Some(entry)) => return Some(Ok(entry)),
+                Ok(None) => return None,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+
+        if let Some(pending) = self.pending.take()
+            && let Err(e) = self.enter(pending)
+        {
+            return Some(Err(e));
+        }
+
+        loop {
+            let frame = self.stack.last_mut()?;
+            let depth = frame.depth + 1;
+            let dir_entry = match frame.entries.next() {
+                Some(Ok(dir_entry)) => dir_entry,
+                Some(Err(e)) => return Some(Err(e)),
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+
+            let file_type = match dir_entry.file_type() {
+                Ok(file_type) => file_type,
+                Err(e) => return Some(Err(e)),
+            };
+            let follow = self.follow_symlinks && file_type.is_symlink();
+            let entry = WalkEntry {
+                path: dir_entry.path(),
+                depth,
+                file_type,
+                parent: Some(Arc::clone(&frame.dir)),
+            };
+
+            // 跟随符号链接时，条目的类型取链接指向的文件，指向不存在的文件时保留链接本身的信息
+            let metadata = if follow {
+                match entry.followed_metadata() {
+                    Ok(metadata) => Some(metadata),
+                    Err(e) => return Some(Err(e)),
+                }
+            } else {
+                None
+            };
+            let entry = WalkEntry {
+                file_type: metadata.as_ref().map_or(file_type, |m| m.file_type()),
+                ..entry
+            };
+
+            match self.visit(entry, metadata, follow) {
+                Ok(Some(entry)) => return Some(Ok(entry)),
+                Ok(None) => continue,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// walk输出的一项
+#[derive(Clone, Debug)]
+pub struct WalkEntry {
+    path: PathBuf,
+    depth: usize,
+    file_type: FileType,
+    parent: Option<Arc<Dir>>, // 所在的目录，起点为None
+}
+
+impl WalkEntry {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn into_path(self) -> PathBuf {
+        self.path
+    }
+
+    /// 路径的最后一级，根路径为"/"或".."时返回整个路径
+    pub fn file_name(&self) -> &OsStr {
+        self.path.file_name().unwrap_or(self.path.as_os_str())
+    }
+
+    /// 相对于起点的深度，起点本身为0
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// 条目的类型，跟随符号链接时为链接指向的文件的类型
+    pub fn file_type(&self) -> FileType {
+        self.file_type
+    }
+
+    /// 条目的元信息，不跟随符号链接；起点以外的条目相对于所在目录的fd查询，不重新解析路径
+    pub fn metadata(&self) -> Result<Metadata> {
+        match &self.parent {
+            Some(parent) => parent.symlink_metadata_at(self.file_name()),
+            None => crate::symlink_metadata(&self.path),
+        }
+    }
+
+    // 跟随符号链接的元信息，链接指向不存在的文件时返回链接本身的信息
+    fn followed_metadata(&self) -> Result<Metadata> {
+        match &self.parent {
+            Some(parent) => parent
+                .metadata_at(self.file_name())
+                .or_else(|_| self.metadata()),
+            None => crate::metadata(&self.path).or_else(|_| self.metadata()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::walk;
+    use crate::temp::TempDir;
+    use crate::{create_dir_all, symlink, write};
+    use std::io;
+    use std::path::PathBuf;
+
+    // 收集相对于root的路径，排序后比较，readdir的顺序不固定
+    fn relative_paths(root: &std::path::Path, walk: super::Walk) -> io::Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+        for entry in walk {
+            let entry = entry?;
+            paths.push(entry.path().strip_prefix(root).unwrap().to_path_buf());
+        }
+        paths.sort();
+        Ok(paths)
+    }
+
+    #[test]
+    fn test_walk() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let root = dir.path();
+        create_dir_all(root.join("a/b"))?;
+        write(root.join("a/file.txt"), b"content")?;
+        write(root.join("a/b/file.log"), b"content")?;
+        write(root.join("top.txt"), b"content")?;
+
+        let paths = relative_paths(root, walk(root))?;
+        let expected = ["", "a", "a/b", "a/b/file.log", "a/file.txt", "top.txt"];
+        assert_eq!(paths, expected.map(PathBuf::from));
+
+        // 目录先于它里面的内容输出
+        let entries = walk(root).collect::<crate::Result<Vec<_>>>()?;
+        assert_eq!(entries[0].depth(), 0);
+        let position = |name: &str| entries.iter().position(|e| e.path() == root.join(name));
+        assert!(position("a") < position("a/b"));
+        assert!(position("a/b") < position("a/b/file.log"));
+
+        let file = entries
+            .iter()
+            .find(|e| e.file_name() == "file.log")
+            .unwrap();
+        assert_eq!(file.depth(), 3);
+        assert!(file.file_type().is_file());
+        assert_eq!(file.metadata()?.len(), 7);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_walk_max_depth_and_filter() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let root = dir.path();
+        create_dir_all(root.join("a/b"))?;
+        create_dir_all(root.join("skip/c"))?;
+        write(root.join("a/b/deep.txt"), b"content")?;
+
+        let paths = relative_paths(root, walk(root).max_depth(1))?;
+        assert_eq!(paths, ["", "a", "skip"].map(PathBuf::from));
+
+        assert_eq!(walk(root).max_depth(0).count(), 1, "Only the root");
+
+        // 被过滤掉的目录不会被进入
+        let paths = relative_paths(root, walk(root).filter(|e| e.file_name() != "skip"))?;
+        assert_eq!(paths, ["", "a", "a/b", "a/b/deep.txt"].map(PathBuf::from));
+
+        // skip_current_dir保留目录本身，只跳过里面的内容
+        let mut walker = walk(root);
+        let mut paths = Vec::new();
+        while let Some(entry) = walker.next() {
+            let entry = entry?;
+            if entry.file_name() == "skip" {
+                walker.skip_current_dir();
+            }
+            paths.push(entry.path().strip_prefix(root).unwrap().to_path_buf());
+        }
+        paths.sort();
+        assert_eq!(
+            paths,
+            ["", "a", "a/b", "a/b/deep.txt", "skip"].map(PathBuf::from)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_walk_symlinks() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let root = dir.path();
+        create_dir_all(root.join("a/b"))?;
+        write(root.join("a/b/file.txt"), b"content")?;
+        symlink(root.join("a"), root.join("link"))?;
+
+        // 默认不跟随，链接本身作为一项输出
+        let entries = walk(root).collect::<crate::Result<Vec<_>>>()?;
+        let link = entries.iter().find(|e| e.file_name() == "link").unwrap();
+        assert!(link.file_type().is_symlink());
+        assert_eq!(entries.len(), 5);
+
+        let paths = relative_paths(root, walk(root).follow_symlinks(true))?;
+        assert!(paths.contains(&PathBuf::from("link/b/file.txt")));
+
+        // 指向祖先目录的链接形成环
+        symlink("..", root.join("a/b/parent"))?;
+        let result = walk(root.join("a"))
+            .follow_symlinks(true)
+            .collect::<crate::Result<Vec<_>>>();
+        assert!(result.is_err(), "Cycle should be detected");
+        if let Err(e) = result {
+            assert_eq!(e.raw_os_error(), Some(libc::ELOOP), "Error should be ELOOP");
+        }
+
+        // 不跟随时没有环
+        assert_eq!(walk(root.join("a")).count(), 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_walk_replaced_dir() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let root = dir.path().join("root");
+        let outside = dir.path().join("outside");
+        create_dir_all(root.join("a"))?;
+        create_dir_all(&outside)?;
+        write(outside.join("secret.txt"), b"content")?;
+
+        // 输出目录a之后、进入它之前，把a换成指向树外的符号链接
+        let mut iter = walk(&root);
+        assert_eq!(iter.next().unwrap()?.depth(), 0);
+        assert_eq!(iter.next().unwrap()?.file_name(), "a");
+        crate::remove_dir(root.join("a"))?;
+        symlink(&outside, root.join("a"))?;
+
+        let result = iter.next().expect("Replaced directory should be reported");
+        assert!(result.is_err(), "Symlink should not be followed");
+        assert!(iter.all(|e| e.is_ok_and(|e| e.file_name() != "secret.txt")));
+
+        // 换成另一个目录时(dev, ino)不一致
+        crate::remove_file(root.join("a"))?;
+        create_dir_all(root.join("a"))?;
+        let mut iter = walk(&root);
+        iter.next().unwrap()?;
+        iter.next().unwrap()?;
+        crate::remove_dir(root.join("a"))?;
+        crate::rename(&outside, root.join("a"))?;
+        let result = iter.next().expect("Replaced directory should be reported");
+        assert!(result.is_err(), "Different directory should be detected");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_walk_missing_root() {
+        let mut iter = walk("/nonexistent/path/for/walk");
+        let result = iter.next().expect("Root error should be reported");
+        assert!(result.is_err(), "Missing root should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::NotFound,
+                "Error should be NotFound"
+            );
+        }
+        assert!(iter.next().is_none());
+    }
+}
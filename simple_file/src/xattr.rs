@@ -0,0 +1,394 @@
+/*
+    扩展属性(xattr)，附加在inode上的name -> value键值对，Linux特有
+
+    fgetxattr(fd: i32, name: *const c_char, value: *mut c_void, size: usize) -> isize
+    fsetxattr(fd: i32, name: *const c_char, value: *const c_void, size: usize, flags: i32) -> i32
+    flistxattr(fd: i32, list: *mut c_char, size: usize) -> isize
+    fremovexattr(fd: i32, name: *const c_char) -> i32
+    另有以路径为参数的getxattr/setxattr/listxattr/removexattr，跟随符号链接
+    以及不跟随符号链接、作用于链接本身的lgetxattr/lsetxattr/llistxattr/lremovexattr
+
+    name必须带命名空间前缀：user.*任何有写权限的用户都可以设置，security.*由SELinux等安全模块使用，
+    trusted.*需要CAP_SYS_ADMIN，system.*由内核解释(例如system.posix_acl_access)
+    文件系统不支持时返回ENOTSUP，属性不存在时返回ENODATA
+
+    名字与文件名一样只是字节序列，不要求是UTF-8，所以使用OsStr/OsString
+    value的长度事先未知，先以size 0调用得到长度，分配后再读取
+    两次调用之间值可能被其他进程改大，此时返回ERANGE，重新查询长度
+*/
+
+use std::ffi::{CString, OsStr, OsString};
+use std::io;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::Path;
+
+use crate::{Error, File, Result, error, to_c_path};
+
+impl File {
+    /// 读取扩展属性，属性不存在时返回None
+    pub fn get_xattr<N: AsRef<OsStr>>(&self, name: N) -> Result<Option<Vec<u8>>> {
+        self.ensure_open()?;
+        let name = to_c_name(name.as_ref())?;
+
+        let result = read_sized(|buf, size| unsafe {
+            libc::fgetxattr(self.fd, name.as_ptr(), buf.cast(), size)
+        });
+        missing_as_none(result).map_err(|e| self.context_error(e, "fgetxattr"))
+    }
+
+    /// 设置扩展属性，已存在时覆盖
+    pub fn set_xattr<N: AsRef<OsStr>>(&mut self, name: N, value: &[u8]) -> Result<()> {
+        self.ensure_open()?;
+        let name = to_c_name(name.as_ref())?;
+
+        let result = unsafe {
+            libc::fsetxattr(
+                self.fd,
+                name.as_ptr(),
+                value.as_ptr().cast(),
+                value.len(),
+                0,
+            )
+        };
+        if result < 0 {
+            return Err(self.record_os_error("fsetxattr"));
+        }
+
+        Ok(())
+    }
+
+    /// 列出全部扩展属性的名字，只包括调用者有权限看到的命名空间(普通用户看不到trusted.*)
+    pub fn list_xattrs(&self) -> Result<Vec<OsString>> {
+        self.ensure_open()?;
+
+        let list = read_sized(|buf, size| unsafe { libc::flistxattr(self.fd, buf.cast(), size) })
+            .map_err(|e| self.context_error(e, "flistxattr"))?;
+        Ok(split_names(&list))
+    }
+
+    /// 删除扩展属性，不存在时返回ENODATA
+    pub fn remove_xattr<N: AsRef<OsStr>>(&mut self, name: N) -> Result<()> {
+        self.ensure_open()?;
+        let name = to_c_name(name.as_ref())?;
+
+        let result = unsafe { libc::fremovexattr(self.fd, name.as_ptr()) };
+        if result < 0 {
+            return Err(self.record_os_error("fremovexattr"));
+        }
+
+        Ok(())
+    }
+}
+
+/// 读取path的扩展属性，跟随符号链接，属性不存在时返回None
+pub fn get_xattr<P: AsRef<Path>, N: AsRef<OsStr>>(path: P, name: N) -> Result<Option<Vec<u8>>> {
+    path_get_xattr(path.as_ref(), name.as_ref(), true)
+}
+
+/// 与get_xattr相同，但path是符号链接时读取链接本身的扩展属性
+pub fn lget_xattr<P: AsRef<Path>, N: AsRef<OsStr>>(path: P, name: N) -> Result<Option<Vec<u8>>> {
+    path_get_xattr(path.as_ref(), name.as_ref(), false)
+}
+
+/// 设置path的扩展属性，已存在时覆盖
+pub fn set_xattr<P: AsRef<Path>, N: AsRef<OsStr>>(path: P, name: N, value: &[u8]) -> Result<()> {
+    path_set_xattr(path.as_ref(), name.as_ref(), value, true)
+}
+
+/// 与set_xattr相同，但设置符号链接本身的扩展属性；Linux不允许在符号链接上设置user.*，返回EPERM
+pub fn lset_xattr<P: AsRef<Path>, N: AsRef<OsStr>>(path: P, name: N, value: &[u8]) -> Result<()> {
+    path_set_xattr(path.as_ref(), name.as_ref(), value, false)
+}
+
+/// 列出path的全部扩展属性的名字
+pub fn list_xattrs<P: AsRef<Path>>(path: P) -> Result<Vec<OsString>> {
+    path_list_xattrs(path.as_ref(), true)
+}
+
+/// 列出符号链接本身的全部扩展属性的名字
+pub fn llist_xattrs<P: AsRef<Path>>(path: P) -> Result<Vec<OsString>> {
+    path_list_xattrs(path.as_ref(), false)
+}
+
+/// 删除path的扩展属性，不存在时返回ENODATA
+pub fn remove_xattr<P: AsRef<Path>, N: AsRef<OsStr>>(path: P, name: N) -> Result<()> {
+    path_remove_xattr(path.as_ref(), name.as_ref(), true)
+}
+
+/// 删除符号链接本身的扩展属性
+pub fn lremove_xattr<P: AsRef<Path>, N: AsRef<OsStr>>(path: P, name: N) -> Result<()> {
+    path_remove_xattr(path.as_ref(), name.as_ref(), false)
+}
+
+// 以下基于路径的实现中，follow为false时使用l*xattr，不跟随最后一级的符号链接
+
+fn path_get_xattr(path: &Path, name: &OsStr, follow: bool) -> Result<Option<Vec<u8>>> {
+    let c_style_str_path = to_c_path(path)?;
+    let name = to_c_name(name)?;
+    let (getxattr, op) = if follow {
+        (libc::getxattr as GetXattr, "getxattr")
+    } else {
+        (libc::lgetxattr as GetXattr, "lgetxattr")
+    };
+
+    let result = read_sized(|buf, size| unsafe {
+        getxattr(c_style_str_path.as_ptr(), name.as_ptr(), buf.cast(), size)
+    });
+    missing_as_none(result).map_err(|e| error::with_context(e, op, Some(path)))
+}
+
+fn path_set_xattr(path: &Path, name: &OsStr, value: &[u8], follow: bool) -> Result<()> {
+    let c_style_str_path = to_c_path(path)?;
+    let name = to_c_name(name)?;
+    let (setxattr, op) = if follow {
+        (libc::setxattr as SetXattr, "setxattr")
+    } else {
+        (libc::lsetxattr as SetXattr, "lsetxattr")
+    };
+
+    let result = unsafe {
+        setxattr(
+            c_style_str_path.as_ptr(),
+            name.as_ptr(),
+            value.as_ptr().cast(),
+            value.len(),
+            0,
+        )
+    };
+    if result < 0 {
+        return Err(error::with_context(
+            io::Error::last_os_error(),
+            op,
+            Some(path),
+        ));
+    }
+
+    Ok(())
+}
+
+fn path_list_xattrs(path: &Path, follow: bool) -> Result<Vec<OsString>> {
+    let c_style_str_path = to_c_path(path)?;
+    let (listxattr, op) = if follow {
+        (libc::listxattr as ListXattr, "listxattr")
+    } else {
+        (libc::llistxattr as ListXattr, "llistxattr")
+    };
+
+    let list =
+        read_sized(|buf, size| unsafe { listxattr(c_style_str_path.as_ptr(), buf.cast(), size) })
+            .map_err(|e| error::with_context(e, op, Some(path)))?;
+    Ok(split_names(&list))
+}
+
+fn path_remove_xattr(path: &Path, name: &OsStr, follow: bool) -> Result<()> {
+    let c_style_str_path = to_c_path(path)?;
+    let name = to_c_name(name)?;
+    let (removexattr, op) = if follow {
+        (libc::removexattr as RemoveXattr, "removexattr")
+    } else {
+        (libc::lremovexattr as RemoveXattr, "lremovexattr")
+    };
+
+    let result = unsafe { removexattr(c_style_str_path.as_ptr(), name.as_ptr()) };
+    if result < 0 {
+        return Err(error::with_context(
+            io::Error::last_os_error(),
+            op,
+            Some(path),
+        ));
+    }
+
+    Ok(())
+}
+
+type GetXattr = unsafe extern "C" fn(
+    *const libc::c_char,
+    *const libc::c_char,
+    *mut libc::c_void,
+    usize,
+) -> isize;
+type SetXattr = unsafe extern "C" fn(
+    *const libc::c_char,
+    *const libc::c_char,
+    *const libc::c_void,
+    usize,
+    i32,
+) -> i32;
+type ListXattr = unsafe extern "C" fn(*const libc::c_char, *mut libc::c_char, usize) -> isize;
+type RemoveXattr = unsafe extern "C" fn(*const libc::c_char, *const libc::c_char) -> i32;
+
+// 名字不要求是UTF-8，按字节传给内核
+fn to_c_name(name: &OsStr) -> Result<CString> {
+    if name.is_empty() {
+        return Err(Error::new(
+            io::ErrorKind::InvalidInput,
+            "Invalid xattr name, empty not allowed",
+        ));
+    }
+
+    Ok(CString::new(name.as_bytes())?)
+}
+
+/*
+    先查询长度再读取，长度为0时不需要第二次调用
+    ERANGE说明两次调用之间值变长了，重新查询；返回值超过缓冲区长度同样视为值已变化，重新查询
+*/
+fn read_sized(mut f: impl FnMut(*mut u8, usize) -> isize) -> io::Result<Vec<u8>> {
+    loop {
+        let len = f(std::ptr::null_mut(), 0);
+        if len < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if len == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut buf = vec![0u8; len as usize];
+        let n = f(buf.as_mut_ptr(), buf.len());
+        if n >= 0 {
+            if n as usize > buf.len() {
+                continue;
+            }
+            buf.truncate(n as usize);
+            return Ok(buf);
+        }
+
+        let err = io::Error::last_os_error();
+        if err.raw_os_error() != Some(libc::ERANGE) {
+            return Err(err);
+        }
+    }
+}
+
+fn missing_as_none(result: io::Result<Vec<u8>>) -> io::Result<Option<Vec<u8>>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if e.raw_os_error() == Some(libc::ENODATA) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+// 名字列表是连续的以'\0'结尾的字符串，名字按原始字节返回
+fn split_names(list: &[u8]) -> Vec<OsString> {
+    list.split(|&b| b == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| OsString::from_vec(name.to_vec()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        get_xattr, lget_xattr, list_xattrs, llist_xattrs, lremove_xattr, lset_xattr, remove_xattr,
+        set_xattr,
+    };
+    use crate::temp::TempDir;
+    use crate::{File, OpenMode, symlink, write};
+    use std::ffi::{OsStr, OsString};
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+
+    #[test]
+    fn test_file_xattr() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("file.txt");
+        write(&path, b"content")?;
+        let mut file = File::open(&path, OpenMode::ReadWrite)?;
+
+        // 文件系统不支持user.*时跳过
+        match file.set_xattr("user.tag", b"blue") {
+            Err(e) if e.raw_os_error() == Some(libc::ENOTSUP) => return Ok(()),
+            result => result?,
+        }
+        assert_eq!(file.get_xattr("user.tag")?, Some(b"blue".to_vec()));
+        assert_eq!(file.get_xattr("user.missing")?, None);
+
+        // 覆盖为更长的值
+        let long = vec![b'x'; 1000];
+        file.set_xattr("user.tag", &long)?;
+        file.set_xattr("user.empty", b"")?;
+        assert_eq!(file.get_xattr("user.tag")?, Some(long));
+        assert_eq!(file.get_xattr("user.empty")?, Some(Vec::new()));
+
+        let mut names = file.list_xattrs()?;
+        names.retain(|name| name.as_bytes().starts_with(b"user."));
+        names.sort();
+        assert_eq!(names, ["user.empty", "user.tag"]);
+
+        file.remove_xattr("user.empty")?;
+        assert_eq!(file.get_xattr("user.empty")?, None);
+        let result = file.remove_xattr("user.empty");
+        assert!(result.is_err(), "Removing missing xattr should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.raw_os_error(),
+                Some(libc::ENODATA),
+                "Error should be ENODATA"
+            );
+        }
+
+        assert!(file.get_xattr("").is_err(), "Empty name should fail");
+
+        // 名字不要求是UTF-8
+        let name = OsStr::from_bytes(b"user.\xff");
+        file.set_xattr(name, b"raw")?;
+        assert_eq!(file.get_xattr(name)?, Some(b"raw".to_vec()));
+        assert!(file.list_xattrs()?.iter().any(|n| n == name));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_path_xattr() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("file.txt");
+        write(&path, b"content")?;
+
+        match set_xattr(&path, "user.origin", b"https://example.com") {
+            Err(e) if e.raw_os_error() == Some(libc::ENOTSUP) => return Ok(()),
+            result => result?,
+        }
+        assert_eq!(
+            get_xattr(&path, "user.origin")?,
+            Some(b"https://example.com".to_vec())
+        );
+        assert!(list_xattrs(&path)?.contains(&OsString::from("user.origin")));
+
+        // 通过fd也能看到
+        let file = File::open(&path, OpenMode::Read)?;
+        assert_eq!(
+            file.get_xattr("user.origin")?,
+            Some(b"https://example.com".to_vec())
+        );
+
+        // l*xattr作用于符号链接本身，看不到目标文件的属性
+        let link = dir.path().join("link");
+        symlink(&path, &link)?;
+        assert!(get_xattr(&link, "user.origin")?.is_some());
+        assert_eq!(lget_xattr(&link, "user.origin")?, None);
+        assert!(!llist_xattrs(&link)?.contains(&OsString::from("user.origin")));
+        let result = lset_xattr(&link, "user.origin", b"link");
+        assert!(result.is_err(), "user.* is not allowed on symlinks");
+        let result = lremove_xattr(&link, "user.origin");
+        assert!(result.is_err(), "Link has no such xattr");
+        assert!(
+            get_xattr(&path, "user.origin")?.is_some(),
+            "Target is untouched"
+        );
+
+        remove_xattr(&path, "user.origin")?;
+        assert_eq!(get_xattr(&path, "user.origin")?, None);
+
+        let result = get_xattr(dir.path().join("missing"), "user.origin");
+        assert!(result.is_err(), "Missing path should fail");
+        if let Err(e) = result {
+            assert_eq!(
+                e.kind(),
+                io::ErrorKind::NotFound,
+                "Error should be NotFound"
+            );
+        }
+
+        Ok(())
+    }
+}